@@ -1,7 +1,17 @@
 //! Server configuration structure.
 
+use blockchain::network::NetworkConfig;
+
 pub struct ServerConfig {
 	pub local_only: bool,
 	pub protected: bool,
-	pub entrypoint: Option<String>
+	pub entrypoint: Option<String>,
+	pub entrypoint_identity: Option<String>,
+	pub network: NetworkConfig,
+	pub mining_threads: usize,
+	pub peer_timeout: u64,
+	pub peer_eviction: u64,
+	pub tls: bool,
+	pub tls_cert: Option<String>,
+	pub tls_key: Option<String>
 }
\ No newline at end of file
@@ -3,11 +3,27 @@
 use iron::prelude::*;
 use persistence::prelude::*;
 
+use sec::aes;
+use sec::rsa::Rsa;
+use sec::sha::sha512;
+use sec::hex::ToHex;
+
+use serde_json::Value;
+
 use blockchain::peer::*;
 use blockchain::network::*;
 
 use blockchain::identity::*;
+use blockchain::identity::identity_cli::{get_identity_key, remember_author_key};
 use blockchain::block::*;
+use blockchain::job::{Job, JobEntity, JobRepository};
+use blockchain::document::{EncryptedDocumentDto, schema_violations};
+use blockchain::file::FileDto;
+use blockchain::receipt::Receipt;
+use blockchain::chain::chain_cli;
+
+use api::middleware::network::NetworkExtractor;
+use api::middleware::peer_auth::PeerAuthExtractor;
 
 pub fn preflight(_: &mut Request) -> IronResult<Response> {
     let mut res = Response::with((::iron::status::Ok, ""));
@@ -22,40 +38,100 @@ pub fn preflight(_: &mut Request) -> IronResult<Response> {
     Ok(res)
 }
 
-/// Store the provided `Request` body in a new `Block` inside the Locksidian blockchain.
+/// Queue the provided `Request` body in order to store it in a new `Block` inside the Locksidian
+/// blockchain. The block is mined in the background by the job worker of the node.
 ///
-/// The generated block hash is then returned to the client:
+/// When the network canonicalises the documents, the body must be a valid JSON document and is
+/// queued in its canonical form. When the network restricts the documents to a set of JSON Schemas,
+/// a document matching none of them is rejected with a `422 Unprocessable Entity` status:
 ///
 /// ```json
 /// {
-///     "block": "{hash}"
+///     "error": "The document does not match any of the network schemas",
+///     "violations": [{"schema": "{schema name}", "path": "{JSON Pointer}", "message": "{violation}"}]
+/// }
+/// ```
+///
+/// The `recipients` query parameter encrypts the document for a comma-separated list of identities
+/// (e.g. `/blocks?recipients={identity},{identity}`), whose public keys must be known to the node.
+/// The block then stores the encrypted envelope of the document, that only the recipients are able
+/// to decrypt using the `GET /blocks/:hash/decrypt` endpoint of their own node.
+///
+/// The record of a certified file cannot be submitted to this endpoint (`400 Bad Request`): it is
+/// created by the `POST /files` endpoint from the uploaded bytes, which determine its size.
+///
+/// The `supersedes` query parameter declares the document as a revision of the document identified
+/// by the given checksum, which must have been stored by the node identity and not superseded yet
+/// (otherwise a `400 Bad Request` status is returned). A revision is always stored in its own block.
+///
+/// The job identifier is immediately returned to the client, along with a `202 Accepted` status.
+/// Use the `GET /jobs/:id` endpoint to follow the job status and get the generated block hash:
+///
+/// ```json
+/// {
+///     "job": "{id}"
 /// }
 /// ```
 pub fn store_document(req: &mut Request) -> IronResult<Response> {
+    let recipients = query_param!(req, "recipients");
+    let supersedes = query_param!(req, "supersedes");
+
     match body_raw!(req) {
         Ok(Some(body)) => {
             let connection = req.get_connection()?;
+            let config = req.get_network_config()?;
             let identity = get_active_identity(&*connection)?;
+
+            // Canonicalise the document first, so that duplicates are detected on canonical content
+            let body = match config.canonicalization.apply(body.as_ref()) {
+                Ok(body) => body,
+                Err(err) => return http_response!(BadRequest, {"error": err.description()})
+            };
+
+            // The size of a file record must be the one of the bytes received by `POST /files`
+            if FileDto::parse(body.as_ref()).is_some() {
+                return http_response!(BadRequest, {"error": "File records can only be created by uploading the file to POST /files"});
+            }
+
+            let violations = schema_violations(body.as_ref(), &config.schemas);
+            if !violations.is_empty() {
+                return http_response!(UnprocessableEntity, {
+                    "error": "The document does not match any of the network schemas",
+                    "violations": violations
+                });
+            }
+
             let repository = BlockRepository::new(&*connection);
-    
-            match Block::new(body, &identity, &repository) {
-                Ok(block) => {
-                    let entity = BlockEntity::new(&block);
-            
-                    match repository.save_head(&entity) {
-                        Ok(1) => {
-	                        let peer_repository = PeerRepository::new(&*connection);
-                            propagate_block(&block, &peer_repository, &*connection)?;
-	                        
-                            http_response!(Ok, {"block": block.hash()})
-                        },
-                        Ok(_) => http_response!(InternalServerError, {
-                                    "warning": "An unexpected number of rows were inserted in the registry"
-                                }),
-                        Err(err) => http_response!(InternalServerError, {"error": err.description()})
-                    }
+            match Block::assert_document_uniqueness(sha512(body.as_bytes()).as_ref(), &repository) {
+                Ok(_) => (),
+                Err(err) => return http_response!(Conflict, {"error": err.description()})
+            };
+
+            if let Some(ref supersedes) = supersedes {
+                match Block::check_supersession(supersedes.as_str(), identity.hash().as_str(), &repository) {
+                    Ok(_) => (),
+                    Err(err) => return http_response!(BadRequest, {"error": err.description()})
+                };
+            }
+
+            let job = match (recipients, supersedes) {
+                (Some(recipients), supersedes) => match seal_document(body.as_ref(), recipients.as_ref(), &config, &*connection) {
+                    Ok(envelope) => Job::encrypt_document(envelope, String::from(aes::CIPHER), supersedes),
+                    Err(err) => return http_response!(BadRequest, {"error": err.description()})
+                },
+                (None, Some(supersedes)) => Job::supersede_document(body, supersedes),
+                (None, None) => Job::new(body)
+            };
+
+            match job {
+                Ok(job) => match JobRepository::new(&*connection).save(&JobEntity::new(&job)) {
+                    Ok(1) => http_response!(Accepted, {"job": job.id()}),
+                    Ok(_) => http_response!(InternalServerError, {
+                                "warning": "An unexpected number of rows were inserted in the registry"
+                            }),
+                    Err(err) => http_response!(InternalServerError, {"error": err.description()})
                 },
-                Err(err) => http_response!(Conflict, {"error": err.description()})
+                Err(err) => http_response!(InternalServerError, {"error": err.description()})
             }
         },
         Ok(None) => http_response!(BadRequest, {"error": "Request body cannot be null"}),
@@ -63,21 +139,57 @@ pub fn store_document(req: &mut Request) -> IronResult<Response> {
     }
 }
 
-/// Return the hash of the current blockchain `HEAD` block:
+/// Return the hash of the current blockchain `HEAD` block, along with a page of the main chain
+/// blocks ordered by height:
 ///
 /// ```json
 /// {
-///     "head": "{hash}"
+///     "head": "{hash}",
+///     "blocks": [{block, as returned by GET /blocks/:hash}],
+///     "next_height": {height of the first block of the next page, null on the last page}
 /// }
 /// ```
-pub fn show_head(req: &mut Request) -> IronResult<Response> {
+///
+/// The page starts at the `from_height` query parameter (`0` by default) and holds at most `limit`
+/// blocks (100 by default, 1000 at most). The `since` and `until` query parameters only select the
+/// blocks whose timestamp is in this interval.
+pub fn list_blocks(req: &mut Request) -> IronResult<Response> {
+    let (from_height, since, until, limit) = match page_params(req) {
+        Ok(params) => params,
+        Err(err) => return http_response!(BadRequest, {"error": err.description()})
+    };
+
     let connection = req.get_connection()?;
     let repository = BlockRepository::new(&*connection);
 
-    match repository.get_head() {
-        Some(head) => http_response!(Ok, {"head": head.hash}),
-        None => http_response!(NoContent, {})
+    let head = match repository.get_head() {
+        Some(head) => head,
+        None => return http_response!(NoContent, {})
+    };
+
+    let entities = match repository.get_page(from_height, since, until, limit) {
+        Some(entities) => entities,
+        None => return http_response!(InternalServerError, {"error": "Unable to load the blocks registry"})
+    };
+
+    let next_height = match entities.last() {
+        Some(last) if entities.len() as i64 == limit => Some(last.height + 1),
+        _ => None
+    };
+
+    let mut blocks = Vec::new();
+    for entity in entities {
+        match entity_to_dto(entity, &*connection) {
+            Ok(dto) => blocks.push(dto),
+            Err(err) => return http_response!(InternalServerError, {"error": err.description()})
+        }
     }
+
+    http_response!(Ok, {
+        "head": head.hash,
+        "blocks": blocks,
+        "next_height": next_height
+    })
 }
 
 /// Get all the `Block` data of the block identitfied by the provided `hash`.
@@ -87,35 +199,201 @@ pub fn get_block(req: &mut Request) -> IronResult<Response> {
             let connection = req.get_connection()?;
             let repository = BlockRepository::new(&*connection);
 
-            match repository.get(&String::from(hash)) {
+            block_response(repository.get(&String::from(hash)), &*connection)
+        },
+        None => http_response!(BadRequest, {"error": "Hash parameter cannot be empty"})
+    }
+}
+
+/// Get all the `Block` data of the current blockchain `HEAD` block.
+pub fn get_head(req: &mut Request) -> IronResult<Response> {
+    let connection = req.get_connection()?;
+    let repository = BlockRepository::new(&*connection);
+
+    block_response(repository.get_head(), &*connection)
+}
+
+/// Get all the `Block` data of the main chain block located at the provided `height`.
+pub fn get_block_at_height(req: &mut Request) -> IronResult<Response> {
+    let height = match route_param!(req, "height").map(|height| height.parse::<i32>()) {
+        Some(Ok(height)) if height >= 0 => height,
+        _ => return http_response!(BadRequest, {"error": "Height parameter should be a positive integer"})
+    };
+
+    let connection = req.get_connection()?;
+    let repository = BlockRepository::new(&*connection);
+
+    block_response(repository.get_by_height(height), &*connection)
+}
+
+/// Issue a proof-of-existence receipt of the main chain `Block` identified by the provided `hash`,
+/// signed by the node identity. It can be verified offline using `locksidian --verify-receipt`.
+///
+/// The `document` query parameter selects the checksum of the proven document, and is required for
+/// a batch block (a `400 Bad Request` status is returned if it is missing or not stored in the
+/// block). A `404 Not Found` status is returned if the block is unknown, and a `409 Conflict` status
+/// if it is not part of the main chain:
+///
+/// ```json
+/// {
+///     "receipt": {
+///         "version": 1,
+///         "consensus": "proof-of-work",
+///         "block": {block header},
+///         "author_key": "{author public key}",
+///         "document": {document inclusion proof},
+///         "headers": [{headers of the following blocks, up to the HEAD block}],
+///         "issuer": "{node identity}",
+///         "issuer_key": "{node public key}",
+///         "issued_at": {timestamp}
+///     },
+///     "signature": "{signature of the receipt by the node identity}"
+/// }
+/// ```
+pub fn get_receipt(req: &mut Request) -> IronResult<Response> {
+    let document = query_param!(req, "document");
+
+    match route_param!(req, "hash") {
+        Some(hash) => {
+            let connection = req.get_connection()?;
+            let config = req.get_network_config()?;
+            let identity = get_active_identity(&*connection)?;
+            let repository = BlockRepository::new(&*connection);
+
+            let entity = match repository.get(&String::from(hash)) {
+                Some(entity) => entity,
+                None => return http_response!(NotFound, {"error": "Unknown block"})
+            };
+
+            if entity.orphan {
+                return http_response!(Conflict, {"error": "The block is not part of the main chain"});
+            }
+
+            match document {
+                Some(ref data_hash) => match repository.locate_document(data_hash.as_ref()) {
+                    Some((ref located, _)) if located.hash == entity.hash => (),
+                    _ => return http_response!(BadRequest, {"error": "The document is not stored in this block"})
+                },
+                None if !entity.merkle_root.is_empty() => return http_response!(BadRequest, {
+                    "error": "The document query parameter is required for a batch block"
+                }),
+                None => ()
+            };
+
+            match Receipt::issue(hash, document, &identity, &config, &*connection) {
+                Ok(Some(receipt)) => {
+                    let dto = receipt.to_dto();
+                    http_response!(Ok, dto)
+                },
+                Ok(None) => http_response!(NotFound, {"error": "Unknown block"}),
+                Err(err) => http_response!(InternalServerError, {"error": err.description()})
+            }
+        },
+        None => http_response!(BadRequest, {"error": "Hash parameter cannot be empty"})
+    }
+}
+
+/// Decrypt the documents of the `Block` identified by the provided `hash` that were encrypted for
+/// the node identity. The request must be signed by the node identity:
+///
+/// ```json
+/// {
+///     "block": "{hash}",
+///     "documents": [{"position": {position in the block}, "data_hash": "{envelope checksum}", "document": "{plaintext}"}]
+/// }
+/// ```
+///
+/// A `403 Forbidden` status is returned if no document of the block was encrypted for the node, and
+/// a `409 Conflict` status if one of its documents cannot be decrypted (whatever the cause).
+pub fn decrypt_block(req: &mut Request) -> IronResult<Response> {
+    match route_param!(req, "hash") {
+        Some(hash) => {
+            let connection = req.get_connection()?;
+            let identity = get_active_identity(&*connection)?;
+            let repository = BlockRepository::new(&*connection);
+
+            let block = match repository.get(&String::from(hash)) {
                 Some(entity) => match Block::from_entity(entity) {
-                    Ok(block) => {
-                        let dto = BlockDto::new(&block);
-                        http_response!(Ok, dto)
-                    },
-                    Err(err) => http_response!(InternalServerError, {"error": err.description()})
+                    Ok(block) => block,
+                    Err(err) => return http_response!(InternalServerError, {"error": err.description()})
                 },
-                None => http_response!(NoContent, {})
+                None => return http_response!(NoContent, {})
+            };
+
+            match decrypt_documents(&block, &identity) {
+                Ok(documents) => match documents.is_empty() {
+                    true => http_response!(Forbidden, {"error": "No document of this block was encrypted for the node identity"}),
+                    false => http_response!(Ok, {"block": block.hash(), "documents": documents})
+                },
+                Err(err) => {
+                    // The cause is not disclosed, so that the endpoint cannot be used as a decryption oracle
+                    warn!("Unable to decrypt block {}: {}", block.hash(), err.description());
+                    http_response!(Conflict, {"error": "Unable to decrypt the documents of this block"})
+                }
             }
         },
         None => http_response!(BadRequest, {"error": "Hash parameter cannot be empty"})
     }
 }
 
-/// Create a local copy of the `Block` if its structure is valid.
+/// Receive the inventory of the blocks announced by a peer, and answer with the hashes of the ones
+/// missing from the registry, which the peer then replicates using `PUT /blocks`. The announced
+/// blocks are recorded as known by the signer of the request, whatever the `sender` field says:
+///
+/// ```json
+/// {
+///     "wanted": ["{hash}"]
+/// }
+/// ```
+pub fn receive_inventory(req: &mut Request) -> IronResult<Response> {
+    let sender = req.get_signer()?;
+    let inventory = match body!(req, InventoryDto) {
+        Ok(Some(dto)) => dto,
+        Ok(None) => return http_response!(BadRequest, {"error": "No content"}),
+        Err(err) => return http_response!(BadRequest, {"error": err.description()})
+    };
+
+    if inventory.hashes.len() > MAX_INVENTORY_SIZE {
+        return http_response!(BadRequest, {"error": format!("An inventory cannot announce more than {} blocks", MAX_INVENTORY_SIZE)});
+    }
+
+    let connection = req.get_connection()?;
+    let block_repository = BlockRepository::new(&*connection);
+    let inventory_repository = InventoryRepository::new(&*connection);
+    peer_cli::record_received(sender.as_ref(), &PeerRepository::new(&*connection)).unwrap_or(());
+
+    let mut wanted = WantedDto { wanted: Vec::new() };
+    for hash in inventory.hashes.iter() {
+        inventory_repository.record(hash.as_ref(), sender.as_ref()).unwrap_or(());
+
+        if block_repository.get(hash).is_none() && !wanted.wanted.contains(hash) {
+            wanted.wanted.push(hash.clone());
+        }
+    }
+
+    http_response!(Ok, wanted)
+}
+
+/// Create a local copy of the `Block` if its structure is valid, then gossip it to our peers. The
+/// block is recorded as received from the signer of the request.
 pub fn replicate_block(req: &mut Request) -> IronResult<Response> {
     let connection = req.get_connection()?;
+    let config = req.get_network_config()?;
     let block_repository = BlockRepository::new(&*connection);
 	let peer_repository = PeerRepository::new(&*connection);
     
-    let mut block = body_to_block(req, &block_repository)?;
-    let should_sync = save_replicated_block(&mut block, &block_repository)?;
-    propagate_block(&block, &peer_repository, &*connection)?;
+    let (mut block, author_key) = body_to_block(req, &config, &block_repository, &*connection)?;
+    peer_cli::record_received(block.received_from().as_ref(), &peer_repository).unwrap_or(());
+
+    let should_sync = save_replicated_block(&mut block, &config, &block_repository)?;
+    remember_author_key(&author_key, &*connection).unwrap_or(());
+    InventoryRepository::new(&*connection).record(block.hash().as_ref(), block.received_from().as_ref()).unwrap_or(());
+    propagate_block(&block, &author_key, &config, &peer_repository, &*connection)?;
 	
 	if should_sync {
 		match peer_repository.get(&block.received_from()) {
 			Some(entity) => match Peer::from_entity(&entity) {
-				Ok(peer) => HttpClient::from_peer(&peer).sync(Some(block.previous()), &block_repository).unwrap_or(()),
+				Ok(peer) => HttpClient::from_peer(&peer).sync(Some(block.previous()), &config, &*connection).unwrap_or(()),
 				Err(_) => ()
 			},
 			None => ()
@@ -125,40 +403,42 @@ pub fn replicate_block(req: &mut Request) -> IronResult<Response> {
     http_response!(Ok, {})
 }
 
-/// Propagate a `Block` to all of our `Peer`s.
-fn propagate_block(block: &Block, repository: &PeerRepository, connection: &SqliteConnection) -> IronResult<()> {
+/// Discard every block of the registry that is not part of the main chain.
+///
+/// Use the `dry_run=true` query parameter in order to list the blocks that would be removed
+/// without altering the registry.
+pub fn prune(req: &mut Request) -> IronResult<Response> {
+    let dry_run = query_param!(req, "dry_run").map(|value| value == "true").unwrap_or(false);
+    let connection = req.get_connection()?;
+
+    match chain_cli::prune(&*connection, dry_run) {
+        Ok(report) => http_response!(Ok, report),
+        Err(err) => http_response!(InternalServerError, {"error": err.description()})
+    }
+}
+
+/// Gossip a `Block` to our active `Peer`s, along with the public key of its author.
+fn propagate_block(block: &Block, author_key: &Rsa, config: &NetworkConfig, repository: &PeerRepository, connection: &SqliteConnection) -> IronResult<()> {
     let identity = get_active_identity(&*connection)?;
+    let author_key = match key_to_hex(&author_key) {
+        Ok(key) => key,
+        Err(err) => return http_error!(InternalServerError, {"error": err.description()})
+    };
     
-    match repository.get_all() {
-        Some(entities) => {
-            let peers: Vec<Peer> = entities.iter()
-                .map(|entity| Peer::from_entity(entity))
-                .filter(|peer| peer.is_ok())
-                .map(|peer| peer.unwrap())
-                .collect();
-            
-            match HttpClient::propagate(&block, &identity, peers) {
-                Ok(_) => Ok(()),
-                Err(_) => Ok(())
-            }
-        },
-        None => http_error!(InternalServerError, {"error": "No peer could be found to propagate this block"})
+    // The peers known to be down are skipped, instead of waiting for their connection to time out
+    let peers = peer_cli::active_peers(&repository);
+    
+    match HttpClient::propagate(&block, &identity, author_key.as_ref(), peers, config.gossip_fanout as usize, &connection) {
+        Ok(_) => Ok(()),
+        Err(_) => Ok(())
     }
 }
 
-fn save_replicated_block(block: &mut Block, repository: &BlockRepository) -> IronResult<bool> {
+fn save_replicated_block(block: &mut Block, config: &NetworkConfig, repository: &BlockRepository) -> IronResult<bool> {
     let mut entity = BlockEntity::new(&block);
-	let mut should_sync = false;
-    
-    let save = match repository.get(&block.previous()) {
-        Some(mut previous) => repository.save_next(&mut entity, &mut previous),
-        None => {
-	        should_sync = true;
-	        repository.save(&entity)
-        }
-    };
+	let should_sync = !block.previous().is_empty() && repository.get(&block.previous()).is_none();
     
-    match save {
+    match repository.save_block(&mut entity, &config) {
         Ok(1) => Ok(should_sync),
         Ok(_) => http_error!(InternalServerError, {
             "warning": "An unexpected number of rows were inserted in the registry"
@@ -174,11 +454,125 @@ fn get_active_identity(connection: &SqliteConnection) -> IronResult<Identity> {
     }
 }
 
-fn body_to_block(req: &mut Request, repository: &BlockRepository) -> IronResult<Block> {
-    let dto = body_to_dto(req)?;
+/// Parse the `(from_height, since, until, limit)` pagination query parameters of the `Request`.
+fn page_params(req: &Request) -> LocksidianResult<(i32, Option<i32>, Option<i32>, i64)> {
+    let from_height = integer_param("from_height", query_param!(req, "from_height"))?;
+    let since = integer_param("since", query_param!(req, "since"))?;
+    let until = integer_param("until", query_param!(req, "until"))?;
+
+    let limit = match integer_param("limit", query_param!(req, "limit"))? {
+        Some(0) => return Err(LocksidianError::new(String::from("The limit should be greater than 0"))),
+        Some(limit) if limit as i64 > MAX_PAGE_SIZE => MAX_PAGE_SIZE,
+        Some(limit) => limit as i64,
+        None => DEFAULT_PAGE_SIZE
+    };
+
+    Ok((from_height.unwrap_or(0), since, until, limit))
+}
+
+/// Parse the optional value of a positive integer query parameter.
+fn integer_param(name: &str, value: Option<String>) -> LocksidianResult<Option<i32>> {
+    match value {
+        Some(value) => match value.parse::<i32>() {
+            Ok(integer) if integer >= 0 => Ok(Some(integer)),
+            _ => Err(LocksidianError::new(format!("Invalid {} value {}, expected a positive integer", name, value)))
+        },
+        None => Ok(None)
+    }
+}
+
+/// Respond with the `BlockDto` of the given block, or with a `204 No Content` status if the block
+/// does not exist.
+fn block_response(entity: Option<BlockEntity>, connection: &SqliteConnection) -> IronResult<Response> {
+    match entity {
+        Some(entity) => match entity_to_dto(entity, &connection) {
+            Ok(dto) => http_response!(Ok, dto),
+            Err(err) => http_response!(InternalServerError, {"error": err.description()})
+        },
+        None => http_response!(NoContent, {})
+    }
+}
+
+/// Convert a persisted block into a `BlockDto`, along with the public key of its author when it is
+/// known to the node.
+fn entity_to_dto(entity: BlockEntity, connection: &SqliteConnection) -> LocksidianResult<BlockDto> {
+    let block = Block::from_entity(entity)?;
+
+    let mut dto = BlockDto::new(&block);
+    dto.author_key = match get_identity_key(block.author().as_ref(), None, &connection) {
+        Ok(key) => key_to_hex(&key).ok(),
+        Err(_) => None
+    };
+
+    Ok(dto)
+}
+
+/// Export the provided public key as an hexadecimal PEM-encoded string.
+fn key_to_hex(key: &Rsa) -> LocksidianResult<String> {
+    let pem = key.export_public_key()?;
+    Ok(pem.to_hex())
+}
+
+/// Encrypt the `document` for the comma-separated `recipients` identities, then return the envelope
+/// to be stored in the block, canonicalised as any other document.
+fn seal_document(document: &str, recipients: &str, config: &NetworkConfig, connection: &SqliteConnection) -> LocksidianResult<String> {
+    let mut identities: Vec<&str> = recipients.split(',')
+        .map(|identity| identity.trim())
+        .filter(|identity| !identity.is_empty())
+        .collect();
+    identities.sort();
+    identities.dedup();
+
+    let mut keys = Vec::new();
+    for identity in identities {
+        keys.push((String::from(identity), get_identity_key(identity, None, &connection)?));
+    }
+
+    let envelope = EncryptedDocumentDto::seal(document, &keys)?.to_json()?;
+    config.canonicalization.apply(envelope.as_ref())
+}
+
+/// Decrypt the documents of the `Block` that were encrypted for the given `Identity`. The documents
+/// of a block that is not flagged as encrypted are never decrypted.
+fn decrypt_documents(block: &Block, identity: &Identity) -> LocksidianResult<Vec<Value>> {
+    let hashes = block.document_hashes()?;
+    let mut documents = Vec::new();
+
+    if !block.is_encrypted() {
+        return Ok(documents);
+    }
+
+    for (position, (document, data_hash)) in block.documents()?.iter().zip(hashes.iter()).enumerate() {
+        match EncryptedDocumentDto::from_envelope(document.as_ref(), block.encryption().as_ref()) {
+            Ok(ref envelope) if envelope.is_recipient(identity.hash().as_str()) => {
+                let plaintext = envelope.open(identity.hash().as_str(), identity.key())?;
+
+                documents.push(json!({
+                    "position": position,
+                    "data_hash": data_hash,
+                    "document": plaintext
+                }));
+            },
+            _ => ()
+        }
+    }
+
+    Ok(documents)
+}
+
+/// Resolve the public key of the block author, then replicate the `Block` carried by the request body.
+fn body_to_block(req: &mut Request, config: &NetworkConfig, repository: &BlockRepository, connection: &SqliteConnection) -> IronResult<(Block, Rsa)> {
+    let signer = req.get_signer()?;
+    let mut dto = body_to_dto(req)?;
+    dto.received_from = signer;
+    
+    let author_key = match get_identity_key(dto.author.as_ref(), dto.author_key.as_ref(), &connection) {
+        Ok(key) => key,
+        Err(err) => return http_error!(BadRequest, {"error": err.description()})
+    };
     
-    match Block::replicate_from(dto, &repository) {
-        Ok(block) => Ok(block),
+    match Block::replicate_from(dto, &author_key, &config, &repository) {
+        Ok(block) => Ok((block, author_key)),
         Err(err) => http_error!(BadRequest, {"error": err.description()})
     }
 }
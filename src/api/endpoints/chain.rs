@@ -0,0 +1,45 @@
+//! Chain management endpoint.
+
+use iron::prelude::*;
+use persistence::prelude::*;
+
+use blockchain::block::BlockRepository;
+use blockchain::chain::ChainTip;
+use blockchain::chain::chain_cli;
+
+use api::middleware::network::NetworkExtractor;
+
+/// Verify the whole blocks registry of the node and return a detailed report.
+///
+/// The optional JSON body `{"relink": true}` repairs the inconsistent `next` links of the main chain.
+pub fn verify(req: &mut Request) -> IronResult<Response> {
+    let relink = match body!(req) {
+        Ok(Some(json)) => json["relink"].as_bool().unwrap_or(false),
+        Ok(None) => false,
+        Err(err) => return http_response!(BadRequest, {"error": err.description()})
+    };
+
+    let connection = req.get_connection()?;
+    let config = req.get_network_config()?;
+
+    match chain_cli::verify(&*connection, &config, relink) {
+        Ok(report) => http_response!(Ok, report),
+        Err(err) => http_response!(InternalServerError, {"error": err.description()})
+    }
+}
+
+
+/// List the tip of every known branch of the registry, from the heaviest one (the `HEAD` of the
+/// main chain) to the lightest one.
+pub fn tips(req: &mut Request) -> IronResult<Response> {
+    let connection = req.get_connection()?;
+    let repository = BlockRepository::new(&*connection);
+
+    match repository.get_tips() {
+        Some(entities) => {
+            let tips: Vec<ChainTip> = entities.iter().map(|entity| ChainTip::new(entity)).collect();
+            http_response!(Ok, tips)
+        },
+        None => http_response!(InternalServerError, {"error": "Unable to load the blocks registry"})
+    }
+}
\ No newline at end of file
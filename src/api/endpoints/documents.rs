@@ -0,0 +1,216 @@
+//! Documents endpoint.
+
+use iron::prelude::*;
+use persistence::prelude::*;
+
+use blockchain::block::{Block, BlockRepository};
+use blockchain::document::{DocumentProofDto, DocumentLocationDto, DocumentHistoryDto, DocumentVerificationDto, AnchorDto};
+use blockchain::document::{DocumentQuery, FieldOperator, FieldRepository};
+use blockchain::identity::identity_cli::get_active_identity;
+use blockchain::job::{Job, JobEntity, JobRepository};
+
+use api::middleware::network::NetworkExtractor;
+
+/// Locate the document identified by the provided `data_hash`: the block storing it, its position
+/// in the block and the position of the block in the chain.
+pub fn get_document(req: &mut Request) -> IronResult<Response> {
+    match route_param!(req, "data_hash") {
+        Some(data_hash) => {
+            let connection = req.get_connection()?;
+            let repository = BlockRepository::new(&*connection);
+
+            match DocumentLocationDto::locate(data_hash, &repository) {
+                Some(location) => http_response!(Ok, location),
+                None => http_response!(NoContent, {})
+            }
+        },
+        None => http_response!(BadRequest, {"error": "Data hash parameter cannot be empty"})
+    }
+}
+
+/// Search the stored JSON documents by the value of the field located at the `path` query
+/// parameter, e.g. `GET /documents?path=$.customer.id&eq=42`.
+///
+/// The value of the field can be compared using the `eq`, `gt`, `gte`, `lt` and `lte` parameters,
+/// or `exists=false` can be used to select the documents lacking the field. The documents can also
+/// be filtered by `author` and by block timestamp (`since` and `until`), and paginated using the
+/// `limit` and `offset` parameters. The location of the matching documents is returned:
+///
+/// ```json
+/// {
+///     "documents": [{location of each document, ordered by block timestamp}]
+/// }
+/// ```
+pub fn search_documents(req: &mut Request) -> IronResult<Response> {
+    let query = match document_query(req) {
+        Ok(query) => query,
+        Err(err) => return http_response!(BadRequest, {"error": err.description()})
+    };
+
+    let connection = req.get_connection()?;
+    let repository = BlockRepository::new(&*connection);
+
+    let data_hashes = match FieldRepository::new(&*connection).search(&query) {
+        Ok(data_hashes) => data_hashes,
+        Err(err) => return http_response!(InternalServerError, {"error": err.description()})
+    };
+
+    let documents: Vec<DocumentLocationDto> = data_hashes.iter()
+        .filter_map(|data_hash| DocumentLocationDto::locate(data_hash.as_ref(), &repository))
+        .collect();
+
+    http_response!(Ok, {"documents": documents})
+}
+
+/// Build the `DocumentQuery` described by the query parameters of the `Request`.
+fn document_query(req: &Request) -> LocksidianResult<DocumentQuery> {
+    let mut query = match query_param!(req, "path") {
+        Some(path) => DocumentQuery::new(path.as_ref())?,
+        None => return Err(LocksidianError::new(String::from("The path parameter is required")))
+    };
+
+    for operator_name in ["eq", "gt", "gte", "lt", "lte"].iter() {
+        let operator_name: &str = operator_name;
+
+        if let (Some(operator), Some(operand)) = (FieldOperator::from_str(operator_name), query_param!(req, operator_name)) {
+            query = query.with_condition(operator, operand)?;
+        }
+    }
+
+    if let Some(exists) = query_param!(req, "exists") {
+        query = query.with_exists(exists.as_ref())?;
+    }
+
+    if let Some(author) = query_param!(req, "author") {
+        query = query.with_author(author);
+    }
+
+    let since = query_param!(req, "since");
+    let until = query_param!(req, "until");
+    let limit = query_param!(req, "limit");
+    let offset = query_param!(req, "offset");
+
+    query
+        .with_period(since.as_ref().map(|since| since.as_str()), until.as_ref().map(|until| until.as_str()))?
+        .with_page(limit.as_ref().map(|limit| limit.as_str()), offset.as_ref().map(|offset| offset.as_str()))
+}
+
+/// Get the revision chain of the document identified by the provided `data_hash`: the location of
+/// each of its versions, from the original one to the latest one.
+pub fn get_history(req: &mut Request) -> IronResult<Response> {
+    match route_param!(req, "data_hash") {
+        Some(data_hash) => {
+            let connection = req.get_connection()?;
+            let repository = BlockRepository::new(&*connection);
+
+            match DocumentHistoryDto::new(data_hash, &repository) {
+                Some(history) => http_response!(Ok, history),
+                None => http_response!(NoContent, {})
+            }
+        },
+        None => http_response!(BadRequest, {"error": "Data hash parameter cannot be empty"})
+    }
+}
+
+/// Hash the raw document provided in the `Request` body using SHA512, and report whether and where
+/// it was certified. A document only stored in orphan blocks is located but not certified, and can
+/// be submitted again. When the network canonicalises the documents, the checksum of the canonical
+/// form of the document is used, unless the document is anchored: the anchors are matched using
+/// the checksum of the raw document.
+pub fn verify_document(req: &mut Request) -> IronResult<Response> {
+    match body_raw!(req) {
+        Ok(Some(body)) => {
+            let connection = req.get_connection()?;
+            let config = req.get_network_config()?;
+            let repository = BlockRepository::new(&*connection);
+
+            match DocumentVerificationDto::verify(body.as_ref(), config.canonicalization, &repository) {
+                Ok(verification) => http_response!(Ok, verification),
+                Err(err) => http_response!(BadRequest, {"error": err.description()})
+            }
+        },
+        Ok(None) => http_response!(BadRequest, {"error": "Request body cannot be null"}),
+        Err(err) => http_response!(InternalServerError, {"error": err.to_string()})
+    }
+}
+
+/// Queue the hash-only anchoring of a confidential document: only its SHA512 checksum and optional
+/// JSON metadata are sent to the node, stored in a new `Block` and replicated across the network.
+///
+/// As for the `POST /blocks` endpoint, the job identifier is immediately returned to the client,
+/// along with a `202 Accepted` status:
+///
+/// ```json
+/// {
+///     "job": "{id}"
+/// }
+/// ```
+pub fn anchor_document(req: &mut Request) -> IronResult<Response> {
+    match body!(req, AnchorDto) {
+        Ok(Some(anchor)) => {
+            let connection = req.get_connection()?;
+            get_active_identity(&*connection)?;
+
+            let data_hash = anchor.data_hash.to_lowercase();
+            let metadata = match anchor.metadata {
+                Some(ref metadata) => metadata.to_string(),
+                None => String::new()
+            };
+
+            match Block::check_anchor(data_hash.as_ref(), metadata.as_ref()) {
+                Ok(_) => (),
+                Err(err) => return http_response!(BadRequest, {"error": err.description()})
+            };
+
+            let repository = BlockRepository::new(&*connection);
+            match Block::assert_document_uniqueness(data_hash.as_ref(), &repository) {
+                Ok(_) => (),
+                Err(err) => return http_response!(Conflict, {"error": err.description()})
+            };
+
+            match Job::anchor_document(data_hash, metadata) {
+                Ok(job) => match JobRepository::new(&*connection).save(&JobEntity::new(&job)) {
+                    Ok(1) => http_response!(Accepted, {"job": job.id()}),
+                    Ok(_) => http_response!(InternalServerError, {
+                                "warning": "An unexpected number of rows were inserted in the registry"
+                            }),
+                    Err(err) => http_response!(InternalServerError, {"error": err.description()})
+                },
+                Err(err) => http_response!(InternalServerError, {"error": err.description()})
+            }
+        },
+        Ok(None) => http_response!(BadRequest, {"error": "Request body cannot be null"}),
+        Err(err) => http_response!(BadRequest, {"error": err.description()})
+    }
+}
+
+/// Get the Merkle inclusion proof of the document identified by the provided `data_hash`:
+///
+/// ```json
+/// {
+///     "data_hash": "{data_hash}",
+///     "block": "{hash}",
+///     "position": {position of the document in the block},
+///     "merkle_root": "{merkle_root}",
+///     "path": [
+///         {"hash": "{sibling hash}", "side": "left|right"}
+///     ]
+/// }
+/// ```
+pub fn get_proof(req: &mut Request) -> IronResult<Response> {
+    match route_param!(req, "data_hash") {
+        Some(data_hash) => {
+            let connection = req.get_connection()?;
+            let repository = BlockRepository::new(&*connection);
+
+            match repository.locate_document(data_hash) {
+                Some((entity, position)) => match Block::from_entity(entity).and_then(|block| DocumentProofDto::new(&block, position)) {
+                    Ok(proof) => http_response!(Ok, proof),
+                    Err(err) => http_response!(InternalServerError, {"error": err.description()})
+                },
+                None => http_response!(NoContent, {})
+            }
+        },
+        None => http_response!(BadRequest, {"error": "Data hash parameter cannot be empty"})
+    }
+}
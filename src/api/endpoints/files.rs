@@ -0,0 +1,156 @@
+//! Files endpoint.
+
+use iron::prelude::*;
+use persistence::prelude::*;
+
+use sec::sha::sha512;
+
+use blockchain::block::{Block, BlockRepository};
+use blockchain::document::schema_violations;
+use blockchain::file::{FileDto, FileStore, MAX_FILE_SIZE};
+use blockchain::identity::identity_cli::get_active_identity;
+use blockchain::job::{Job, JobEntity, JobRepository};
+
+use api::middleware::body::BodyExtractor;
+use api::middleware::network::NetworkExtractor;
+use api::multipart::parse_upload;
+
+/// Certify the binary file uploaded in the `Request` body, either as an `application/octet-stream`
+/// body or as the file part of a `multipart/form-data` body. The optional `name` and `media_type`
+/// query parameters are used when the upload does not provide them.
+///
+/// The file is kept in the content-addressed store of the node, and its record is queued in order
+/// to be stored in a new `Block`. The stored file is removed if its record cannot be queued, unless
+/// it was already stored for another record. The job identifier is immediately returned to the client, along
+/// with the checksum of the record and the record itself, with a `202 Accepted` status:
+///
+/// ```json
+/// {
+///     "job": "{id}",
+///     "data_hash": "{record checksum}",
+///     "file": {"hash": "{file checksum}", "size": {size}, "media_type": "{media type}", "name": "{name}"}
+/// }
+/// ```
+pub fn store_file(req: &mut Request) -> IronResult<Response> {
+    let name = query_param!(req, "name");
+    let media_type = query_param!(req, "media_type");
+    let content_type = match req.headers.get_raw("Content-Type").and_then(|header| header.get(0)) {
+        Some(value) => String::from_utf8_lossy(value).into_owned(),
+        None => String::from("application/octet-stream")
+    };
+
+    let body = match req.take_body_bytes(MAX_FILE_SIZE + 1) {
+        Ok(body) => body,
+        Err(err) => return http_response!(InternalServerError, {"error": err.description()})
+    };
+
+    if body.len() as u64 > MAX_FILE_SIZE {
+        return http_response!(PayloadTooLarge, {"error": format!("A file cannot exceed {} bytes", MAX_FILE_SIZE)});
+    }
+
+    let upload = match parse_upload(content_type.as_str(), body) {
+        Ok(upload) => upload,
+        Err(err) => return http_response!(BadRequest, {"error": err.description()})
+    };
+
+    if upload.bytes.is_empty() {
+        return http_response!(BadRequest, {"error": "The uploaded file cannot be empty"});
+    }
+
+    let connection = req.get_connection()?;
+    let config = req.get_network_config()?;
+    get_active_identity(&*connection)?;
+
+    let media_type = upload.media_type.or(media_type).unwrap_or(String::from("application/octet-stream"));
+    let name = upload.name.or(name);
+    let file = match FileDto::new(&upload.bytes, media_type.as_str(), name.as_ref().map(|name| name.as_str())) {
+        Ok(file) => file,
+        Err(err) => return http_response!(BadRequest, {"error": err.description()})
+    };
+
+    let record = match file.to_json().and_then(|record| config.canonicalization.apply(record.as_str())) {
+        Ok(record) => record,
+        Err(err) => return http_response!(InternalServerError, {"error": err.description()})
+    };
+
+    let violations = schema_violations(record.as_str(), &config.schemas);
+    if !violations.is_empty() {
+        return http_response!(UnprocessableEntity, {
+            "error": "The file record does not match any of the network schemas",
+            "violations": violations
+        });
+    }
+
+    let data_hash = sha512(record.as_bytes());
+    let repository = BlockRepository::new(&*connection);
+    match Block::assert_document_uniqueness(data_hash.as_str(), &repository) {
+        Ok(_) => (),
+        Err(err) => return http_response!(Conflict, {"error": err.description()})
+    };
+
+    let store = FileStore::default();
+    let already_stored = match store.contains(file.hash.as_str()) {
+        Ok(already_stored) => already_stored,
+        Err(err) => return http_response!(InternalServerError, {"error": err.description()})
+    };
+
+    match store.store(&upload.bytes) {
+        Ok(_) => (),
+        Err(err) => return http_response!(InternalServerError, {"error": err.description()})
+    };
+
+    let saved = Job::new(record).and_then(|job| match JobRepository::new(&*connection).save(&JobEntity::new(&job)) {
+        Ok(1) => Ok(job),
+        Ok(_) => Err(LocksidianError::new(String::from("An unexpected number of rows were inserted in the registry"))),
+        Err(err) => Err(err)
+    });
+
+    match saved {
+        Ok(job) => http_response!(Accepted, {"job": job.id(), "data_hash": data_hash, "file": file}),
+        Err(err) => {
+            // Do not keep a file that no job is going to certify
+            if !already_stored {
+                if let Err(remove_err) = store.remove(file.hash.as_str()) {
+                    warn!("Unable to remove the uploaded file {}: {}", file.hash, remove_err.description());
+                }
+            }
+
+            http_response!(InternalServerError, {"error": err.description()})
+        }
+    }
+}
+
+/// Stream back the file certified by the document identified by the provided `data_hash` (i.e. the
+/// checksum of its record), with its recorded media type and name.
+pub fn get_file(req: &mut Request) -> IronResult<Response> {
+    match route_param!(req, "data_hash") {
+        Some(data_hash) => {
+            let connection = req.get_connection()?;
+            let repository = BlockRepository::new(&*connection);
+
+            let file = match FileDto::find(data_hash, &repository) {
+                Ok(Some(file)) => file,
+                Ok(None) => return http_response!(NoContent, {}),
+                Err(err) => return http_response!(InternalServerError, {"error": err.description()})
+            };
+
+            match FileStore::default().open(file.hash.as_str()) {
+                Ok(Some(content)) => {
+                    let mut res = Response::with((::iron::status::Ok, content));
+                    res.headers.set_raw("Content-Type", vec![file.media_type.into_bytes()]);
+
+                    if let Some(name) = file.name {
+                        res.headers.set_raw("Content-Disposition", vec![
+                            format!("attachment; filename=\"{}\"", name).into_bytes()
+                        ]);
+                    }
+
+                    Ok(res)
+                },
+                Ok(None) => http_response!(NotFound, {"error": "The file is not stored by this node"}),
+                Err(err) => http_response!(InternalServerError, {"error": err.description()})
+            }
+        },
+        None => http_response!(BadRequest, {"error": "Data hash parameter cannot be empty"})
+    }
+}
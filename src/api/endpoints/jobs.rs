@@ -0,0 +1,39 @@
+//! Jobs endpoint.
+
+use iron::prelude::*;
+use persistence::prelude::*;
+
+use blockchain::job::{Job, JobDto, JobRepository};
+
+/// Get the status of the job identified by the provided `id`:
+///
+/// ```json
+/// {
+///     "id": "{id}",
+///     "status": "queued|mining|stored|failed",
+///     "block": "{hash, once stored}",
+///     "error": "{reason, once failed}",
+///     "created_at": {timestamp},
+///     "updated_at": {timestamp}
+/// }
+/// ```
+pub fn get_job(req: &mut Request) -> IronResult<Response> {
+    match route_param!(req, "id") {
+        Some(id) => {
+            let connection = req.get_connection()?;
+            let repository = JobRepository::new(&*connection);
+
+            match repository.get(&String::from(id)) {
+                Some(entity) => match Job::from_entity(entity) {
+                    Ok(job) => {
+                        let dto = JobDto::new(&job);
+                        http_response!(Ok, dto)
+                    },
+                    Err(err) => http_response!(InternalServerError, {"error": err.description()})
+                },
+                None => http_response!(NoContent, {})
+            }
+        },
+        None => http_response!(BadRequest, {"error": "Id parameter cannot be empty"})
+    }
+}
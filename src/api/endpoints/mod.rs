@@ -5,4 +5,8 @@ pub mod node;
 pub mod identities;
 pub mod blocks;
 pub mod peers;
-pub mod metrics;
\ No newline at end of file
+pub mod metrics;
+pub mod chain;
+pub mod jobs;
+pub mod documents;
+pub mod files;
\ No newline at end of file
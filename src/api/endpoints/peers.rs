@@ -7,26 +7,21 @@ use api::middleware::node::NodeExtractor;
 
 use blockchain::peer::*;
 use blockchain::network::*;
+use blockchain::identity::identity_cli::get_active_identity;
 
+/// List the active peers of the node: the peers demoted for being silent past the timeout are not
+/// advertised to the other nodes.
 pub fn get_all(req: &mut Request) -> IronResult<Response> {
 	let connection = req.get_connection()?;
 	let repository = PeerRepository::new(&*connection);
 	
-	match repository.get_all() {
-		Some(entities) => {
-			let peers: Vec<PeerDto> = entities.iter()
-				.map(|entity| Peer::from_entity(entity))
-				.filter(|peer| peer.is_ok())
-				.map(|peer| peer.unwrap())
-				.map(|peer| PeerDto::new(&peer))
-				.filter(|dto| dto.is_ok())
-				.map(|dto| dto.unwrap())
-				.collect();
-			
-			http_response!(Ok, peers)
-		},
-		None => http_response!(NoContent, {})
-	}
+	let peers: Vec<PeerDto> = peer_cli::active_peers(&repository).iter()
+		.map(|peer| PeerDto::new(&peer))
+		.filter(|dto| dto.is_ok())
+		.map(|dto| dto.unwrap())
+		.collect();
+	
+	http_response!(Ok, peers)
 }
 
 pub fn purge(req: &mut Request) -> IronResult<Response> {
@@ -71,9 +66,14 @@ pub fn register(req: &mut Request) -> IronResult<Response> {
     let connection = req.get_connection()?;
     let repository = PeerRepository::new(&*connection);
     let address = req.get_node_address()?;
+    let scheme = req.get_node_scheme()?;
+    let registrar = match get_active_identity(&*connection) {
+        Ok(identity) => identity.hash(),
+        Err(err) => return http_response!(InternalServerError, {"error": err.description()})
+    };
 
-    match peer_cli::register(&mut peer, &repository, address.as_ref()) {
-        Ok(_) => match peer_cli::current_identity_as_peer(&*connection, address) {
+    match peer_cli::register(&mut peer, &repository, address.as_ref(), registrar.as_ref()) {
+        Ok(_) => match peer_cli::current_identity_as_peer(&*connection, address, scheme) {
             Ok(peer) => match PeerDto::new(&peer) {
                 Ok(dto) => {
                     info!("Successfully registered peer {} at {}", peer.identity(), peer.address());
@@ -96,6 +96,35 @@ pub fn register(req: &mut Request) -> IronResult<Response> {
     }
 }
 
+/// Prove that the node holds the private key of its active identity by signing the nonce sent by a
+/// registrar, along with the node's own address and the registrar identity:
+///
+/// ```json
+/// {
+///     "signature": "{hexadecimal signature}"
+/// }
+/// ```
+pub fn challenge(req: &mut Request) -> IronResult<Response> {
+	let dto = match body!(req, ChallengeDto) {
+		Ok(Some(dto)) => dto,
+		Ok(None) => return http_response!(BadRequest, {"error": "No content"}),
+		Err(err) => return http_response!(BadRequest, {"error": err.description()})
+	};
+	
+	let connection = req.get_connection()?;
+	let address = req.get_node_address()?;
+	let identity = match get_active_identity(&*connection) {
+		Ok(identity) => identity,
+		Err(err) => return http_response!(InternalServerError, {"error": err.description()})
+	};
+	
+	// The node signs the address it announces itself, never one provided by the registrar
+	match prove_possession(dto.nonce.as_ref(), address.as_ref(), dto.registrar.as_ref(), identity.key()) {
+		Ok(proof) => http_response!(Ok, proof),
+		Err(err) => http_response!(BadRequest, {"error": err.description()})
+	}
+}
+
 fn body_to_peer(req: &mut Request) -> IronResult<Peer> {
     let dto = body_to_dto(req)?;
     
@@ -56,6 +56,17 @@
 //! 	None => ...
 //! }
 //! ```
+//!
+//! # Query parameter
+//!
+//! The `query_param!` macro allows you to access the value of a query string parameter of the
+//! request URL, as an `Option<String>`.
+//!
+//! Usage:
+//!
+//! ```rust
+//! let dry_run = query_param!(req, "dry_run").map(|value| value == "true").unwrap_or(false);
+//! ```
 
 macro_rules! body {
     ($req:ident) => {
@@ -76,7 +87,7 @@ macro_rules! body_raw {
 macro_rules! http_response {
     ($status:ident, $payload:tt) => {
         {
-            if ::iron::status::$status != ::iron::status::Ok {
+            if ::iron::status::$status != ::iron::status::Ok && ::iron::status::$status != ::iron::status::Accepted {
                 warn!("{}: {}", ::iron::status::$status, json!($payload)["error"]);
             }
             Ok(::iron::Response::with((
@@ -129,4 +140,12 @@ macro_rules! route_param {
 	($req:ident, $param:tt) => {
 		$req.extensions.get::<::router::Router>().unwrap().find($param);
 	}
+}
+
+macro_rules! query_param {
+	($req:ident, $param:tt) => {
+		$req.url.clone().into_generic_url().query_pairs()
+			.find(|&(ref name, _)| name == $param)
+			.map(|(_, value)| value.into_owned())
+	}
 }
\ No newline at end of file
@@ -0,0 +1,55 @@
+//! Binary request body extractor.
+//!
+//! The `body_raw!` macro only reads UTF-8 request bodies. The binary bodies (e.g. uploaded files)
+//! are read once using `get_body_bytes`, and kept in the request extensions so that both the
+//! middlewares and the handlers can access them.
+
+use error::*;
+
+use std::io::Read;
+
+use iron::prelude::*;
+use iron::typemap;
+
+pub struct BodyBytes;
+
+impl typemap::Key for BodyBytes {
+    type Value = Vec<u8>;
+}
+
+pub trait BodyExtractor {
+
+    /// Read at most `limit` bytes of the request body.
+    fn get_body_bytes(&mut self, limit: u64) -> LocksidianResult<&[u8]>;
+
+    /// Read at most `limit` bytes of the request body, and take them out of the request.
+    fn take_body_bytes(&mut self, limit: u64) -> LocksidianResult<Vec<u8>>;
+}
+
+impl<'a, 'b> BodyExtractor for Request<'a, 'b> {
+    fn get_body_bytes(&mut self, limit: u64) -> LocksidianResult<&[u8]> {
+        if !self.extensions.contains::<BodyBytes>() {
+            let mut bytes = Vec::new();
+
+            if let Err(err) = self.body.by_ref().take(limit).read_to_end(&mut bytes) {
+                return Err(LocksidianError::from_err(err));
+            }
+
+            self.extensions.insert::<BodyBytes>(bytes);
+        }
+
+        match self.extensions.get::<BodyBytes>() {
+            Some(bytes) => Ok(bytes.as_slice()),
+            None => Err(LocksidianError::new(String::from("Unable to read the request body")))
+        }
+    }
+
+    fn take_body_bytes(&mut self, limit: u64) -> LocksidianResult<Vec<u8>> {
+        self.get_body_bytes(limit)?;
+
+        match self.extensions.remove::<BodyBytes>() {
+            Some(bytes) => Ok(bytes),
+            None => Err(LocksidianError::new(String::from("Unable to read the request body")))
+        }
+    }
+}
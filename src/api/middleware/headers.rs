@@ -14,6 +14,7 @@
 //! Expires: "0"
 //! Content-Security-Policy: "default-src 'none'; frame-ancestors: 'none;'
 //! Access-Control-Allow-Origin: "*"
+//! Content-Type: "application/json; charset=utf-8" (unless set by the handler, e.g. for files)
 //! ```
 
 use time;
@@ -48,10 +49,12 @@ impl AfterMiddleware for HeadersMiddleware {
             "default-src 'none'; frame-ancestors: 'none';".as_bytes()
         )]);
         res.headers.set(AccessControlAllowOrigin::Any);
-        res.headers.set(ContentType(Mime(
-            TopLevel::Application, SubLevel::Json,
-            vec![(Attr::Charset, Value::Utf8)])
-        ));
+        if !res.headers.has::<ContentType>() {
+            res.headers.set(ContentType(Mime(
+                TopLevel::Application, SubLevel::Json,
+                vec![(Attr::Charset, Value::Utf8)])
+            ));
+        }
 
         Ok(res)
     }
@@ -3,9 +3,16 @@
 mod headers;
 mod pool;
 mod protected;
+mod replay;
+pub mod peer_auth;
+pub mod body;
 pub mod node;
+pub mod network;
 
 pub use self::headers::HeadersMiddleware;
 pub use self::pool::PoolMiddleware;
 pub use self::protected::ProtectedMiddleware;
-pub use self::node::NodeMiddleware;
\ No newline at end of file
+pub use self::replay::ReplayCache;
+pub use self::peer_auth::PeerAuthMiddleware;
+pub use self::node::NodeMiddleware;
+pub use self::network::NetworkMiddleware;
\ No newline at end of file
@@ -0,0 +1,47 @@
+//! Network configuration middleware.
+//!
+//! `BeforeMiddleware` used to share the `NetworkConfig` loaded at daemon startup with the Iron
+//! handlers.
+
+use std::sync::Arc;
+
+use iron::prelude::*;
+use iron::{typemap, BeforeMiddleware};
+
+use blockchain::network::NetworkConfig;
+
+pub struct NetworkMiddleware {
+    config: Arc<NetworkConfig>
+}
+
+impl typemap::Key for NetworkMiddleware {
+    type Value = Arc<NetworkConfig>;
+}
+
+impl NetworkMiddleware {
+    pub fn new(config: NetworkConfig) -> NetworkMiddleware {
+        NetworkMiddleware {
+            config: Arc::new(config)
+        }
+    }
+}
+
+impl BeforeMiddleware for NetworkMiddleware {
+    fn before(&self, req: &mut Request) -> IronResult<()> {
+        req.extensions.insert::<NetworkMiddleware>(self.config.clone());
+        Ok(())
+    }
+}
+
+pub trait NetworkExtractor {
+    fn get_network_config(&self) -> IronResult<Arc<NetworkConfig>>;
+}
+
+impl<'a, 'b> NetworkExtractor for Request<'a, 'b> {
+    fn get_network_config(&self) -> IronResult<Arc<NetworkConfig>> {
+        match self.extensions.get::<NetworkMiddleware>() {
+            Some(config) => Ok(config.clone()),
+            None => http_error!(InternalServerError, {"error": "No network configuration is embedded in this request"})
+        }
+    }
+}
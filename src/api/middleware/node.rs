@@ -4,37 +4,47 @@ use iron::prelude::*;
 use iron::{typemap, BeforeMiddleware};
 
 pub struct NodeMiddleware {
-    address: String
+    address: String,
+    scheme: &'static str
 }
 
 impl typemap::Key for NodeMiddleware {
-    type Value = String;
+    type Value = (String, &'static str);
 }
 
 impl NodeMiddleware {
-    pub fn new(address: String) -> NodeMiddleware {
+    pub fn new(address: String, scheme: &'static str) -> NodeMiddleware {
         NodeMiddleware {
-            address: address
+            address: address,
+            scheme: scheme
         }
     }
 }
 
 impl BeforeMiddleware for NodeMiddleware {
     fn before(&self, req: &mut Request) -> IronResult<()> {
-        req.extensions.insert::<NodeMiddleware>(self.address.clone());
+        req.extensions.insert::<NodeMiddleware>((self.address.clone(), self.scheme));
         Ok(())
     }
 }
 
 pub trait NodeExtractor {
     fn get_node_address(&self) -> IronResult<String>;
+    fn get_node_scheme(&self) -> IronResult<&'static str>;
 }
 
 impl<'a, 'b> NodeExtractor for Request<'a, 'b> {
     fn get_node_address(&self) -> IronResult<String> {
         match self.extensions.get::<NodeMiddleware>() {
-            Some(address) => Ok(address.clone()),
+            Some(&(ref address, _)) => Ok(address.clone()),
             None => http_error!(InternalServerError, "No node address is embedded in this request")
         }
     }
+
+    fn get_node_scheme(&self) -> IronResult<&'static str> {
+        match self.extensions.get::<NodeMiddleware>() {
+            Some(&(_, scheme)) => Ok(scheme),
+            None => http_error!(InternalServerError, "No node scheme is embedded in this request")
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,177 @@
+//! Node-to-node authentication middleware.
+//!
+//! `BeforeMiddleware` used to:
+//!
+//! - Check if the URL is one of the endpoints the peers use to register, announce and replicate blocks;
+//! - Get the signer identity, the timestamp, the nonce and the signature of the request from the
+//!   X-LS-IDENTITY, X-LS-TIMESTAMP, X-LS-NONCE and X-LS-NODE-SIGNATURE headers;
+//! - Get the public key of the signer from the peers registry; a registering peer signs the
+//!   request using the key it registers instead;
+//! - Verify the signature of the request method, path, body checksum, timestamp and nonce;
+//! - Check that the signer did not already use the same nonce, so that a request cannot be replayed.
+//!
+//! Sends 403 error if the signature is missing or invalid.
+//!
+//! Embeds the verified signer identity in the request otherwise, see `PeerAuthExtractor`.
+
+use error::*;
+use iron::prelude::*;
+use iron::{typemap, BeforeMiddleware};
+
+use persistence::prelude::*;
+use blockchain::get_current_timestamp;
+use blockchain::network::{RequestSignature, IDENTITY_HEADER, TIMESTAMP_HEADER, NONCE_HEADER, SIGNATURE_HEADER};
+use blockchain::peer::{Peer, PeerDto, PeerRepository};
+use api::middleware::ReplayCache;
+
+pub struct PeerAuthMiddleware {
+    endpoints: Vec<(&'static str, &'static str)>,
+    replay_cache: ReplayCache
+}
+
+impl typemap::Key for PeerAuthMiddleware {
+    type Value = String;
+}
+
+impl PeerAuthMiddleware {
+    pub fn new() -> PeerAuthMiddleware {
+        PeerAuthMiddleware {
+            endpoints: vec![
+                ("PUT", "/blocks"),
+                ("POST", "/blocks/inventory"),
+                ("POST", "/peers/register")
+            ],
+            replay_cache: ReplayCache::new()
+        }
+    }
+
+    fn is_authenticated_route(&self, referer: &str, method: &str) -> bool {
+        self.endpoints.iter().any(|&(endpoint_method, endpoint)| endpoint_method == method && endpoint == referer)
+    }
+
+    fn process_request(&self, req: &mut Request, referer: String, method: String) -> IronResult<()> {
+        match self.authenticate(req, referer.as_ref(), method.as_ref()) {
+            Ok(signer) => {
+                req.extensions.insert::<PeerAuthMiddleware>(signer);
+                Ok(())
+            },
+            Err(err) => {
+                warn!("Rejected {} {} request: {}", method, referer, err.description());
+                http_error!(Forbidden, {"error": "Forbidden"})
+            }
+        }
+    }
+
+    fn get_referer(&self, req: &mut Request) -> String {
+        let mut referer: String = String::from("/");
+        let path: String = req.url.path().join("/");
+        referer.push_str(&path);
+
+        referer
+    }
+
+    fn authenticate(&self, req: &mut Request, referer: &str, method: &str) -> LocksidianResult<String> {
+        let timestamp = self.get_header(req, TIMESTAMP_HEADER)?;
+        let signature = RequestSignature {
+            identity: self.get_header(req, IDENTITY_HEADER)?,
+            timestamp: match timestamp.parse::<u64>() {
+                Ok(timestamp) => timestamp,
+                Err(err) => return Err(LocksidianError::from_err(err))
+            },
+            nonce: self.get_header(req, NONCE_HEADER)?,
+            signature: self.get_header(req, SIGNATURE_HEADER)?
+        };
+
+        let body = match body_raw!(req) {
+            Ok(Some(body)) => body,
+            Ok(None) => String::new(),
+            Err(_) => return Err(LocksidianError::new(String::from("Error while parsing HTTP request body as raw data")))
+        };
+
+        let signer = self.get_signer_peer(req, referer, signature.identity.as_ref(), body.as_ref())?;
+        let now = get_current_timestamp();
+        signature.verify(signer.key(), method, referer, body.as_bytes(), now)?;
+
+        // The nonce is only remembered once the signature is verified, so that it cannot be burnt by a third party
+        let key = format!("{}\n{}", signature.identity, signature.nonce.to_lowercase());
+        match self.replay_cache.check(key, signature.timestamp, now) {
+            true => Ok(signature.identity),
+            false => Err(LocksidianError::new(format!("The nonce {} was already used by {}", signature.nonce, signature.identity)))
+        }
+    }
+
+    /// Returns the registered `Peer` of the given `identity`, or the registering one.
+    fn get_signer_peer(&self, req: &mut Request, referer: &str, identity: &str, body: &str) -> LocksidianResult<Peer> {
+        if referer == "/peers/register" {
+            return match ::serde_json::from_str::<PeerDto>(body) {
+                Ok(dto) => dto.to_peer(),
+                Err(err) => Err(LocksidianError::from_err(err))
+            };
+        }
+
+        let connection = match req.get_connection() {
+            Ok(connection) => connection,
+            Err(err) => return Err(LocksidianError::from_err(err))
+        };
+
+        match PeerRepository::new(&*connection).get(&String::from(identity)) {
+            Some(entity) => Peer::from_entity(&entity),
+            None => Err(LocksidianError::new(format!("Unknown peer {}", identity)))
+        }
+    }
+
+    fn get_header(&self, req: &mut Request, name: &str) -> LocksidianResult<String> {
+        match req.headers.get_raw(name) {
+            Some(header) => match header.get(0) {
+                Some(value) => match String::from_utf8(value.clone()) {
+                    Ok(value) => Ok(value),
+                    Err(err) => Err(LocksidianError::from_err(err))
+                },
+                None => Err(LocksidianError::new(String::from("Requested header has no content")))
+            },
+            None => Err(LocksidianError::new(format!("Header \"{}\" not found", name)))
+        }
+    }
+}
+
+impl BeforeMiddleware for PeerAuthMiddleware {
+    fn before(&self, req: &mut Request) -> IronResult<()> {
+        let referer = self.get_referer(req);
+        let method = req.method.to_string();
+
+        match self.is_authenticated_route(referer.as_ref(), method.as_ref()) {
+            true => self.process_request(req, referer, method),
+            false => Ok(())
+        }
+    }
+}
+
+pub trait PeerAuthExtractor {
+    fn get_signer(&self) -> IronResult<String>;
+}
+
+impl<'a, 'b> PeerAuthExtractor for Request<'a, 'b> {
+    fn get_signer(&self) -> IronResult<String> {
+        match self.extensions.get::<PeerAuthMiddleware>() {
+            Some(signer) => Ok(signer.clone()),
+            None => http_error!(Forbidden, {"error": "No authenticated peer is embedded in this request"})
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn node_to_node_endpoints_should_be_authenticated() {
+        let middleware = PeerAuthMiddleware::new();
+
+        assert!(middleware.is_authenticated_route("/blocks", "PUT"));
+        assert!(middleware.is_authenticated_route("/blocks/inventory", "POST"));
+        assert!(middleware.is_authenticated_route("/peers/register", "POST"));
+        assert!(!middleware.is_authenticated_route("/blocks", "POST"));
+        assert!(!middleware.is_authenticated_route("/blocks", "GET"));
+        assert!(!middleware.is_authenticated_route("/peers", "GET"));
+    }
+}
@@ -4,9 +4,18 @@
 //!
 //! - Check if URL is protected under specified method;
 //! - Get the current identity;
-//! - Check if X-LS-SIGNATURE header is present and has hexadecimal data;
-//! - Get sha512 request body hash checksum;
-//! - Compare request body hash with X-LS-SIGNATURE header and verfiy signature.
+//! - Check if X-LS-SIGNATURE and X-LS-TIMESTAMP headers are present;
+//! - Check that the timestamp is no more than `MAX_CLOCK_SKEW` seconds away from the node's clock;
+//! - Get sha512 request body hash checksum (the checksum of an empty body for bodyless requests);
+//!   the binary bodies of the file uploads are hashed as they are;
+//! - Verify the X-LS-SIGNATURE header against the request method, path and query, body checksum and
+//!   timestamp, one per line (`{METHOD}\n{path?query}\n{sha512 checksum}\n{timestamp}`);
+//! - Check that the same signature was not already accepted, so that a request cannot be replayed.
+//!
+//! The node administration and document decryption endpoints are always protected, while the
+//! document, anchor and file submission endpoints are only protected when the node runs in
+//! protected mode. A `*` segment of a protected endpoint matches any route parameter (e.g.
+//! `/blocks/*/decrypt`).
 //!
 //! Sends 403 error if protection blocked the request.
 //!
@@ -19,34 +28,54 @@ use iron::BeforeMiddleware;
 use persistence::prelude::*;
 use blockchain::identity::identity_cli::get_active_identity;
 use blockchain::identity::Identity;
+use blockchain::file::MAX_FILE_SIZE;
+use blockchain::get_current_timestamp;
+use blockchain::network::{TIMESTAMP_HEADER, MAX_CLOCK_SKEW};
+use api::middleware::body::BodyExtractor;
+use api::middleware::ReplayCache;
 use sec::sha::sha512;
 use sec::rsa::Rsa;
+use sec::hex::ToHex;
 
 use std::collections::HashMap;
 
 pub struct ProtectedMiddleware {
-    endpoints_filter: HashMap<&'static str, Vec<&'static str>>
+    endpoints_filter: HashMap<&'static str, Vec<&'static str>>,
+    replay_cache: ReplayCache
 }
 
 impl ProtectedMiddleware {
-    pub fn new() -> ProtectedMiddleware {
+    pub fn new(protected: bool) -> ProtectedMiddleware {
         let mut endpoints_filter = HashMap::new();
 
-        ProtectedMiddleware::init(&mut endpoints_filter);
+        ProtectedMiddleware::init(&mut endpoints_filter, protected);
 
         ProtectedMiddleware {
-            endpoints_filter: endpoints_filter
+            endpoints_filter: endpoints_filter,
+            replay_cache: ReplayCache::new()
         }
     }
 
-    fn init(endpoints_filter : &mut HashMap<&'static str, Vec<&'static str>>) {
-        endpoints_filter.insert("/blocks", vec!["POST"]);
+    fn init(endpoints_filter : &mut HashMap<&'static str, Vec<&'static str>>, protected: bool) {
+        endpoints_filter.insert("/chain/verify", vec!["POST"]);
+        endpoints_filter.insert("/blocks/*/decrypt", vec!["GET"]);
+
+        let mut blocks_methods = vec!["DELETE"];
+        if protected {
+            blocks_methods.push("POST");
+        }
+        endpoints_filter.insert("/blocks", blocks_methods);
+
+        if protected {
+            endpoints_filter.insert("/files", vec!["POST"]);
+            endpoints_filter.insert("/documents/anchor", vec!["POST"]);
+        }
     }
 
     fn process_request(&self, req: &mut Request) -> IronResult<()> {
         match self.check_signature(req) {
-            Ok(_) => Ok(()),
-            Err(_) => http_error!(Forbidden, {"error": "Forbidden"})
+            Ok(true) => Ok(()),
+            _ => http_error!(Forbidden, {"error": "Forbidden"})
         }
     }
 
@@ -58,10 +87,8 @@ impl ProtectedMiddleware {
     }
 
     fn is_method_protected(&self, referer: &str, method: &str) -> bool {
-        match self.endpoints_filter.get(&referer) {
-            Some(methods) => methods.contains(&method),
-            None => false
-        }
+        self.endpoints_filter.iter()
+            .any(|(pattern, methods)| matches_pattern(pattern, referer) && methods.contains(&method))
     }
 
     fn get_referer(&self, req: &mut Request) -> String {
@@ -82,10 +109,35 @@ impl ProtectedMiddleware {
     fn check_signature(&self, req: &mut Request) -> LocksidianResult<bool> {
         let hash_raw = self.get_body_hash(req)?;
         let signature_raw = self.get_header(req, "X-LS-SIGNATURE")?;
+        let timestamp = self.get_timestamp(req)?;
+        let now = get_current_timestamp();
+
+        if skew(timestamp, now) > MAX_CLOCK_SKEW {
+            return Err(LocksidianError::new(format!("The request timestamp {} is out of the accepted window", timestamp)));
+        }
+
         let identity_raw = self.get_identity(req)?;
         let key: &Rsa = identity_raw.key();
 
-        key.verify_signature(hash_raw.as_bytes(), signature_raw.as_slice())
+        let method = req.method.to_string();
+        let uri = self.get_request_uri(req);
+        let message = signed_message(method.as_ref(), uri.as_ref(), hash_raw.as_ref(), timestamp);
+        match key.verify_signature(message.as_bytes(), signature_raw.as_slice())? {
+            true => Ok(self.replay_cache.check(signature_raw.to_hex(), timestamp, now)),
+            false => Ok(false)
+        }
+    }
+
+    fn get_timestamp(&self, req: &mut Request) -> LocksidianResult<u64> {
+        let timestamp = self.get_header(req, TIMESTAMP_HEADER)?;
+
+        match String::from_utf8(timestamp) {
+            Ok(timestamp) => match timestamp.trim().parse::<u64>() {
+                Ok(timestamp) => Ok(timestamp),
+                Err(err) => Err(LocksidianError::from_err(err))
+            },
+            Err(err) => Err(LocksidianError::from_err(err))
+        }
     }
 
     fn get_header(&self, req: &mut Request, name : &str) -> LocksidianResult<Vec<u8>> {
@@ -103,19 +155,54 @@ impl ProtectedMiddleware {
     }
 
     fn get_body_hash(&self, req: &mut Request) -> LocksidianResult<String> {
+        if self.get_referer(req) == "/files" {
+            return Ok(sha512(req.get_body_bytes(MAX_FILE_SIZE + 1)?));
+        }
+
         match body_raw!(req) {
-            Ok(body) => self.calculate_body_hash(body),
+            Ok(Some(body)) => Ok(sha512(body.as_bytes())),
+            Ok(None) => Ok(sha512(b"")),
             Err(_) => Err(LocksidianError::new(String::from("Error while parsing HTTP request body as raw data")))
         }
     }
 
-    fn calculate_body_hash(&self, body: Option<String>) -> LocksidianResult<String> {
-        match body {
-            Some(data) => Ok(sha512(data.as_bytes())),
-            None => Err(LocksidianError::new(String::from("No body available")))
+    /// Path and query of the request (e.g. `/blocks?dry_run=true`), as signed by the administrator.
+    fn get_request_uri(&self, req: &mut Request) -> String {
+        let mut uri = self.get_referer(req);
+
+        match req.url.query() {
+            Some(query) => {
+                uri.push_str("?");
+                uri.push_str(query);
+            },
+            None => ()
         }
+
+        uri
+    }
+
+}
+
+/// Returns the message signed by the administrator: the request method, path and query, body hash
+/// checksum and timestamp, one per line.
+fn signed_message(method: &str, uri: &str, hash: &str, timestamp: u64) -> String {
+    format!("{}\n{}\n{}\n{}", method.to_uppercase(), uri, hash, timestamp)
+}
+
+fn skew(timestamp: u64, now: u64) -> u64 {
+    match now > timestamp {
+        true => now - timestamp,
+        false => timestamp - now
     }
+}
 
+/// Returns `true` if the `referer` path matches the endpoint `pattern`, segment by segment.
+fn matches_pattern(pattern: &str, referer: &str) -> bool {
+    let patterns: Vec<&str> = pattern.trim_right_matches('/').split('/').collect();
+    let segments: Vec<&str> = referer.trim_right_matches('/').split('/').collect();
+
+    patterns.len() == segments.len() && patterns.iter().zip(segments.iter())
+        .all(|(pattern, segment)| *pattern == "*" || pattern == segment)
 }
 
 impl BeforeMiddleware for ProtectedMiddleware {
@@ -125,4 +212,38 @@ impl BeforeMiddleware for ProtectedMiddleware {
             false => Ok(())
         }
     }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn protected_endpoints_should_match_route_parameters() {
+        let middleware = ProtectedMiddleware::new(false);
+
+        assert!(middleware.is_method_protected("/blocks/abc/decrypt", "GET"));
+        assert!(middleware.is_method_protected("/blocks", "DELETE"));
+        assert!(!middleware.is_method_protected("/blocks", "POST"));
+        assert!(!middleware.is_method_protected("/blocks/abc", "GET"));
+        assert!(!middleware.is_method_protected("/blocks/abc/receipt", "GET"));
+        assert!(!middleware.is_method_protected("/files", "POST"));
+        assert!(!middleware.is_method_protected("/documents/anchor", "POST"));
+
+        let middleware = ProtectedMiddleware::new(true);
+        assert!(middleware.is_method_protected("/blocks", "POST"));
+        assert!(middleware.is_method_protected("/files", "POST"));
+        assert!(middleware.is_method_protected("/documents/anchor", "POST"));
+        assert!(!middleware.is_method_protected("/documents/verify", "POST"));
+        assert!(!middleware.is_method_protected("/files/abc", "GET"));
+    }
+
+    #[test]
+    fn signed_messages_should_include_the_request_and_the_timestamp() {
+        assert_eq!(signed_message("post", "/blocks?supersedes=def", "abc", 1000), "POST\n/blocks?supersedes=def\nabc\n1000");
+        assert!(signed_message("POST", "/blocks", "abc", 1000) != signed_message("POST", "/blocks?recipients=def", "abc", 1000));
+        assert!(signed_message("POST", "/blocks", "abc", 1000) != signed_message("DELETE", "/blocks", "abc", 1000));
+        assert_eq!(skew(1000, 1000 + MAX_CLOCK_SKEW), MAX_CLOCK_SKEW);
+        assert_eq!(skew(1000 + MAX_CLOCK_SKEW, 1000), MAX_CLOCK_SKEW);
+    }
 }
\ No newline at end of file
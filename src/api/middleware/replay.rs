@@ -0,0 +1,59 @@
+//! Replay protection shared by the middlewares verifying signed requests.
+//!
+//! A signed request is only accepted within `MAX_CLOCK_SKEW` seconds of its timestamp. Within this
+//! window, each request is identified by a unique value (its nonce or its signature), which is
+//! remembered until the request timestamp falls out of the window: a captured request cannot be
+//! replayed.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use blockchain::network::MAX_CLOCK_SKEW;
+
+pub struct ReplayCache {
+    seen: Mutex<HashMap<String, u64>>
+}
+
+impl ReplayCache {
+    pub fn new() -> ReplayCache {
+        ReplayCache {
+            seen: Mutex::new(HashMap::new())
+        }
+    }
+
+    /// Returns `true` if the request identified by `key`, signed at `timestamp`, is seen for the
+    /// first time, and remember it. The requests that fell out of the window are forgotten.
+    pub fn check(&self, key: String, timestamp: u64, now: u64) -> bool {
+        let mut seen = match self.seen.lock() {
+            Ok(seen) => seen,
+            Err(poisoned) => poisoned.into_inner()
+        };
+
+        seen.retain(|_, seen_at| *seen_at + MAX_CLOCK_SKEW >= now);
+
+        match seen.contains_key(&key) {
+            true => false,
+            false => {
+                seen.insert(key, timestamp);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn requests_should_only_be_accepted_once_within_the_window() {
+        let cache = ReplayCache::new();
+
+        assert!(cache.check(String::from("a"), 1000, 1000));
+        assert!(!cache.check(String::from("a"), 1000, 1000 + MAX_CLOCK_SKEW));
+        assert!(cache.check(String::from("b"), 1000, 1000));
+
+        // Forgotten once its timestamp is out of the window, where it is rejected anyway
+        assert!(cache.check(String::from("a"), 1000, 1001 + MAX_CLOCK_SKEW));
+    }
+}
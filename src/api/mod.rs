@@ -7,6 +7,7 @@ mod router;
 mod server;
 mod config;
 mod middleware;
+mod multipart;
 mod endpoints;
 pub mod cli;
 
@@ -0,0 +1,173 @@
+//! Uploaded files extraction.
+//!
+//! A file is either uploaded as the whole body of an `application/octet-stream` request, or as a
+//! part of a `multipart/form-data` request (RFC 7578). In the latter case, the first part having a
+//! file name (or named `file`) is used.
+
+use error::*;
+
+/// File extracted from a request body.
+pub struct Upload {
+    pub bytes: Vec<u8>,
+    pub media_type: Option<String>,
+    pub name: Option<String>
+}
+
+/// Extract the uploaded file from a request `body` of the given `content_type`.
+pub fn parse_upload(content_type: &str, body: Vec<u8>) -> LocksidianResult<Upload> {
+    let media_type = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+
+    match media_type.as_str() {
+        "application/octet-stream" => Ok(Upload {
+            bytes: body,
+            media_type: None,
+            name: None
+        }),
+        "multipart/form-data" => match parameter(content_type, "boundary") {
+            Some(boundary) => parse_multipart(&body, boundary.as_str()),
+            None => Err(LocksidianError::new(String::from("The multipart boundary is missing")))
+        },
+        _ => Err(LocksidianError::new(format!("Unsupported upload content type: {}", media_type)))
+    }
+}
+
+/// Search the file part of a `multipart/form-data` body.
+fn parse_multipart(body: &[u8], boundary: &str) -> LocksidianResult<Upload> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let separator = format!("\r\n--{}", boundary).into_bytes();
+
+    let mut position = match find(body, &delimiter, 0) {
+        Some(start) => start + delimiter.len(),
+        None => return Err(LocksidianError::new(String::from("The multipart body has no part")))
+    };
+
+    // Each part starts right after a delimiter line, until the closing `--{boundary}--` delimiter
+    while !body[position..].starts_with(b"--") {
+        let (headers_end, content_end) = match (find(body, b"\r\n\r\n", position), find(body, &separator, position)) {
+            (Some(headers_end), Some(content_end)) if headers_end + 4 <= content_end => (headers_end, content_end),
+            _ => return Err(LocksidianError::new(String::from("The multipart body is malformed")))
+        };
+
+        let headers = String::from_utf8_lossy(&body[position..headers_end]);
+        let mut disposition = None;
+        let mut media_type = None;
+
+        for line in headers.split("\r\n") {
+            match line.find(':') {
+                Some(index) => match line[..index].trim().to_lowercase().as_str() {
+                    "content-disposition" => disposition = Some(String::from(line[index + 1..].trim())),
+                    "content-type" => media_type = Some(String::from(line[index + 1..].trim())),
+                    _ => ()
+                },
+                None => ()
+            }
+        }
+
+        if let Some(disposition) = disposition {
+            let name = parameter(disposition.as_str(), "filename");
+
+            if name.is_some() || parameter(disposition.as_str(), "name") == Some(String::from("file")) {
+                return Ok(Upload {
+                    bytes: body[headers_end + 4..content_end].to_vec(),
+                    media_type: media_type,
+                    name: name
+                });
+            }
+        }
+
+        position = content_end + separator.len();
+    }
+
+    Err(LocksidianError::new(String::from("The multipart body has no file part")))
+}
+
+/// Value of the `name` parameter of a header value (e.g. `form-data; name="file"`), unquoted.
+fn parameter(header: &str, name: &str) -> Option<String> {
+    header.split(';').skip(1)
+        .filter_map(|pair| match pair.find('=') {
+            Some(index) if pair[..index].trim().to_lowercase() == name => {
+                Some(String::from(pair[index + 1..].trim().trim_matches('"')))
+            },
+            _ => None
+        })
+        .next()
+}
+
+/// Position of the first occurrence of `needle` in `haystack`, starting from `from`.
+///
+/// Uses the Boyer-Moore-Horspool algorithm: on a mismatch, the window is shifted by the distance
+/// between the last occurrence of its last byte in the needle and the end of the needle, so that
+/// most of the bytes of a large upload are never compared to the boundary.
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    if needle.is_empty() || haystack.len() < needle.len() {
+        return None;
+    }
+
+    let last = needle.len() - 1;
+    let mut shifts = [needle.len(); 256];
+    for (index, &byte) in needle[..last].iter().enumerate() {
+        shifts[byte as usize] = last - index;
+    }
+
+    let mut position = from;
+    while position + last < haystack.len() {
+        if &haystack[position..position + needle.len()] == needle {
+            return Some(position);
+        }
+
+        position += shifts[haystack[position + last] as usize];
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_extract_the_file_part_of_a_multipart_body() {
+        let body = concat!(
+            "--XyZ\r\n",
+            "Content-Disposition: form-data; name=\"comment\"\r\n\r\n",
+            "Signed lease\r\n",
+            "--XyZ\r\n",
+            "Content-Disposition: form-data; name=\"document\"; filename=\"lease.pdf\"\r\n",
+            "Content-Type: application/pdf\r\n\r\n",
+            "%PDF-1.4\r\n\r\n",
+            "--XyZ--\r\n"
+        );
+
+        let upload = parse_upload("multipart/form-data; boundary=\"XyZ\"", body.as_bytes().to_vec()).unwrap();
+        assert_eq!(upload.bytes.as_slice(), b"%PDF-1.4\r\n");
+        assert_eq!(upload.media_type, Some(String::from("application/pdf")));
+        assert_eq!(upload.name, Some(String::from("lease.pdf")));
+    }
+
+    #[test]
+    fn should_reject_multipart_bodies_without_file() {
+        let body = "--XyZ\r\nContent-Disposition: form-data; name=\"comment\"\r\n\r\nHello\r\n--XyZ--\r\n";
+
+        assert!(parse_upload("multipart/form-data; boundary=XyZ", body.as_bytes().to_vec()).is_err());
+        assert!(parse_upload("multipart/form-data", body.as_bytes().to_vec()).is_err());
+        assert!(parse_upload("text/plain", body.as_bytes().to_vec()).is_err());
+    }
+
+    #[test]
+    fn should_find_the_first_occurrence_of_a_boundary() {
+        let body = b"%PDF\r\n--XyZ\r\n--XyZ--";
+
+        assert_eq!(find(body, b"\r\n--XyZ", 0), Some(4));
+        assert_eq!(find(body, b"\r\n--XyZ", 5), Some(11));
+        assert_eq!(find(body, b"--XyZ--", 0), Some(13));
+        assert_eq!(find(body, b"--Abc", 0), None);
+        assert_eq!(find(body, b"--XyZ--", 14), None);
+        assert_eq!(find(b"aaab", b"ab", 0), Some(2));
+    }
+
+    #[test]
+    fn octet_stream_body_should_be_the_file() {
+        let upload = parse_upload("application/octet-stream", vec![0, 159, 146, 150]).unwrap();
+        assert_eq!(upload.bytes, vec![0, 159, 146, 150]);
+    }
+}
@@ -16,17 +16,43 @@ pub fn routes() -> Router {
         identities_hash: get "/identities/:hash" => endpoints::identities::get_identity_by_hash,
 
         // Block API
-        show_head: get "/blocks" => endpoints::blocks::show_head,
+        list_blocks: get "/blocks" => endpoints::blocks::list_blocks,
         store_document: post "/blocks" => endpoints::blocks::store_document,
         store_document_preflight: options "/blocks" => endpoints::blocks::preflight,
+        blocks_head: get "/blocks/HEAD" => endpoints::blocks::get_head,
+        blocks_height: get "/blocks/height/:height" => endpoints::blocks::get_block_at_height,
         get_block: get "/blocks/:hash" => endpoints::blocks::get_block,
+        blocks_receipt: get "/blocks/:hash/receipt" => endpoints::blocks::get_receipt,
+        blocks_decrypt: get "/blocks/:hash/decrypt" => endpoints::blocks::decrypt_block,
         blocks_replicate: put "/blocks" => endpoints::blocks::replicate_block,
+        blocks_inventory: post "/blocks/inventory" => endpoints::blocks::receive_inventory,
+        blocks_prune: delete "/blocks" => endpoints::blocks::prune,
+
+        // Document API
+        documents_search: get "/documents" => endpoints::documents::search_documents,
+        documents_get: get "/documents/:data_hash" => endpoints::documents::get_document,
+        documents_verify: post "/documents/verify" => endpoints::documents::verify_document,
+        documents_anchor: post "/documents/anchor" => endpoints::documents::anchor_document,
+        documents_proof: get "/documents/:data_hash/proof" => endpoints::documents::get_proof,
+        documents_history: get "/documents/:data_hash/history" => endpoints::documents::get_history,
+
+        // File API
+        store_file: post "/files" => endpoints::files::store_file,
+        get_file: get "/files/:data_hash" => endpoints::files::get_file,
+
+        // Job API
+        get_job: get "/jobs/:id" => endpoints::jobs::get_job,
 
         // Peer API
         register: post "/peers/register" => endpoints::peers::register,
+        peers_challenge: post "/peers/challenge" => endpoints::peers::challenge,
         peers_all: get "/peers" => endpoints::peers::get_all,
         peers_purge: delete "/peers" => endpoints::peers::purge,
 
+        // Chain API
+        chain_verify: post "/chain/verify" => endpoints::chain::verify,
+        chain_tips: get "/chain/tips" => endpoints::chain::tips,
+
         // Metrics API
         metrics: get "/metrics" => endpoints::metrics::get_all,
 
@@ -13,13 +13,15 @@ use persistence::prelude::*;
 use api::middleware::*;
 use api::ServerConfig;
 
+use blockchain::algorithm::set_mining_threads;
+use blockchain::consensus::consensus_cli;
 use blockchain::identity::Identity;
+use blockchain::job::job_worker;
 use blockchain::identity::identity_cli::get_active_identity;
 
 use std::net::SocketAddrV4;
 use blockchain::network::*;
 use blockchain::peer::*;
-use blockchain::block::BlockRepository;
 
 /// HTTP server exposing the `Locksidian` REST API.
 pub struct Server {
@@ -35,13 +37,30 @@ pub struct Server {
     protected: bool,
     
     /// Optional network entrypoint IP address or hostname
-    entrypoint: Option<String>
+    entrypoint: Option<String>,
+
+    /// Optional identity of the network entrypoint, trusted on first use otherwise
+    entrypoint_identity: Option<String>,
+    
+    /// Configuration of the network joined by this node
+    network: NetworkConfig,
+
+    /// Delays, in seconds, after which a silent peer is demoted then evicted
+    peer_timeout: u64,
+    peer_eviction: u64,
+
+    /// Is the REST API served over HTTPS, optionally using the provided certificate and key files?
+    tls: bool,
+    tls_cert: Option<String>,
+    tls_key: Option<String>
 }
 
 impl Server {
 
     /// Create a new `Server` instance.
     pub fn new(socket: SocketAddrV4, config: ServerConfig) -> Server {
+        set_mining_threads(config.mining_threads);
+
         Server {
             listen_addr: format!("{}:{}", socket.ip(), socket.port()),
 	        remote_addr: match config.local_only {
@@ -49,7 +68,14 @@ impl Server {
 		        false => format!("{}:{}", get_public_ip().unwrap_or(format!("{}", socket.ip())), socket.port()),
 	        },
             protected: config.protected,
-			entrypoint: config.entrypoint
+			entrypoint: config.entrypoint,
+			entrypoint_identity: config.entrypoint_identity,
+			network: config.network,
+			peer_timeout: config.peer_timeout,
+			peer_eviction: config.peer_eviction,
+			tls: config.tls,
+			tls_cert: config.tls_cert,
+			tls_key: config.tls_key
         }
     }
 
@@ -58,12 +84,11 @@ impl Server {
     fn configure_middlewares<H: Handler>(&self, handler: H) -> LocksidianResult<Chain> {
         let mut chain = Chain::new(handler);
 
-        chain.link_before(NodeMiddleware::new(self.addr()));
+        chain.link_before(NodeMiddleware::new(self.addr(), self.scheme()));
+        chain.link_before(NetworkMiddleware::new(self.network.clone()));
         chain.link_before(PoolMiddleware::new(database_path())?);
-
-        if self.protected {
-            chain.link_before(ProtectedMiddleware::new());
-        }
+        chain.link_before(ProtectedMiddleware::new(self.protected));
+        chain.link_before(PeerAuthMiddleware::new());
 
         chain.link_after(HeadersMiddleware);
 
@@ -74,11 +99,14 @@ impl Server {
     /// on the configured address.
     pub fn start<H: Handler>(&self, handler: H) -> LocksidianResult<String> {
         let chain = self.configure_middlewares(handler)?;
-        let status = Iron::new(chain).http(self.listen_addr.as_str());
+        let status = match self.tls {
+            true => Iron::new(chain).https(self.listen_addr.as_str(), self.tls_server()?),
+            false => Iron::new(chain).http(self.listen_addr.as_str())
+        };
 
         match status {
             Ok(mut listener) => {
-                info!("Locksidian daemon listening on: {}", self.listen_addr);
+                info!("Locksidian daemon listening on: {}://{}", self.scheme(), self.listen_addr);
 				
                 match self.on_start() {
 					Ok(_) => Ok(String::from("Daemon initialization successful!")),
@@ -94,6 +122,14 @@ impl Server {
         }
     }
 	
+	/// Configure the TLS listener using the certificate of the active identity.
+	fn tls_server(&self) -> LocksidianResult<::hyper_openssl::OpensslServer> {
+		let connection = get_connection(database_path())?;
+		let identity = get_active_identity(&connection)?;
+		
+		tls::server(&identity, self.tls_cert.clone(), self.tls_key.clone())
+	}
+	
 	/// Gracefully stops the running `Listening` instance.
 	fn stop(&self, listener: &mut Listening) -> LocksidianResult<String> {
 		match listener.close() {
@@ -106,8 +142,12 @@ impl Server {
     fn on_start(&self) -> LocksidianResult<()> {
 		let connection = get_connection(database_path())?;
 		let identity = self.setup_identity(&connection)?;
+		consensus_cli::check_origin(&connection, &self.network)?;
+		HttpClient::check_tls()?;
 		
 		self.setup_network(&connection, &identity)?;
+		job_worker::start(self.network.clone())?;
+		peer_monitor::start(self.peer_timeout, self.peer_eviction, self.addr(), self.scheme())?;
 		
 		Ok(())
     }
@@ -125,13 +165,13 @@ impl Server {
 	fn setup_network(&self, connection: &SqliteConnection, identity: &Identity) -> LocksidianResult<()> {
 		match self.entrypoint {
 			Some(ref entrypoint) => {
-				let client = HttpClient::from_address(entrypoint.clone());
+				let client = HttpClient::from_address(entrypoint.clone(), self.entrypoint_identity.clone());
 				let repository = PeerRepository::new(&connection);
 				
 				let peer = self.network_registration(&client, &identity, &repository)?;
 				let client = HttpClient::from_peer(&peer);
 				
-				self.register_network_peers(&client, &repository)?;
+				self.register_network_peers(&client, &identity, &repository)?;
 				info!("Successfully registered onto the network. Entrypoint is: {}", self.remote_addr);
 				
 				info!("Syncing the blockchain...");
@@ -147,28 +187,43 @@ impl Server {
 	/// Try to establish a connection and register our instance with the network entrypoint.
 	fn network_registration<T: Client>(&self, client: &T, identity: &Identity, repository: &PeerRepository) -> LocksidianResult<Peer> {
 		let key = identity.public_key_to_hex()?;
-		let peer = Peer::new(key, self.addr())?;
+		let peer = Peer::new(key, self.addr())?.with_scheme(self.scheme())?;
 		
-		match client.register(&peer) {
+		match client.register(&peer, &identity) {
+			Ok(ref peer) if self.entrypoint_identity.as_ref().map_or(false, |pinned| *pinned != peer.identity()) => {
+				Err(LocksidianError::new(format!("The entrypoint answered as {} instead of its pinned identity", peer.identity())))
+			},
 			Ok(mut peer) => {
-				peer_cli::register(&mut peer, &repository, self.remote_addr.as_ref())?;
+				peer_cli::register(&mut peer, &repository, self.remote_addr.as_ref(), identity.hash().as_ref())?;
 				Ok(peer)
 			},
 			Err(err) => Err(LocksidianError::from_err(err))
 		}
 	}
 	
-	/// If the registration process is successfull, we gather the `Peer`s list to update our registry.
-	fn register_network_peers<T: Client>(&self, client: &T, repository: &PeerRepository) -> LocksidianResult<()> {
+	/// If the registration process is successfull, we gather the `Peer`s list to update our registry,
+	/// then register our instance with each of them: a node only accepts the blocks and inventories
+	/// sent by its registered peers.
+	fn register_network_peers<T: Client>(&self, client: &T, identity: &Identity, repository: &PeerRepository) -> LocksidianResult<()> {
 		let mut peers = client.get_peers()?;
-		peer_cli::register_batch(&mut peers, &repository, self.remote_addr.as_ref())
+		peer_cli::register_batch(&mut peers, &repository, self.remote_addr.as_ref(), identity.hash().as_ref())?;
+		
+		let key = identity.public_key_to_hex()?;
+		let current = Peer::new(key, self.addr())?.with_scheme(self.scheme())?;
+		
+		for peer in peers.iter().filter(|peer| peer.address() != self.remote_addr) {
+			match HttpClient::from_peer(&peer).register(&current, &identity) {
+				Ok(_) => (),
+				Err(err) => warn!("Unable to register onto peer {} ({}): {}", peer.identity(), peer.address(), err.description())
+			}
+		}
+		
+		Ok(())
 	}
 	
 	/// After joining the P2P network, sync the blockchain state of the entrypoint
 	fn entrypoint_sync<T: Client>(&self, client: &T, connection: &SqliteConnection) -> LocksidianResult<()> {
-		let repository = BlockRepository::new(&connection);
-		
-		match client.sync(None, &repository) {
+		match client.sync(None, &self.network, &connection) {
 			Ok(_) => Ok(()),
 			Err(_) => Ok(())
 		}
@@ -178,4 +233,9 @@ impl Server {
     pub fn addr(&self) -> String {
         self.remote_addr.clone()
     }
+
+    /// Scheme of the REST API, either `http` or `https`.
+    pub fn scheme(&self) -> &'static str {
+        tls::scheme(self.tls)
+    }
 }
\ No newline at end of file
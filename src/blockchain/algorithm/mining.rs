@@ -0,0 +1,228 @@
+//! Multi-threaded and cancellable Proof of Work mining.
+//!
+//! The nonce space of a block header is split between several worker threads: the worker `i` out
+//! of `n` tries the nonces `i`, `i + n`, `i + 2n`, etc. Every worker keeps searching until no
+//! smaller nonce than the best one found so far can be tried, so that the mined nonce is always the
+//! smallest valid one, whatever the number of workers.
+//!
+//! A mining task is bound to the mining epoch it started in: calling `cancel_mining` starts a new
+//! epoch and makes every running task stop as soon as possible (e.g. when a new `HEAD` block has
+//! been stored, making the block being mined stale).
+
+use error::*;
+use num_bigint::BigUint;
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use std::thread;
+
+/// Number of worker threads used to mine a block (`0` stands for the default value, i.e. `1`).
+static MINING_THREADS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Current mining epoch, incremented each time the running mining tasks are cancelled.
+static MINING_EPOCH: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Outcome of a mining task.
+#[derive(Debug, PartialEq)]
+pub enum Mined {
+
+    /// The `(hash, nonce)` couple having the smallest valid nonce.
+    Found(String, u32),
+
+    /// The whole `u32` nonce space was tried without satisfying the target.
+    Exhausted,
+
+    /// The task was cancelled before a valid nonce was found.
+    Cancelled
+}
+
+/// Set the number of worker threads used to mine a block.
+pub fn set_mining_threads(threads: usize) {
+    MINING_THREADS.store(threads, Ordering::SeqCst);
+}
+
+/// Returns the number of worker threads used to mine a block.
+pub fn mining_threads() -> usize {
+    match MINING_THREADS.load(Ordering::SeqCst) {
+        0 => 1,
+        threads => threads
+    }
+}
+
+/// Returns the current mining epoch.
+pub fn mining_epoch() -> usize {
+    MINING_EPOCH.load(Ordering::SeqCst)
+}
+
+/// Cancel every running mining task.
+pub fn cancel_mining() {
+    MINING_EPOCH.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Search, using `workers` threads, the smallest nonce for which the header hash returned by the
+/// `hasher` is lower than the `target`. The search is cancelled as soon as the mining epoch is no
+/// longer `epoch`.
+pub fn mine<F>(hasher: F, target: &BigUint, workers: usize, epoch: usize) -> LocksidianResult<Mined>
+    where F: Fn(u32) -> String + Send + Sync + 'static {
+    mine_in(hasher, target, workers, &MINING_EPOCH, epoch)
+}
+
+/// Mine as `mine` does, the search being cancelled as soon as the given `epochs` counter is no
+/// longer `epoch`.
+fn mine_in<F>(hasher: F, target: &BigUint, workers: usize, epochs: &'static AtomicUsize, epoch: usize) -> LocksidianResult<Mined>
+    where F: Fn(u32) -> String + Send + Sync + 'static {
+    let workers = match workers {
+        0 => 1,
+        workers => workers
+    };
+
+    let hasher = Arc::new(hasher);
+    let target = Arc::new(target.clone());
+    let found = Arc::new(AtomicBool::new(false));
+    let best: Arc<Mutex<Option<(String, u32)>>> = Arc::new(Mutex::new(None));
+
+    let handles: Vec<_> = (0..workers).map(|worker| {
+        let hasher = hasher.clone();
+        let target = target.clone();
+        let found = found.clone();
+        let best = best.clone();
+
+        thread::spawn(move || mine_stride(worker as u32, workers as u32, &*hasher, &*target, epochs, epoch, &*found, &*best))
+    }).collect();
+
+    let mut error = None;
+    for handle in handles {
+        match handle.join() {
+            Ok(Ok(())) => (),
+            Ok(Err(err)) => error = Some(err),
+            Err(_) => error = Some(LocksidianError::new(String::from("A mining worker thread panicked")))
+        }
+    }
+
+    if let Some(err) = error {
+        return Err(err);
+    }
+
+    if epochs.load(Ordering::SeqCst) != epoch {
+        return Ok(Mined::Cancelled);
+    }
+
+    let result = match best.lock() {
+        Ok(mut best) => best.take(),
+        Err(_) => return Err(LocksidianError::new(String::from("Unable to read the mining result")))
+    };
+
+    match result {
+        Some((hash, nonce)) => Ok(Mined::Found(hash, nonce)),
+        None => Ok(Mined::Exhausted)
+    }
+}
+
+/// Try the nonces `first`, `first + stride`, `first + 2 * stride`, etc. until a valid one is found,
+/// a smaller valid nonce has been found by another worker, the nonce space is exhausted or the
+/// task is cancelled.
+fn mine_stride<F>(first: u32, stride: u32, hasher: &F, target: &BigUint, epochs: &AtomicUsize, epoch: usize, found: &AtomicBool, best: &Mutex<Option<(String, u32)>>) -> LocksidianResult<()>
+    where F: Fn(u32) -> String {
+    let mut nonce = first;
+
+    loop {
+        if epochs.load(Ordering::Relaxed) != epoch {
+            return Ok(());
+        }
+
+        if found.load(Ordering::Relaxed) && !is_below_best(nonce, best) {
+            return Ok(());
+        }
+
+        let hash = hasher(nonce);
+        match BigUint::parse_bytes(hash.as_bytes(), 16) {
+            Some(value) => if value < *target {
+                submit(hash, nonce, best);
+                found.store(true, Ordering::Relaxed);
+                return Ok(());
+            },
+            None => return Err(LocksidianError::new(format!("Unable to mine the block: {} is not a valid hexadecimal hash", hash)))
+        }
+
+        nonce = match nonce.checked_add(stride) {
+            Some(next) => next,
+            None => return Ok(())
+        };
+    }
+}
+
+/// Returns `true` if `nonce` is smaller than the best valid nonce found so far.
+fn is_below_best(nonce: u32, best: &Mutex<Option<(String, u32)>>) -> bool {
+    match best.lock() {
+        Ok(best) => match *best {
+            Some((_, best_nonce)) => nonce < best_nonce,
+            None => true
+        },
+        Err(_) => false
+    }
+}
+
+/// Keep the `(hash, nonce)` couple if its nonce is smaller than the best valid nonce found so far.
+fn submit(hash: String, nonce: u32, best: &Mutex<Option<(String, u32)>>) {
+    if let Ok(mut best) = best.lock() {
+        let replace = match *best {
+            Some((_, best_nonce)) => nonce < best_nonce,
+            None => true
+        };
+
+        if replace {
+            *best = Some((hash, nonce));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use num_bigint::BigUint;
+    use sec::sha::sha512;
+
+    // Each test mines in its own epoch, so that the blocks stored by the other tests running in
+    // parallel do not cancel its mining tasks
+    static SMALLEST_NONCE_EPOCH: AtomicUsize = ATOMIC_USIZE_INIT;
+    static WORKERS_EPOCH: AtomicUsize = ATOMIC_USIZE_INIT;
+    static CANCELLED_EPOCH: AtomicUsize = ATOMIC_USIZE_INIT;
+
+    fn hasher(nonce: u32) -> String {
+        sha512(format!("locksidian{}", nonce).as_bytes())
+    }
+
+    fn target() -> BigUint {
+        BigUint::parse_bytes(format!("1{}", "0".repeat(125)).as_bytes(), 16).unwrap()
+    }
+
+    #[test]
+    fn mining_should_find_the_smallest_valid_nonce() {
+        let target = target();
+        let expected = (0..).find(|nonce| BigUint::parse_bytes(hasher(*nonce).as_bytes(), 16).unwrap() < target).unwrap();
+
+        match mine_in(hasher, &target, 1, &SMALLEST_NONCE_EPOCH, 0).unwrap() {
+            Mined::Found(hash, nonce) => {
+                assert_eq!(nonce, expected);
+                assert_eq!(hash, hasher(expected));
+            },
+            other => panic!("Unexpected mining result: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn mining_result_should_not_depend_on_the_number_of_workers() {
+        let target = target();
+
+        let single = mine_in(hasher, &target, 1, &WORKERS_EPOCH, 0).unwrap();
+        let multiple = mine_in(hasher, &target, 4, &WORKERS_EPOCH, 0).unwrap();
+
+        assert_eq!(single, multiple);
+    }
+
+    #[test]
+    fn mining_should_stop_once_the_epoch_is_over() {
+        CANCELLED_EPOCH.fetch_add(1, Ordering::SeqCst);
+        assert_eq!(mine_in(hasher, &target(), 2, &CANCELLED_EPOCH, 0).unwrap(), Mined::Cancelled);
+    }
+}
@@ -1,5 +1,11 @@
 //! `Locksidian` blockchain algorithms.
 
 mod pow;
+mod work;
+mod mining;
+mod retarget;
 
-pub use self::pow::ProofOfWork;
\ No newline at end of file
+pub use self::pow::ProofOfWork;
+pub use self::mining::{Mined, mine, mining_threads, set_mining_threads, mining_epoch, cancel_mining};
+pub use self::work::{work_to_hex, add_work};
+pub use self::retarget::{retarget, MAX_BASE_DIFFICULTY, MIN_BASE_DIFFICULTY};
\ No newline at end of file
@@ -0,0 +1,80 @@
+//! Time-based difficulty retargeting.
+//!
+//! The Proof of Work target of a block is `2^(base_difficulty - size_penalty)`. The base difficulty
+//! of the `ORIGIN` block is `512`, and is then adjusted every `window` blocks depending on the time
+//! it took to mine the previous window, compared to the timespan expected from the target block
+//! interval of the network.
+//!
+//! Each time the actual timespan is half (resp. twice) the expected one, the base difficulty is
+//! decreased (resp. increased) by one bit, doubling (resp. halving) the expected number of hashes
+//! required to mine a block. A single retargeting cannot move the base difficulty by more than
+//! `MAX_RETARGET_STEP` bits.
+
+use std::cmp;
+
+/// Base difficulty of the `ORIGIN` block, which is also the easiest base difficulty.
+pub const MAX_BASE_DIFFICULTY: u32 = 512;
+
+/// Hardest base difficulty a retargeting can lead to.
+pub const MIN_BASE_DIFFICULTY: u32 = 256;
+
+/// Maximum number of bits the base difficulty can be moved by a single retargeting.
+const MAX_RETARGET_STEP: u32 = 4;
+
+/// Compute the new base difficulty from the `base_difficulty` of the previous window, its `actual`
+/// timespan and its `expected` timespan (both in seconds).
+pub fn retarget(base_difficulty: u32, actual: u64, expected: u64) -> u32 {
+    let mut step = 0;
+
+    // The window was mined too fast: the target has to be harder to reach
+    let mut timespan = cmp::max(actual, 1);
+    while timespan.saturating_mul(2) <= expected && step < MAX_RETARGET_STEP {
+        timespan *= 2;
+        step += 1;
+    }
+
+    if step > 0 {
+        return cmp::max(base_difficulty.saturating_sub(step), MIN_BASE_DIFFICULTY);
+    }
+
+    // The window was mined too slowly: the target has to be easier to reach
+    let mut timespan = cmp::max(expected, 1);
+    while timespan.saturating_mul(2) <= actual && step < MAX_RETARGET_STEP {
+        timespan *= 2;
+        step += 1;
+    }
+
+    cmp::min(base_difficulty + step, MAX_BASE_DIFFICULTY)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn base_difficulty_should_be_kept_when_on_target() {
+        assert_eq!(500, retarget(500, 600, 600));
+        assert_eq!(500, retarget(500, 400, 600));
+        assert_eq!(500, retarget(500, 1000, 600));
+    }
+
+    #[test]
+    fn base_difficulty_should_decrease_when_blocks_are_too_fast() {
+        assert_eq!(499, retarget(500, 300, 600));
+        assert_eq!(498, retarget(500, 150, 600));
+        assert_eq!(500 - MAX_RETARGET_STEP, retarget(500, 0, 600));
+    }
+
+    #[test]
+    fn base_difficulty_should_increase_when_blocks_are_too_slow() {
+        assert_eq!(501, retarget(500, 1200, 600));
+        assert_eq!(503, retarget(500, 4800, 600));
+        assert_eq!(500 + MAX_RETARGET_STEP, retarget(500, 600000, 600));
+    }
+
+    #[test]
+    fn base_difficulty_should_stay_within_its_bounds() {
+        assert_eq!(MAX_BASE_DIFFICULTY, retarget(MAX_BASE_DIFFICULTY - 1, 600000, 600));
+        assert_eq!(MIN_BASE_DIFFICULTY, retarget(MIN_BASE_DIFFICULTY + 1, 1, 600));
+    }
+}
@@ -0,0 +1,61 @@
+//! Cumulative work arithmetic.
+//!
+//! The work of a block and the cumulative work of a chain are stored in the registry as zero-padded
+//! hexadecimal strings, so that they can be compared using a simple SQL ordering.
+
+use error::*;
+use num_bigint::BigUint;
+
+/// Width of the hexadecimal representation of a work value (640 bits).
+const WORK_HEX_WIDTH: usize = 160;
+
+/// Format the given work value as a zero-padded hexadecimal string.
+pub fn work_to_hex(work: &BigUint) -> String {
+    format!("{:0>width$}", work.to_str_radix(16), width = WORK_HEX_WIDTH)
+}
+
+/// Add the `work` of a block to the cumulative `chain_work` of its previous block.
+pub fn add_work(chain_work: &str, work: &str) -> LocksidianResult<String> {
+    let chain_work = parse_work(chain_work)?;
+    let work = parse_work(work)?;
+
+    Ok(work_to_hex(&(chain_work + work)))
+}
+
+/// Parse an hexadecimal work value.
+fn parse_work(work: &str) -> LocksidianResult<BigUint> {
+    match BigUint::parse_bytes(work.as_bytes(), 16) {
+        Some(value) => Ok(value),
+        None => Err(LocksidianError::new(format!("Unable to parse the block work: {}", work)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use num_bigint::ToBigUint;
+
+    #[test]
+    fn work_should_be_zero_padded() {
+        let work = work_to_hex(&255.to_biguint().unwrap());
+
+        assert_eq!(WORK_HEX_WIDTH, work.len());
+        assert!(work.ends_with("000ff"));
+    }
+
+    #[test]
+    fn works_should_be_added() {
+        let chain_work = work_to_hex(&255.to_biguint().unwrap());
+        let work = work_to_hex(&1.to_biguint().unwrap());
+
+        assert_eq!(work_to_hex(&256.to_biguint().unwrap()), add_work(&chain_work, &work).unwrap());
+    }
+
+    #[test]
+    fn heavier_chains_should_be_ordered_first() {
+        let light = work_to_hex(&255.to_biguint().unwrap());
+        let heavy = work_to_hex(&256.to_biguint().unwrap());
+
+        assert!(heavy > light);
+    }
+}
@@ -5,15 +5,35 @@ use error::*;
 use num::pow::checked_pow as pow;
 use num_bigint::{BigUint, ToBigUint};
 
+use std::cmp;
+use std::collections::HashSet;
+
 use sec::sha::sha512;
 use sec::hex::*;
+use sec::aes;
+use sec::rsa::Rsa;
+use sec::merkle::merkle_root;
+
+use persistence::repository::QueryRepository;
 
 use blockchain::get_current_timestamp;
-use blockchain::algorithm::ProofOfWork;
+use blockchain::algorithm::{ProofOfWork, Mined, MAX_BASE_DIFFICULTY, MIN_BASE_DIFFICULTY, work_to_hex, mine, mining_threads, mining_epoch, retarget};
+use blockchain::consensus::{ConsensusEngine, ConsensusKind};
+use blockchain::document::{Canonicalization, EncryptedDocumentDto, schema_violations};
+use blockchain::file::FileDto;
 use blockchain::identity::Identity;
+use blockchain::identity::identity_cli::compute_key_hash;
+use blockchain::network::NetworkConfig;
 
 use super::*;
 
+/// Fixed size, in bytes, of the document of an anchor used to compute its Proof of Work difficulty.
+/// This is also the maximum size of its metadata.
+const ANCHOR_SIZE: usize = 1024;
+
+/// Maximum delay, in seconds, a replicated block can be stamped ahead of the node's clock.
+const MAX_TIMESTAMP_DRIFT: u64 = 600;
+
 pub struct Block {
 	// Block data
 	data: String,
@@ -24,6 +44,12 @@ pub struct Block {
 	timestamp: u64,
 	nonce: u32,
 	previous: String,
+	base_difficulty: u32,
+	merkle_root: String,
+	canonicalization: String,
+	metadata_hash: String,
+	supersedes: String,
+	encryption: String,
 	
 	// Block Metadata
 	hash: String,
@@ -31,26 +57,147 @@ pub struct Block {
 	next: String,
 	author: String,
 	received_at: u64,
-	received_from: String
+	received_from: String,
+	work: String
 }
 
 impl Block {
 	
-	/// Instantiate a new `Block` containing an arbitrary JSON document.
-	pub fn new(data: String, author: &Identity, repository: &BlockRepository) -> LocksidianResult<Self> {
-		// Block creation timestamp
-		let timestamp = get_current_timestamp();
-		let received_at = get_current_timestamp();
+	/// Instantiate a new `Block` containing an arbitrary JSON document, canonicalised then sealed
+	/// using the rules of the network.
+	pub fn new(data: String, author: &Identity, config: &NetworkConfig, repository: &BlockRepository) -> LocksidianResult<Self> {
+		let data = config.canonicalization.apply(data.as_ref())?;
+		let data_hash = sha512(data.as_bytes());
 		
-		// Compute data hash and browse the blockchain in order to find a possible duplicate
+		Block::forge(data, data_hash, String::new(), String::new(), String::new(), String::new(), author, config, repository)
+	}
+	
+	/// Instantiate a new `Block` containing a revision of the document identified by `supersedes`,
+	/// which must have been stored by the same author. The superseded checksum is recorded in the
+	/// block header and covered by the signature of the author.
+	pub fn supersede(data: String, supersedes: String, author: &Identity, config: &NetworkConfig, repository: &BlockRepository) -> LocksidianResult<Self> {
+		Block::check_supersession(supersedes.as_ref(), author.hash().as_ref(), &repository)?;
+		
+		let data = config.canonicalization.apply(data.as_ref())?;
 		let data_hash = sha512(data.as_bytes());
-		Block::assert_document_uniqueness(data_hash.as_ref(), &repository)?;
 		
-		// Create a partial `Block` structure used to calculate the PoW algorithm
-		let signature = author.key().sign(data.as_bytes())?;
+		Block::forge(data, data_hash, String::new(), String::new(), supersedes, String::new(), author, config, repository)
+	}
+	
+	/// Instantiate a new `Block` containing the `envelope` of a document encrypted for a set of
+	/// recipients (see `EncryptedDocumentDto`), optionally superseding the document identified by
+	/// `supersedes`. The cipher of the envelope is recorded in the `encryption` field of the block
+	/// header, so that the nodes replicating the block know its document cannot be read.
+	pub fn encrypted(envelope: String, supersedes: String, author: &Identity, config: &NetworkConfig, repository: &BlockRepository) -> LocksidianResult<Self> {
+		if !supersedes.is_empty() {
+			Block::check_supersession(supersedes.as_ref(), author.hash().as_ref(), &repository)?;
+		}
+		
+		let data = config.canonicalization.apply(envelope.as_ref())?;
+		let encryption = EncryptedDocumentDto::from_envelope(data.as_ref(), aes::CIPHER)?.cipher;
+		let data_hash = sha512(data.as_bytes());
+		
+		Block::forge(data, data_hash, String::new(), String::new(), supersedes, encryption, author, config, repository)
+	}
+	
+	/// Instantiate a new `Block` anchoring a confidential document through its SHA512 checksum only:
+	/// the document never leaves the premises of its author. The `data` of the block is the optional
+	/// JSON `metadata` of the document (or an empty string), committed in the block header through
+	/// its checksum.
+	pub fn anchor(data_hash: String, metadata: String, author: &Identity, config: &NetworkConfig, repository: &BlockRepository) -> LocksidianResult<Self> {
+		Block::check_anchor(data_hash.as_ref(), metadata.as_ref())?;
+		let metadata_hash = sha512(metadata.as_bytes());
+		
+		Block::forge(metadata, data_hash, String::new(), metadata_hash, String::new(), String::new(), author, config, repository)
+	}
+	
+	/// Instantiate a new `Block` containing several JSON documents, committed in the block header
+	/// through the Merkle root of their checksums. The `data` of the block is the JSON array of the
+	/// documents.
+	///
+	/// A batch of a single document produces a regular single document `Block`. An error caused by
+	/// one of the documents names its position in the batch.
+	pub fn new_batch(documents: Vec<String>, author: &Identity, config: &NetworkConfig, repository: &BlockRepository) -> LocksidianResult<Self> {
+		if documents.len() == 1 {
+			return Block::new(documents[0].clone(), author, config, repository);
+		}
+		
+		let mut canonical = Vec::new();
+		for (index, document) in documents.iter().enumerate() {
+			match config.canonicalization.apply(document.as_ref()) {
+				Ok(document) => canonical.push(document),
+				Err(err) => return Err(LocksidianError::new(format!("Document #{} of the batch: {}", index, err.description())))
+			};
+		}
+		
+		let leaves: Vec<String> = canonical.iter().map(|document| sha512(document.as_bytes())).collect();
+		let merkle_root = match merkle_root(&leaves) {
+			Some(root) => root,
+			None => return Err(LocksidianError::new(String::from("A block cannot be created without any document")))
+		};
+		
+		match ::serde_json::to_string(&canonical) {
+			Ok(data) => {
+				let data_hash = sha512(data.as_bytes());
+				Block::forge(data, data_hash, merkle_root, String::new(), String::new(), String::new(), author, config, repository)
+			},
+			Err(err) => Err(LocksidianError::from_err(err))
+		}
+	}
+	
+	/// Sign the checksum of the data and seal a new `Block` using the consensus engine of the network.
+	///
+	/// If a new `HEAD` block is stored while sealing, the block is stale: it is built again on top
+	/// of the new `HEAD` block and sealed again.
+	fn forge(data: String, data_hash: String, merkle_root: String, metadata_hash: String, supersedes: String, encryption: String, author: &Identity, config: &NetworkConfig, repository: &BlockRepository) -> LocksidianResult<Self> {
+		let engine = config.consensus_engine()?;
+		let signature = author.key().sign(Block::signed_message(data_hash.as_ref(), supersedes.as_ref()).as_bytes())?;
+		
+		loop {
+			let epoch = mining_epoch();
+			let mut block = Block::on_head(data.clone(), data_hash.clone(), merkle_root.clone(), metadata_hash.clone(), supersedes.clone(), encryption.clone(), signature.clone(), author, config, repository)?;
+			
+			// Seal the block (e.g. compute the PoW)
+			let sealed = engine.seal(&mut block)?;
+			match (sealed, mining_epoch() == epoch) {
+				(Some((hash, nonce)), true) => {
+					block.nonce = nonce;
+					block.hash = hash;
+					block.work = work_to_hex(&engine.work(&block)?);
+					
+					// Return our complete `Block` structure
+					return Ok(block);
+				},
+				_ => info!("A new HEAD block has been stored while sealing, building the block again on top of it")
+			}
+		}
+	}
+	
+	/// Create a partial `Block` structure, built on top of the current `HEAD` block, used to seal
+	/// the block.
+	fn on_head(data: String, data_hash: String, merkle_root: String, metadata_hash: String, supersedes: String, encryption: String, signature: Vec<u8>, author: &Identity, config: &NetworkConfig, repository: &BlockRepository) -> LocksidianResult<Self> {
 		let head = repository.get_head().unwrap_or(BlockEntity::empty());
+		let height = (head.height + 1) as u64;
+		
+		// Block creation timestamp, which cannot precede the one of its parent
+		let timestamp = cmp::max(get_current_timestamp(), head.timestamp as u64);
+		let received_at = get_current_timestamp();
+		
+		// The metadata of an anchor is not canonicalised
+		let canonicalization = match metadata_hash.is_empty() {
+			true => config.canonicalization.as_str(),
+			false => ""
+		};
+		
+		// A block sealed using a guessed base difficulty would be rejected by the peers
+		let base_difficulty = match Block::expected_base_difficulty(head.hash.as_ref(), height, &config, &repository) {
+			Some(base_difficulty) => base_difficulty,
+			None => return Err(LocksidianError::new(format!(
+				"Unable to compute the base difficulty of a block built on top of {}: some of its ancestors are missing", head.hash
+			)))
+		};
 		
-		let mut block = Block {
+		let block = Block {
 			data: data,
 			
 			data_hash: data_hash,
@@ -58,21 +205,27 @@ impl Block {
 			timestamp: timestamp,
 			nonce: 0,
 			previous: head.hash,
+			base_difficulty: base_difficulty,
+			merkle_root: merkle_root,
+			canonicalization: String::from(canonicalization),
+			metadata_hash: metadata_hash,
+			supersedes: supersedes,
+			encryption: encryption,
 			
 			hash: String::new(),
-			height: (head.height + 1) as u64,
+			height: height,
 			next: String::new(),
 			author: author.hash(),
 			received_at: received_at,
-			received_from: author.hash()
+			received_from: author.hash(),
+			work: String::new()
 		};
-
-		// Compute the PoW
-		let (hash, nonce) = block.compute()?;
-		block.nonce = nonce;
-		block.hash = hash;
-
-		// Return our complete `Block` structure
+		
+		// Browse the blockchain in order to find a possible duplicate of the documents
+		for data_hash in block.document_hashes()? {
+			Block::assert_document_uniqueness(data_hash.as_ref(), &repository)?;
+		}
+		
 		Ok(block)
 	}
 
@@ -87,13 +240,51 @@ impl Block {
 				timestamp: entity.timestamp as u64,
 				nonce: entity.nonce as u32,
 				previous: entity.previous,
+				base_difficulty: entity.base_difficulty as u32,
+				merkle_root: entity.merkle_root,
+				canonicalization: entity.canonicalization,
+				metadata_hash: entity.metadata_hash,
+				supersedes: entity.supersedes,
+				encryption: entity.encryption,
 
 				hash: entity.hash,
 				height: entity.height as u64,
 				next: entity.next,
 				author: entity.author,
 				received_at: entity.received_at as u64,
-				received_from: entity.received_from
+				received_from: entity.received_from,
+				work: entity.work
+			}),
+			Err(err) => Err(LocksidianError::from_err(err))
+		}
+	}
+	
+	/// Adapt a `BlockHeaderDto` into a `Block` structure holding the given `data`, which may be empty
+	/// when only the header of the block is known.
+	pub fn from_header(header: &BlockHeaderDto, data: String) -> LocksidianResult<Self> {
+		match header.signature.from_hex() {
+			Ok(signature) => Ok(Block {
+				data: data,
+				
+				data_hash: header.data_hash.clone(),
+				signature: signature,
+				timestamp: header.timestamp,
+				nonce: header.nonce,
+				previous: header.previous.clone(),
+				base_difficulty: header.base_difficulty,
+				merkle_root: header.merkle_root.clone(),
+				canonicalization: header.canonicalization.clone(),
+				metadata_hash: header.metadata_hash.clone(),
+				supersedes: header.supersedes.clone(),
+				encryption: header.encryption.clone(),
+				
+				hash: header.hash.clone(),
+				height: header.height,
+				next: String::new(),
+				author: header.author.clone(),
+				received_at: 0,
+				received_from: String::new(),
+				work: String::new()
 			}),
 			Err(err) => Err(LocksidianError::from_err(err))
 		}
@@ -110,13 +301,20 @@ impl Block {
 				timestamp: dto.timestamp,
 				nonce: dto.nonce,
 				previous: dto.previous,
+				base_difficulty: dto.base_difficulty,
+				merkle_root: dto.merkle_root,
+				canonicalization: dto.canonicalization,
+				metadata_hash: dto.metadata_hash,
+				supersedes: dto.supersedes,
+				encryption: dto.encryption,
 				
 				hash: dto.hash,
 				height: dto.height,
 				next: dto.next,
 				author: dto.author,
 				received_at: get_current_timestamp(),
-				received_from: received_from.unwrap_or(&dto.received_from).clone()
+				received_from: received_from.unwrap_or(&dto.received_from).clone(),
+				work: String::new()
 			}),
 			Err(err) => Err(LocksidianError::from_err(err))
 		}
@@ -124,27 +322,194 @@ impl Block {
 	
 	/// Create a new `Block` structure from the replication data provided by one of the network peers
 	/// through the use of a `BlockReplicationDto`.
-	pub fn replicate_from(dto: BlockReplicationDto, repository: &BlockRepository) -> LocksidianResult<Self> {
-		let replica = Block::partial_replica(dto)?;
-		replica.integrity_check(&repository)?;
+	///
+	/// The `author_key` is the public key of the block author, used to verify the block signature.
+	/// The documents of the block must match the JSON Schemas of the network, so that a misconfigured
+	/// peer cannot slip other documents in. A superseded document must have the same author, and must
+	/// be known once the ancestors of the block are linked (see `check_revision`).
+	pub fn replicate_from(dto: BlockReplicationDto, author_key: &Rsa, config: &NetworkConfig, repository: &BlockRepository) -> LocksidianResult<Self> {
+		let mut replica = Block::partial_replica(dto)?;
+		replica.check_schemas(&config)?;
+		
+		let linked = replica.has_linked_ancestors(&repository);
+		replica.check_revision(linked, &repository)?;
+		
+		replica.admit(&author_key, &config, &repository)?;
 		
 		Ok(replica)
 	}
 	
+	/// Create a new `Block` structure from a `BlockDto` fetched from one of the network peers while
+	/// syncing the blockchain.
+	pub fn sync_from(dto: BlockDto, received_from: Option<&String>, author_key: &Rsa, config: &NetworkConfig, repository: &BlockRepository) -> LocksidianResult<Self> {
+		let mut block = Block::from_dto(dto, received_from)?;
+		block.admit(&author_key, &config, &repository)?;
+		
+		Ok(block)
+	}
+	
+	/// Perform an integrity check of a `Block` received from the network, then compute its work
+	/// using the consensus engine of the network.
+	fn admit(&mut self, author_key: &Rsa, config: &NetworkConfig, repository: &BlockRepository) -> LocksidianResult<()> {
+		let engine = config.consensus_engine()?;
+		
+		self.integrity_check(&author_key, &*engine, &config, &repository)?;
+		self.work = work_to_hex(&engine.work(&self)?);
+		
+		Ok(())
+	}
+	
 	/// Perform an integrity check of the provided `Block`, namely:
 	///
 	/// - Recompute and check the validity of the document checksum;
-	/// - Assert the uniqueness of the JSON document stored into this `Block`;
-	/// - Validate the Proof of Work.
-	pub fn integrity_check(&self, repository: &BlockRepository) -> LocksidianResult<()> {
-		let data_hash = self.check_data_hash()?;
+	/// - Verify the block signature against the public key of its author;
+	/// - Check the Merkle root and size of a batch of documents;
+	/// - Check that the documents are canonicalised according to the mode recorded in the block, which
+	///   must be the mode of the network;
+	/// - Check that only a single document block supersedes a document or is encrypted;
+	/// - Assert the uniqueness of the JSON documents stored into this `Block`;
+	/// - Validate the block hash and the consensus rules of the network (e.g. its Proof of Work);
+	/// - Check that the block is not stamped in the future, nor before its parent;
+	/// - Check the base difficulty against the retargeting rule of the network;
+	/// - Enforce the replication depth of the network.
+	fn integrity_check(&self, author_key: &Rsa, engine: &ConsensusEngine, config: &NetworkConfig, repository: &BlockRepository) -> LocksidianResult<()> {
+		self.check_data_hash()?;
+		self.check_signature(&author_key, &config)?;
+		
+		let data_hashes = self.check_merkle_root()?;
+		self.check_canonicalization()?;
+		self.check_canonicalization_mode(&config)?;
+		self.check_supersedes()?;
+		self.check_encryption()?;
+		if data_hashes.len() as u64 > config.max_batch_size {
+			return Err(LocksidianError::new(format!(
+				"Block stores {} documents, the maximum being {}", data_hashes.len(), config.max_batch_size
+			)));
+		}
+		
+		let mut unique: HashSet<&String> = HashSet::new();
+		for data_hash in data_hashes.iter() {
+			if !unique.insert(data_hash) {
+				return Err(LocksidianError::new(format!("Document hash {} is stored several times in the block", data_hash)));
+			}
+			
+			Block::assert_document_uniqueness(data_hash.as_ref(), &repository)?;
+		}
 		
-		Block::assert_document_uniqueness(data_hash.as_ref(), &repository)?;
-		self.validate()?;
+		self.check_hash()?;
+		engine.verify(&self)?;
+		self.check_timestamp(get_current_timestamp(), &repository)?;
+		self.check_base_difficulty(&config, &repository)?;
+		self.check_replication_depth(&config, &repository)?;
 		
 		Ok(())
 	}
 	
+	/// Verify a `Block` that is already stored in the registry: its document checksum, its Merkle
+	/// root, the canonicalisation of its documents, the structure of a revision, its hash, the
+	/// consensus rules of the network and its signature are all recomputed and checked.
+	pub fn verify(&self, author_key: &Rsa, engine: &ConsensusEngine, config: &NetworkConfig) -> LocksidianResult<()> {
+		self.check_data_hash()?;
+		self.check_merkle_root()?;
+		self.check_canonicalization()?;
+		self.check_supersedes()?;
+		self.check_encryption()?;
+		self.check_hash()?;
+		engine.verify(&self)?;
+		self.check_signature(&author_key, &config)
+	}
+	
+	/// Returns an error if the provided `author_key` does not belong to the block author, or if the block
+	/// signature cannot be verified using this key.
+	///
+	/// The signature covers the `data_hash` (along with the superseded checksum of a revision), so that
+	/// it can be verified without the data. The blocks created by older nodes have their `data` signed
+	/// instead: such a signature is only accepted up to the `legacy_signature_height` of the network,
+	/// as any string signed by the author (e.g. a registration challenge) would pass for the data of
+	/// a block otherwise.
+	pub fn check_signature(&self, author_key: &Rsa, config: &NetworkConfig) -> LocksidianResult<()> {
+		if compute_key_hash(&author_key)? != self.author {
+			return Err(LocksidianError::new(format!("The provided public key does not belong to the block author {}", self.author)));
+		}
+		
+		let signed = match self.is_data_hash_signed(&author_key) {
+			Ok(true) => Ok(true),
+			Ok(false) if self.supersedes.is_empty() && self.height <= config.legacy_signature_height => {
+				author_key.verify_signature(self.data.as_bytes(), self.signature())
+			},
+			Ok(false) => Ok(false),
+			Err(err) => Err(err)
+		};
+		
+		match signed {
+			Ok(true) => Ok(()),
+			Ok(false) => Err(LocksidianError::new(format!("Block signature does not match the author {}", self.author))),
+			Err(err) => Err(LocksidianError::new(format!("Unable to verify the block signature: {}", err.description())))
+		}
+	}
+	
+	/// Returns `true` if the block signature covers its `data_hash`, `false` if it is a block created
+	/// by an older node, whose signature covers its `data`.
+	pub fn is_data_hash_signed(&self, author_key: &Rsa) -> LocksidianResult<bool> {
+		let message = Block::signed_message(self.data_hash.as_ref(), self.supersedes.as_ref());
+		author_key.verify_signature(message.as_bytes(), self.signature())
+	}
+	
+	/// Message signed by the author of a block: `BLOCK` followed, on a second line, by its
+	/// `data_hash` and the checksum of the document it `supersedes`, if any. The prefix separates the
+	/// block signatures from the other messages signed by an identity (e.g. the node requests).
+	pub fn signed_message(data_hash: &str, supersedes: &str) -> String {
+		format!("BLOCK\n{}{}", data_hash, supersedes)
+	}
+	
+	/// Returns an error if the document superseded by the block was not stored by the block author,
+	/// or has already been superseded by another main chain block.
+	///
+	/// The superseded document may be stored in an ancestor of the block that is not known yet (e.g.
+	/// while syncing the blockchain from its `HEAD` block): an unknown document is only an error once
+	/// the ancestors are `linked`, see `check_linked`.
+	fn check_revision(&self, linked: bool, repository: &BlockRepository) -> LocksidianResult<()> {
+		if self.supersedes.is_empty() {
+			return Ok(());
+		}
+		
+		match repository.locate_document(self.supersedes.as_ref()) {
+			Some((ref entity, _)) if entity.author == self.author => (),
+			Some(_) => return Err(LocksidianError::new(format!("Only the author of document {} can supersede it", self.supersedes))),
+			None if !linked => return Ok(()),
+			None => return Err(LocksidianError::new(format!("The superseded document {} is unknown", self.supersedes)))
+		};
+		
+		match repository.get_revisions(self.supersedes.as_ref())?.iter().find(|entity| entity.hash != self.hash) {
+			Some(entity) => Err(LocksidianError::new(format!("Document {} has already been superseded in block {}", self.supersedes, entity.hash))),
+			None => Ok(())
+		}
+	}
+	
+	/// Returns `true` if the previous block is linked to the chain, so that every ancestor of the
+	/// block is known.
+	fn has_linked_ancestors(&self, repository: &BlockRepository) -> bool {
+		match self.previous.is_empty() {
+			true => true,
+			false => repository.get(&self.previous).map(|previous| !previous.chain_work.is_empty()).unwrap_or(false)
+		}
+	}
+	
+	/// Returns an error if the document identified by `supersedes` is not stored in the registry, was
+	/// not stored by `author`, or has already been superseded by a main chain block.
+	pub fn check_supersession(supersedes: &str, author: &str, repository: &BlockRepository) -> LocksidianResult<()> {
+		match repository.locate_document(supersedes) {
+			Some((ref entity, _)) if entity.author == author => (),
+			Some(_) => return Err(LocksidianError::new(format!("Only the author of document {} can supersede it", supersedes))),
+			None => return Err(LocksidianError::new(format!("The superseded document {} is unknown", supersedes)))
+		};
+		
+		match repository.get_superseding(supersedes) {
+			Some(entity) => Err(LocksidianError::new(format!("Document {} has already been superseded in block {}", supersedes, entity.hash))),
+			None => Ok(())
+		}
+	}
+	
 	/// Create a partial `Block` replica from a `BlockReplicationDto`.
 	fn partial_replica(dto: BlockReplicationDto) -> LocksidianResult<Self> {
 		match dto.signature.from_hex() {
@@ -156,42 +521,366 @@ impl Block {
 				timestamp: dto.timestamp,
 				nonce: dto.nonce,
 				previous: dto.previous,
+				base_difficulty: dto.base_difficulty,
+				merkle_root: dto.merkle_root,
+				canonicalization: dto.canonicalization,
+				metadata_hash: dto.metadata_hash,
+				supersedes: dto.supersedes,
+				encryption: dto.encryption,
 				
 				hash: dto.hash,
 				height: dto.height,
 				next: String::new(),
 				author: dto.author,
 				received_at: get_current_timestamp(),
-				received_from: dto.received_from
+				received_from: dto.received_from,
+				work: String::new()
 			}),
 			Err(err) => Err(LocksidianError::from_err(err))
 		}
 	}
 	
 	/// Returns an error if the recomputed data checksum does not match the stored `data_hash`.
+	///
+	/// The `data_hash` of an anchor cannot be recomputed: its metadata checksum is checked instead.
 	fn check_data_hash(&self) -> LocksidianResult<String> {
+		if self.is_anchor() {
+			Block::check_anchor(self.data_hash.as_ref(), self.data.as_ref())?;
+			
+			return match self.merkle_root.is_empty() && self.metadata_hash == sha512(self.data.as_bytes()) {
+				true => Ok(self.data_hash.clone()),
+				false => Err(LocksidianError::new(String::from("Block metadata_hash does not match the recomputed metadata checksum")))
+			};
+		}
+		
 		let recomputed_data_hash = sha512(self.data.as_bytes());
 		
 		match self.data_hash == recomputed_data_hash {
 			true => Ok(recomputed_data_hash),
-			false => Err(LocksidianError::new(String::from("Replica data_hash does not match the recomputed data checksum")))
+			false => Err(LocksidianError::new(String::from("Block data_hash does not match the recomputed data checksum")))
+		}
+	}
+	
+	/// Returns an error if the Merkle root of a batch block does not match the one recomputed from
+	/// its documents, or if the batch holds less than two documents. The checksums of the documents
+	/// stored in the block are returned.
+	fn check_merkle_root(&self) -> LocksidianResult<Vec<String>> {
+		let data_hashes = self.document_hashes()?;
+		
+		if self.merkle_root.is_empty() {
+			return Ok(data_hashes);
+		}
+		
+		if data_hashes.len() < 2 {
+			return Err(LocksidianError::new(String::from("A batch block should store at least two documents")));
+		}
+		
+		match merkle_root(&data_hashes) {
+			Some(ref root) if *root == self.merkle_root => Ok(data_hashes),
+			_ => Err(LocksidianError::new(String::from("Block merkle_root does not match the recomputed documents Merkle root")))
 		}
 	}
 	
-	/// Returns an `Error` if the specified document hash does exist in the local registry.
-	fn assert_document_uniqueness(data_hash: &str, repository: &BlockRepository) -> LocksidianResult<()> {
-		match repository.get_by_data_hash(data_hash) {
-			Some(entity) => Err(LocksidianError::new(
+	/// Returns an error if the canonicalisation mode recorded in the block is unknown, or if one of
+	/// its documents is not in canonical form.
+	fn check_canonicalization(&self) -> LocksidianResult<()> {
+		let mode = Canonicalization::from_str(self.canonicalization.as_ref())?;
+		
+		for document in self.documents()? {
+			if mode.apply(document.as_ref())? != document {
+				return Err(LocksidianError::new(format!("Block {} stores a document that is not canonicalised", self.hash)));
+			}
+		}
+		
+		Ok(())
+	}
+	
+	/// Returns an error if the canonicalisation mode recorded in the block is not the mode of the
+	/// network. The metadata of an anchor is never canonicalised.
+	fn check_canonicalization_mode(&self, config: &NetworkConfig) -> LocksidianResult<()> {
+		let expected = match self.is_anchor() {
+			true => Canonicalization::Raw,
+			false => config.canonicalization
+		};
+		
+		match Canonicalization::from_str(self.canonicalization.as_ref())? == expected {
+			true => Ok(()),
+			false => Err(LocksidianError::new(format!(
+				"Block {} canonicalization mode \"{}\" does not match the network mode \"{}\"", self.hash, self.canonicalization, expected.as_str()
+			)))
+		}
+	}
+	
+	/// Returns an error if a batch block or an anchor supersedes a document: a revision is always
+	/// stored in its own block.
+	fn check_supersedes(&self) -> LocksidianResult<()> {
+		match self.supersedes.is_empty() || (self.merkle_root.is_empty() && !self.is_anchor()) {
+			true => Ok(()),
+			false => Err(LocksidianError::new(format!("Block {} cannot supersede a document", self.hash)))
+		}
+	}
+	
+	/// Returns an error if a batch block or an anchor is flagged as encrypted: an encrypted document
+	/// is always stored in its own block.
+	fn check_encryption(&self) -> LocksidianResult<()> {
+		match self.encryption.is_empty() || (self.merkle_root.is_empty() && !self.is_anchor()) {
+			true => Ok(()),
+			false => Err(LocksidianError::new(format!("Block {} cannot store an encrypted document", self.hash)))
+		}
+	}
+	
+	/// Returns an error if one of the documents of the block does not match the JSON Schemas of the
+	/// network.
+	///
+	/// The schemas apply to the plaintext of the encrypted documents, which cannot be read here: the
+	/// document of a block flagged as encrypted must be a well-formed envelope instead.
+	fn check_schemas(&self, config: &NetworkConfig) -> LocksidianResult<()> {
+		for document in self.documents()? {
+			if self.is_encrypted() {
+				EncryptedDocumentDto::from_envelope(document.as_ref(), self.encryption.as_ref())?;
+				continue;
+			}
+			
+			let violations = schema_violations(document.as_ref(), &config.schemas);
+			
+			if !violations.is_empty() {
+				let details: Vec<String> = violations.iter()
+					.map(|violation| format!("{} {}: {}", violation.schema, violation.path, violation.message))
+					.collect();
+				
+				return Err(LocksidianError::new(format!(
+					"Block {} stores a document that does not match the network schemas: {}", self.hash, details.join("; ")
+				)));
+			}
+		}
+		
+		Ok(())
+	}
+	
+	/// Returns an error if `data_hash` is not a SHA512 checksum, or if the `metadata` of an anchor is
+	/// neither empty nor a JSON document of at most `ANCHOR_SIZE` bytes.
+	pub fn check_anchor(data_hash: &str, metadata: &str) -> LocksidianResult<()> {
+		if data_hash.len() != 128 || !data_hash.chars().all(|c| c.is_digit(16) && !c.is_uppercase()) {
+			return Err(LocksidianError::new(format!("{} is not a lowercase hexadecimal SHA512 checksum", data_hash)));
+		}
+		
+		if metadata.len() > ANCHOR_SIZE {
+			return Err(LocksidianError::new(format!("Anchor metadata cannot exceed {} bytes", ANCHOR_SIZE)));
+		}
+		
+		match metadata.is_empty() || ::serde_json::from_str::<::serde_json::Value>(metadata).is_ok() {
+			true => Ok(()),
+			false => Err(LocksidianError::new(String::from("Anchor metadata should be a JSON document")))
+		}
+	}
+	
+	/// Returns the JSON documents stored in the block: either its `data`, or the documents of the
+	/// JSON array stored in the `data` of a batch block. An anchor stores no document.
+	pub fn documents(&self) -> LocksidianResult<Vec<String>> {
+		if self.is_anchor() {
+			return Ok(Vec::new());
+		}
+		
+		match self.merkle_root.is_empty() {
+			true => Ok(vec![self.data.clone()]),
+			false => match ::serde_json::from_str::<Vec<String>>(self.data.as_ref()) {
+				Ok(documents) => Ok(documents),
+				Err(err) => Err(LocksidianError::new(format!("Invalid batch block data: {}", err)))
+			}
+		}
+	}
+	
+	/// Returns the checksums of the JSON documents stored in the block, in their storage order.
+	pub fn document_hashes(&self) -> LocksidianResult<Vec<String>> {
+		match self.merkle_root.is_empty() {
+			true => Ok(vec![self.data_hash.clone()]),
+			false => Ok(self.documents()?.iter().map(|document| sha512(document.as_bytes())).collect())
+		}
+	}
+	
+	/// Returns an error if the recomputed block header checksum does not match the stored `hash`.
+	fn check_hash(&self) -> LocksidianResult<()> {
+		match self.hash == self.calculate_hash() {
+			true => Ok(()),
+			false => Err(LocksidianError::new(String::from("Block hash does not match the recomputed block header checksum")))
+		}
+	}
+	
+	/// Check the header of a `Block` whose data may not be known (e.g. while verifying a receipt
+	/// offline): its hash and, on a Proof of Work network, its Proof of Work. Without the data, the
+	/// size penalty cannot be recomputed and the hash is only checked against the target of the
+	/// base difficulty.
+	pub fn check_header(&self, consensus: &ConsensusKind) -> LocksidianResult<()> {
+		self.check_hash()?;
+		self.check_base_difficulty_bounds()?;
+		
+		let difficulty = match (consensus, self.data.is_empty() && !self.is_anchor()) {
+			(&ConsensusKind::ProofOfAuthority, _) => return Ok(()),
+			(&ConsensusKind::ProofOfWork, true) => self.base_difficulty as usize,
+			(&ConsensusKind::ProofOfWork, false) => self.difficulty()?
+		};
+		
+		match self.validate_with_target(&self.target(difficulty)?)? {
+			Some(_) => Ok(()),
+			None => Err(LocksidianError::new(format!("Block {} does not satisfy its Proof of Work", self.hash)))
+		}
+	}
+	
+	/// Returns an error if the base difficulty of the block is out of the bounds of the retargeting
+	/// rule, or does not match the one recomputed from its ancestors. The latter check is skipped when
+	/// these ancestors are not known yet (e.g. while syncing the blockchain from its `HEAD` block): it
+	/// is performed once the block is linked to the chain, see `check_linked`.
+	pub fn check_base_difficulty(&self, config: &NetworkConfig, repository: &BlockRepository) -> LocksidianResult<()> {
+		self.check_base_difficulty_bounds()?;
+		
+		match Block::expected_base_difficulty(self.previous.as_ref(), self.height, &config, &repository) {
+			Some(expected) if expected != self.base_difficulty => Err(LocksidianError::new(format!(
+				"Block base difficulty {} does not match the expected base difficulty {}", self.base_difficulty, expected
+			))),
+			_ => Ok(())
+		}
+	}
+	
+	/// Returns an error if the base difficulty of the block cannot result from the retargeting rule:
+	/// a base difficulty of `MAX_BASE_DIFFICULTY` or more would require no work at all.
+	fn check_base_difficulty_bounds(&self) -> LocksidianResult<()> {
+		match self.base_difficulty >= MIN_BASE_DIFFICULTY && self.base_difficulty <= MAX_BASE_DIFFICULTY {
+			true => Ok(()),
+			false => Err(LocksidianError::new(format!(
+				"Block base difficulty {} is out of the [{}, {}] bounds", self.base_difficulty, MIN_BASE_DIFFICULTY, MAX_BASE_DIFFICULTY
+			)))
+		}
+	}
+	
+	/// Returns an error if the block is stamped more than `MAX_TIMESTAMP_DRIFT` seconds ahead of
+	/// `now`, or before its parent: a miner could otherwise stretch the timestamps of a retargeting
+	/// window and lower the base difficulty. The latter check is skipped when the parent is not known
+	/// yet: it is performed once the block is linked to the chain, see `check_linked`.
+	pub fn check_timestamp(&self, now: u64, repository: &BlockRepository) -> LocksidianResult<()> {
+		if self.timestamp > now + MAX_TIMESTAMP_DRIFT {
+			return Err(LocksidianError::new(format!(
+				"Block timestamp {} is more than {} seconds ahead of the node's clock", self.timestamp, MAX_TIMESTAMP_DRIFT
+			)));
+		}
+		
+		match repository.get(&self.previous) {
+			Some(ref parent) if (parent.timestamp as u64) > self.timestamp => Err(LocksidianError::new(format!(
+				"Block timestamp {} precedes the timestamp {} of its parent", self.timestamp, parent.timestamp
+			))),
+			_ => Ok(())
+		}
+	}
+	
+	/// Perform the checks of a stored `Block` that require its ancestors, which are skipped when the
+	/// block is received before them. Called once the block is linked to the chain.
+	pub fn check_linked(&self, config: &NetworkConfig, repository: &BlockRepository) -> LocksidianResult<()> {
+		self.check_timestamp(get_current_timestamp(), &repository)?;
+		self.check_base_difficulty(&config, &repository)?;
+		self.check_revision(true, &repository)
+	}
+	
+	/// Compute the base difficulty expected for a block at the given `height`, built on top of the
+	/// `previous` block, by applying the retargeting rule of the network to the timestamps of its
+	/// ancestors. Returns `None` if the required ancestors are missing from the registry.
+	pub fn expected_base_difficulty(previous: &str, height: u64, config: &NetworkConfig, repository: &BlockRepository) -> Option<u32> {
+		if previous.is_empty() || !config.is_retargeting() {
+			return Some(MAX_BASE_DIFFICULTY);
+		}
+		
+		let parent = match repository.get(&String::from(previous)) {
+			Some(parent) => parent,
+			None => return None
+		};
+		
+		// The difficulty is only retargeted at the beginning of each window
+		let window = config.retarget_window;
+		if height <= window || (height - 1) % window != 0 {
+			return Some(parent.base_difficulty as u32);
+		}
+		
+		let mut first = parent.clone();
+		for _ in 1..window {
+			first = match repository.get(&first.previous) {
+				Some(ancestor) => ancestor,
+				None => return None
+			};
+		}
+		
+		let actual = (parent.timestamp as u64).saturating_sub(first.timestamp as u64);
+		let expected = (window - 1) * config.target_block_interval;
+		
+		Some(retarget(parent.base_difficulty as u32, actual, expected))
+	}
+	
+	/// Enforce the replication policy of the network: a block whose `previous` block already has a
+	/// `next` block belongs to a fork, and is rejected if it lies more than `max_replication_depth`
+	/// blocks behind the current `HEAD` block. Otherwise, it will be stored as an orphan block.
+	fn check_replication_depth(&self, config: &NetworkConfig, repository: &BlockRepository) -> LocksidianResult<()> {
+		match repository.get(&self.previous) {
+			Some(ref previous) if !previous.next.is_empty() && previous.next != self.hash => (),
+			_ => return Ok(())
+		};
+		
+		let head_height = match repository.get_head() {
+			Some(head) => head.height as u64,
+			None => return Ok(())
+		};
+		
+		match Block::is_too_far_behind(self.height, head_height, config.max_replication_depth) {
+			true => Err(LocksidianError::new(format!(
+				"Block {} is too far behind the HEAD block: {} blocks behind, the maximum being {}",
+				self.hash, head_height - self.height, config.max_replication_depth
+			))),
+			false => Ok(())
+		}
+	}
+	
+	/// Returns `true` if a block at the given `height` lies more than `max_depth` blocks behind the
+	/// `HEAD` block.
+	fn is_too_far_behind(height: u64, head_height: u64, max_depth: u64) -> bool {
+		head_height > height && head_height - height > max_depth
+	}
+	
+	/// Returns an `Error` if the specified document hash is stored on the main chain of the local
+	/// registry. A document only stored in orphan blocks can be stored again.
+	pub fn assert_document_uniqueness(data_hash: &str, repository: &BlockRepository) -> LocksidianResult<()> {
+		match repository.locate_document(data_hash) {
+			Some((ref entity, _)) if !entity.orphan => Err(LocksidianError::new(
 				format!("Document hash {} is already stored in block {}", data_hash, entity.hash)
 			)),
-			None => Ok(())
+			_ => Ok(())
 		}
 	}
 	
 	/// Calculate the current `Block` hash.
-	fn calculate_hash(&self) -> String {
-		let pow_buffer = format!("{}{}{}{}{}", self.data_hash, self.signature.to_hex(), self.timestamp(), self.nonce, self.previous);
-		sha512(pow_buffer.as_bytes())
+	pub fn calculate_hash(&self) -> String {
+		let (before_nonce, after_nonce) = self.header_parts();
+		sha512(format!("{}{}{}", before_nonce, self.nonce, after_nonce).as_bytes())
+	}
+	
+	/// Split the block header hashed by the Proof of Work into the fields placed before and after
+	/// the nonce. The base difficulty is only part of the header once retargeted, the Merkle root
+	/// only for batch blocks, the canonicalization mode only for canonicalised documents, the
+	/// metadata checksum only for anchors, the superseded checksum only for revisions and the cipher
+	/// only for encrypted documents, so that the hash of the other blocks remains unchanged.
+	fn header_parts(&self) -> (String, String) {
+		let before_nonce = format!("{}{}{}", self.data_hash, self.signature.to_hex(), self.timestamp());
+		
+		let mut after_nonce = self.previous.clone();
+		if self.base_difficulty != MAX_BASE_DIFFICULTY {
+			after_nonce.push_str(self.base_difficulty.to_string().as_ref());
+		}
+		after_nonce.push_str(self.merkle_root.as_ref());
+		after_nonce.push_str(self.canonicalization.as_ref());
+		after_nonce.push_str(self.metadata_hash.as_ref());
+		if !self.supersedes.is_empty() {
+			after_nonce.push_str(format!("supersedes{}", self.supersedes).as_str());
+		}
+		if !self.encryption.is_empty() {
+			after_nonce.push_str(format!("encryption{}", self.encryption).as_str());
+		}
+		
+		(before_nonce, after_nonce)
 	}
 	
 	/// If the provided `pow_value` (representing the decimal value of `pow_hash`) is lower than the
@@ -243,6 +932,46 @@ impl Block {
 		self.previous.clone()
 	}
 
+	/// `base_difficulty` getter.
+	pub fn base_difficulty(&self) -> u32 {
+		self.base_difficulty
+	}
+
+	/// `merkle_root` getter.
+	pub fn merkle_root(&self) -> String {
+		self.merkle_root.clone()
+	}
+
+	/// `canonicalization` getter.
+	pub fn canonicalization(&self) -> String {
+		self.canonicalization.clone()
+	}
+	
+	/// `metadata_hash` getter.
+	pub fn metadata_hash(&self) -> String {
+		self.metadata_hash.clone()
+	}
+	
+	/// `supersedes` getter.
+	pub fn supersedes(&self) -> String {
+		self.supersedes.clone()
+	}
+	
+	/// `encryption` getter.
+	pub fn encryption(&self) -> String {
+		self.encryption.clone()
+	}
+	
+	/// Is the document of the block encrypted for a set of recipients?
+	pub fn is_encrypted(&self) -> bool {
+		!self.encryption.is_empty()
+	}
+	
+	/// Is the block anchoring a confidential document through its checksum only?
+	pub fn is_anchor(&self) -> bool {
+		!self.metadata_hash.is_empty()
+	}
+	
 	/// `hash` getter.
 	pub fn hash(&self) -> String {
 		self.hash.clone()
@@ -272,18 +1001,37 @@ impl Block {
 	pub fn received_from(&self) -> String {
 		self.received_from.clone()
 	}
+
+	/// `work` getter.
+	pub fn work(&self) -> String {
+		self.work.clone()
+	}
 }
 
 impl ProofOfWork for Block {
 
-	/// Calculate the Proof of Work difficulty for the given `Block`.
+	/// Calculate the Proof of Work difficulty for the given `Block`: its (retargeted) base
+	/// difficulty, minus a penalty depending on the size of its data. The penalty of a batch block
+	/// depends on its largest document, and the one of an anchor on a fixed size. The record of a
+	/// certified file is further penalised depending on the size of the file.
 	fn difficulty(&self) -> LocksidianResult<usize> {
-		let base = 512;
+		let base = self.base_difficulty as usize;
 		let divider = 32;
+		let penalty = |document: &String| match FileDto::parse(document.as_str()) {
+			Some(file) => document.len() / divider + file.penalty(),
+			None => document.len() / divider
+		};
 
-		let difficulty = base - self.data().len() / divider;
+		let penalty = match (self.is_anchor(), self.merkle_root.is_empty()) {
+			(true, _) => ANCHOR_SIZE / divider,
+			(false, true) => penalty(&self.data),
+			(false, false) => self.documents()?.iter().map(penalty).max().unwrap_or(0)
+		};
 
-		Ok(difficulty)
+		match base.checked_sub(penalty) {
+			Some(difficulty) => Ok(difficulty),
+			None => Err(LocksidianError::new(String::from("Unable to compute block's PoW: the document is too large")))
+		}
 	}
 
 	/// Calculate the Proof of Work target based on the given `difficulty` factor.
@@ -299,21 +1047,30 @@ impl ProofOfWork for Block {
 		}
 	}
 
-	/// Compute the `Block` nonce using the proof of work algorithm.
-	fn compute(&mut self) -> LocksidianResult<(String, u32)> {
+	/// Compute the `Block` nonce using the proof of work algorithm, on as many threads as
+	/// configured. When the whole nonce space is exhausted, the block timestamp is increased in
+	/// order to get a new header to mine. Returns `None` if mining was cancelled.
+	fn compute(&mut self) -> LocksidianResult<Option<(String, u32)>> {
+		let epoch = mining_epoch();
 		let difficulty = self.difficulty()?;
 		let target = self.target(difficulty)?;
-		self.signature = self.signature().to_vec();
-
-		self.nonce = 0;
 
 		loop {
-			match self.validate_with_target(&target) {
-				Ok(Some(result)) => return Ok(result),
-				Ok(None) => { self.nonce += 1; },
-				Err(err) => return Err(LocksidianError::from_err(err))
+			let (before_nonce, after_nonce) = self.header_parts();
+			let hasher = move |nonce: u32| sha512(format!("{}{}{}", before_nonce, nonce, after_nonce).as_bytes());
+
+			match mine(hasher, &target, mining_threads(), epoch)? {
+				Mined::Found(hash, nonce) => {
+					self.nonce = nonce;
+					return Ok(Some((hash, nonce)));
+				},
+				Mined::Exhausted => {
+					self.timestamp += 1;
+					warn!("Nonce space exhausted, mining again using the {} timestamp", self.timestamp);
+				},
+				Mined::Cancelled => return Ok(None)
 			}
-		};
+		}
 	}
 
 	fn validate(&self) -> LocksidianResult<Option<(String, u32)>> {
@@ -322,6 +1079,16 @@ impl ProofOfWork for Block {
 
 		self.validate_with_target(&target)
 	}
+
+	/// Calculate the work represented by the `Block`, i.e. the expected number of hashes required
+	/// to solve its Proof of Work: `2^512 / target`.
+	fn work(&self) -> LocksidianResult<BigUint> {
+		let difficulty = self.difficulty()?;
+		let max_target = self.target(512)?;
+		let target = self.target(difficulty)?;
+
+		Ok(max_target / target)
+	}
 }
 
 #[cfg(test)]
@@ -337,13 +1104,20 @@ mod test {
             timestamp: 0,
             nonce: 0,
             previous: String::new(),
+            base_difficulty: MAX_BASE_DIFFICULTY,
+            merkle_root: String::new(),
+            canonicalization: String::new(),
+            metadata_hash: String::new(),
+            supersedes: String::new(),
+            encryption: String::new(),
 
             hash: String::new(),
             height: 0,
             next: String::new(),
             author: String::new(),
             received_at: 0,
-            received_from: String::new()
+            received_from: String::new(),
+            work: String::new()
         }
 	}
 
@@ -355,6 +1129,231 @@ mod test {
 		assert_eq!(512, difficulty);
 	}
 
+	#[test]
+	fn difficulty_should_depend_on_the_retargeted_base_difficulty() {
+		let mut block = mock_block_data(r#"{"Hello": "World!"}"#);
+		let hash = block.calculate_hash();
+
+		block.base_difficulty = 500;
+
+		assert_eq!(500, block.difficulty().unwrap());
+		assert!(hash != block.calculate_hash());
+	}
+	
+	#[test]
+	fn base_difficulty_should_stay_within_the_retargeting_bounds() {
+		let mut block = mock_block_data(r#"{"Hello": "World!"}"#);
+		assert!(block.check_base_difficulty_bounds().is_ok());
+		
+		block.base_difficulty = MIN_BASE_DIFFICULTY;
+		assert!(block.check_base_difficulty_bounds().is_ok());
+		
+		block.base_difficulty = MAX_BASE_DIFFICULTY + 1;
+		assert!(block.check_base_difficulty_bounds().is_err());
+		
+		block.base_difficulty = MIN_BASE_DIFFICULTY - 1;
+		assert!(block.check_base_difficulty_bounds().is_err());
+	}
+
+	#[test]
+	fn timestamp_should_not_drift_into_the_future() {
+		use persistence::prelude::*;
+		
+		let connection = get_connection(String::from(":memory:")).unwrap();
+		setup_database(&connection).unwrap();
+		let repository = BlockRepository::new(&connection);
+		
+		let mut block = mock_block_data("{}");
+		block.timestamp = 1000 + MAX_TIMESTAMP_DRIFT;
+		assert!(block.check_timestamp(1000, &repository).is_ok());
+		
+		block.timestamp += 1;
+		assert!(block.check_timestamp(1000, &repository).is_err());
+	}
+	
+	#[test]
+	fn timestamp_should_not_precede_the_parent_once_linked() {
+		use persistence::prelude::*;
+		
+		let connection = get_connection(String::from(":memory:")).unwrap();
+		setup_database(&connection).unwrap();
+		let repository = BlockRepository::new(&connection);
+		
+		let mut block = mock_block_data("{}");
+		block.previous = String::from("parent");
+		block.timestamp = 999;
+		
+		// The parent is not known yet
+		assert!(block.check_timestamp(1000, &repository).is_ok());
+		
+		let mut parent = BlockEntity::empty();
+		parent.hash = String::from("parent");
+		parent.timestamp = 1000;
+		parent.height = 1;
+		repository.save(&parent).unwrap();
+		assert!(block.check_timestamp(1000, &repository).is_err());
+		
+		block.timestamp = 1000;
+		assert!(block.check_timestamp(1000, &repository).is_ok());
+	}
+
+	#[test]
+	fn batch_block_should_commit_its_documents_through_a_merkle_root() {
+		let documents = vec![String::from(r#"{"Hello": "World!"}"#), String::from(r#"{"Foo": "Bar"}"#)];
+		let leaves: Vec<String> = documents.iter().map(|document| sha512(document.as_bytes())).collect();
+
+		let mut block = mock_block_data(::serde_json::to_string(&documents).unwrap().as_ref());
+		block.merkle_root = merkle_root(&leaves).unwrap();
+
+		assert_eq!(documents, block.documents().unwrap());
+		assert_eq!(leaves, block.check_merkle_root().unwrap());
+		assert_eq!(512, block.difficulty().unwrap());
+
+		block.merkle_root = leaves[0].clone();
+		assert!(block.check_merkle_root().is_err());
+	}
+
+	#[test]
+	fn anchor_should_commit_its_metadata_and_use_a_fixed_size() {
+		let mut block = mock_block_data(r#"{"reference": "INV-42"}"#);
+		block.data_hash = sha512("confidential".as_bytes());
+		block.metadata_hash = sha512(block.data.as_bytes());
+
+		assert!(block.is_anchor());
+		assert!(block.documents().unwrap().is_empty());
+		assert_eq!(block.data_hash, block.check_data_hash().unwrap());
+		assert_eq!(512 - ANCHOR_SIZE / 32, block.difficulty().unwrap());
+
+		block.data = String::from(r#"{"reference": "INV-43"}"#);
+		assert!(block.check_data_hash().is_err());
+		assert!(Block::check_anchor("not a checksum", "").is_err());
+	}
+
+	#[test]
+	fn revision_should_record_the_superseded_document_in_its_header() {
+		let mut block = mock_block_data(r#"{"version": 2}"#);
+		let hash = block.calculate_hash();
+		let signed = Block::signed_message(block.data_hash.as_ref(), block.supersedes.as_ref());
+
+		block.supersedes = sha512(r#"{"version": 1}"#.as_bytes());
+		assert!(hash != block.calculate_hash());
+		assert!(signed != Block::signed_message(block.data_hash.as_ref(), block.supersedes.as_ref()));
+		assert!(block.check_supersedes().is_ok());
+
+		block.metadata_hash = sha512(block.data.as_bytes());
+		assert!(block.check_supersedes().is_err());
+	}
+
+	#[test]
+	fn only_envelopes_should_be_stored_in_encrypted_blocks() {
+		let author = Rsa::generate(2048).unwrap();
+		let envelope = EncryptedDocumentDto::seal("{}", &[(String::from("alice"), author)]).unwrap().to_json().unwrap();
+		let mut config = NetworkConfig::default();
+		config.schemas = ::serde_json::from_str(r#"{"patient": {"type": "object", "required": ["name"]}}"#).unwrap();
+		
+		// An envelope is only exempted from the schemas in a block flagged as encrypted
+		let mut block = mock_block_data(envelope.as_ref());
+		let hash = block.calculate_hash();
+		assert!(block.check_schemas(&config).is_err());
+		
+		block.encryption = String::from(aes::CIPHER);
+		assert!(hash != block.calculate_hash());
+		assert!(block.check_schemas(&config).is_ok());
+		assert!(block.check_encryption().is_ok());
+		
+		block.data = String::from(r#"{"encrypted": {"cipher": "aes-256-gcm", "name": "John Doe"}}"#);
+		assert!(block.check_schemas(&config).is_err());
+		
+		block.metadata_hash = sha512(block.data.as_bytes());
+		assert!(block.check_encryption().is_err());
+	}
+
+	#[test]
+	fn revision_check_should_be_deferred_until_the_ancestors_are_linked() {
+		use persistence::prelude::*;
+		
+		let connection = get_connection(String::from(":memory:")).unwrap();
+		setup_database(&connection).unwrap();
+		let repository = BlockRepository::new(&connection);
+		
+		let mut revision = mock_block_data(r#"{"version": 2}"#);
+		revision.hash = String::from("revision");
+		revision.author = String::from("alice");
+		revision.supersedes = sha512(r#"{"version": 1}"#.as_bytes());
+		assert!(revision.check_revision(false, &repository).is_ok());
+		assert!(revision.check_revision(true, &repository).is_err());
+		
+		let mut original = BlockEntity::empty();
+		original.hash = String::from("original");
+		original.data_hash = revision.supersedes.clone();
+		original.author = String::from("alice");
+		original.height = 1;
+		repository.save(&original).unwrap();
+		assert!(revision.check_revision(true, &repository).is_ok());
+		
+		// The block being linked is already stored, and a revision stored in an orphan block is ignored
+		let mut stored = BlockEntity::new(&revision);
+		stored.height = 2;
+		repository.save(&stored).unwrap();
+		
+		let mut orphan = stored.clone();
+		orphan.hash = String::from("orphan");
+		orphan.orphan = true;
+		repository.save(&orphan).unwrap();
+		assert!(revision.check_revision(true, &repository).is_ok());
+		assert_eq!(repository.get_superseding(revision.supersedes.as_ref()).unwrap().hash, "revision");
+		
+		revision.hash = String::from("fork");
+		assert!(revision.check_revision(true, &repository).is_err());
+		
+		revision.author = String::from("mallory");
+		assert!(revision.check_revision(false, &repository).is_err());
+	}
+
+	#[test]
+	fn file_record_difficulty_should_depend_on_the_file_size() {
+		let mut file = FileDto::new(b"%PDF-1.4", "application/pdf", Some("lease.pdf")).unwrap();
+		file.size = 1024 * 1024;
+
+		let record = file.to_json().unwrap();
+		let block = mock_block_data(record.as_str());
+
+		assert_eq!(512 - record.len() / 32 - 11, block.difficulty().unwrap());
+	}
+
+	#[test]
+	fn canonicalised_block_should_record_its_mode_in_the_header() {
+		let mut block = mock_block_data(r#"{"b": 2, "a": 1}"#);
+		let hash = block.calculate_hash();
+		assert!(block.check_canonicalization().is_ok());
+
+		block.canonicalization = String::from("jcs");
+		assert!(hash != block.calculate_hash());
+		assert!(block.check_canonicalization().is_err());
+
+		block.data = String::from(r#"{"a":1,"b":2}"#);
+		assert!(block.check_canonicalization().is_ok());
+	}
+	
+	#[test]
+	fn replicated_block_should_use_the_network_canonicalization_mode() {
+		let mut jcs = NetworkConfig::default();
+		jcs.canonicalization = Canonicalization::Jcs;
+		
+		let mut block = mock_block_data(r#"{"a":1}"#);
+		assert!(block.check_canonicalization_mode(&NetworkConfig::default()).is_ok());
+		assert!(block.check_canonicalization_mode(&jcs).is_err());
+		
+		block.canonicalization = String::from("jcs");
+		assert!(block.check_canonicalization_mode(&jcs).is_ok());
+		assert!(block.check_canonicalization_mode(&NetworkConfig::default()).is_err());
+		
+		// The metadata of an anchor is never canonicalised
+		block.canonicalization = String::new();
+		block.metadata_hash = sha512(block.data.as_bytes());
+		assert!(block.check_canonicalization_mode(&jcs).is_ok());
+	}
+
 	#[test]
 	fn difficulty_should_be_equal_to_508() {
 		let block = mock_block_data(r#"{"message": "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua."}"#);
@@ -392,7 +1391,7 @@ mod test {
 	#[test]
 	fn block_pow_should_compute_a_nonce_of_0() {
 		let mut block = mock_block_data(r#"{"Hello": "World!"}"#);
-		let (hash, nonce) = block.compute().unwrap();
+		let (hash, nonce) = block.compute().unwrap().unwrap();
 
 		assert_eq!(0, nonce);
 		assert_eq!("8ab3361c051a97ddc3c665d29f2762f8ac4240d08995f8724b6d07d8cbedd32c28f589ccdae514f20a6c8eea6f755408dd3dd6837d66932ca2352eaeab594427", hash);
@@ -401,7 +1400,7 @@ mod test {
 	#[test]
 	fn block_pow_should_compute_a_nonce_of_12623() {
 		let mut block = mock_block_data(r#"{"message": "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat. Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat nulla pariatur. Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia deserunt mollit anim id est laborum."}"#);
-		let (hash, nonce) = block.compute().unwrap();
+		let (hash, nonce) = block.compute().unwrap().unwrap();
 
 		assert_eq!(12623, nonce);
 		assert_eq!("0001357cc00eaa17d81b9026372bc291fde84b7936fc8870534efbcf30f0c808b4fa1b94831b955293759dd7d9ac3166590fecefa1b0d87ad4fda9a1b45e165e", hash);
@@ -424,4 +1423,85 @@ mod test {
 		let result = block.validate().unwrap();
 		assert_eq!(None, result);
 	}
+
+	#[test]
+	fn work_should_grow_with_the_difficulty() {
+		let light = mock_block_data(r#"{"Hello": "World!"}"#);
+		let heavy = mock_block_data(r#"{"message": "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua."}"#);
+
+		assert_eq!("1", format!("{:x}", ProofOfWork::work(&light).unwrap()));
+		assert_eq!("10", format!("{:x}", ProofOfWork::work(&heavy).unwrap()));
+	}
+
+	#[test]
+	fn fork_blocks_should_be_accepted_up_to_the_maximum_depth() {
+		assert!(!Block::is_too_far_behind(10, 10, 5));
+		assert!(!Block::is_too_far_behind(12, 10, 5));
+		assert!(!Block::is_too_far_behind(5, 10, 5));
+		assert!(Block::is_too_far_behind(4, 10, 5));
+	}
+
+	#[test]
+	fn signature_should_be_verified_with_the_author_key() {
+		let key = Rsa::generate(2048).unwrap();
+		let mut block = mock_block_data(r#"{"Hello": "World!"}"#);
+		block.data_hash = sha512(block.data.as_bytes());
+		block.signature = key.sign(Block::signed_message(block.data_hash.as_ref(), "").as_bytes()).unwrap();
+		block.author = compute_key_hash(&key).unwrap();
+
+		assert!(block.check_signature(&key, &NetworkConfig::default()).is_ok());
+	}
+
+	#[test]
+	fn legacy_signature_should_only_be_accepted_up_to_the_cut_over_height() {
+		let key = Rsa::generate(2048).unwrap();
+		let mut block = mock_block_data(r#"{"Hello": "World!"}"#);
+		block.signature = key.sign(block.data.as_bytes()).unwrap();
+		block.author = compute_key_hash(&key).unwrap();
+		block.height = 10;
+
+		let mut config = NetworkConfig::default();
+		assert!(block.check_signature(&key, &config).is_err());
+
+		config.legacy_signature_height = 10;
+		assert!(block.check_signature(&key, &config).is_ok());
+
+		block.height = 11;
+		assert!(block.check_signature(&key, &config).is_err());
+
+		// A bare data hash signature is not a block signature either
+		block.height = 1;
+		block.data_hash = sha512(block.data.as_bytes());
+		block.signature = key.sign(block.data_hash.as_bytes()).unwrap();
+		assert!(!block.is_data_hash_signed(&key).unwrap());
+	}
+
+	#[test]
+	fn signature_should_not_be_verified_with_another_key() {
+		let key = Rsa::generate(2048).unwrap();
+		let forger = Rsa::generate(2048).unwrap();
+		let mut block = mock_block_data(r#"{"Hello": "World!"}"#);
+		block.signature = forger.sign(block.data.as_bytes()).unwrap();
+		block.author = compute_key_hash(&key).unwrap();
+
+		let mut config = NetworkConfig::default();
+		config.legacy_signature_height = block.height;
+		assert!(block.check_signature(&key, &config).is_err());
+		assert!(block.check_signature(&forger, &config).is_err());
+	}
+
+	#[test]
+	fn signature_should_cover_the_data_hash() {
+		let key = Rsa::generate(2048).unwrap();
+		let mut block = mock_block_data(r#"{"Hello": "World!"}"#);
+		block.data_hash = sha512(block.data.as_bytes());
+		block.signature = key.sign(Block::signed_message(block.data_hash.as_ref(), "").as_bytes()).unwrap();
+		block.author = compute_key_hash(&key).unwrap();
+
+		assert!(block.is_data_hash_signed(&key).unwrap());
+		assert!(block.check_signature(&key, &NetworkConfig::default()).is_ok());
+
+		block.data = String::new();
+		assert!(block.check_signature(&key, &NetworkConfig::default()).is_ok());
+	}
 }
\ No newline at end of file
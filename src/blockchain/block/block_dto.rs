@@ -5,8 +5,13 @@
 use sec::hex::ToHex;
 
 use blockchain::block::Block;
+use blockchain::algorithm::MAX_BASE_DIFFICULTY;
 use blockchain::identity::Identity;
 
+fn default_base_difficulty() -> u32 {
+    MAX_BASE_DIFFICULTY
+}
+
 /// Simple `BlockDto` representing the entire `Block` structure.
 ///
 /// Used to display all the data of the given `Block`.
@@ -22,13 +27,41 @@ pub struct BlockDto {
     pub timestamp: u64,
     pub nonce: u32,
     pub previous: String,
+
+    /// Retargeted base difficulty of the Proof of Work, missing from the blocks sent by older nodes.
+    #[serde(default = "default_base_difficulty")]
+    pub base_difficulty: u32,
+
+    /// Merkle root of the documents of a batch block, empty for a single document block.
+    #[serde(default)]
+    pub merkle_root: String,
+
+    /// Canonicalization mode of the documents, empty for raw documents.
+    #[serde(default)]
+    pub canonicalization: String,
+
+    /// Checksum of the metadata of an anchor, empty for a block storing documents.
+    #[serde(default)]
+    pub metadata_hash: String,
+
+    /// Checksum of the document superseded by the document of the block, if any.
+    #[serde(default)]
+    pub supersedes: String,
+
+    /// Cipher of the encrypted document of the block, empty for a plaintext document.
+    #[serde(default)]
+    pub encryption: String,
     
     pub hash: String,
     pub height: u64,
     pub next: String,
     pub author: String,
     pub received_at: u64,
-    pub received_from: String
+    pub received_from: String,
+
+    /// Hexadecimal PEM-encoded public key of the block author, when known by the serving node.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author_key: Option<String>
 }
 
 impl BlockDto {
@@ -43,21 +76,33 @@ impl BlockDto {
             timestamp: block.timestamp(),
             nonce: block.nonce(),
             previous: block.previous(),
+            base_difficulty: block.base_difficulty(),
+            merkle_root: block.merkle_root(),
+            canonicalization: block.canonicalization(),
+            metadata_hash: block.metadata_hash(),
+            supersedes: block.supersedes(),
+            encryption: block.encryption(),
 
             hash: block.hash(),
             height: block.height(),
             next: block.next(),
             author: block.author(),
             received_at: block.received_at(),
-            received_from: block.received_from()
+            received_from: block.received_from(),
+
+            author_key: None
         }
     }
 }
 
 /// DTO used for `Block` replication requests.
 ///
-/// The fields `next`, `received_at` and `received_from` are omitted because they are
-/// linked to the context of a node.
+/// The fields `next` and `received_at` are omitted because they are linked to the context of a
+/// node. The `received_from` field is ignored by the receiving node, which uses the verified signer
+/// of the request instead.
+///
+/// The `author_key` is the hexadecimal PEM-encoded public key of the block author, allowing the
+/// receiving node to verify the block signature even if the author is not one of its peers.
 #[derive(
 	Debug, Clone,
 	Serialize, Deserialize
@@ -71,16 +116,42 @@ pub struct BlockReplicationDto {
     pub nonce: u32,
     pub previous: String,
 
+    /// Retargeted base difficulty of the Proof of Work, missing from the blocks sent by older nodes.
+    #[serde(default = "default_base_difficulty")]
+    pub base_difficulty: u32,
+
+    /// Merkle root of the documents of a batch block, empty for a single document block.
+    #[serde(default)]
+    pub merkle_root: String,
+
+    /// Canonicalization mode of the documents, empty for raw documents.
+    #[serde(default)]
+    pub canonicalization: String,
+
+    /// Checksum of the metadata of an anchor, empty for a block storing documents.
+    #[serde(default)]
+    pub metadata_hash: String,
+
+    /// Checksum of the document superseded by the document of the block, if any.
+    #[serde(default)]
+    pub supersedes: String,
+
+    /// Cipher of the encrypted document of the block, empty for a plaintext document.
+    #[serde(default)]
+    pub encryption: String,
+
     pub hash: String,
     pub height: u64,
     pub author: String,
-    pub received_from: String
+    pub received_from: String,
+
+    pub author_key: Option<String>
 }
 
 impl BlockReplicationDto {
 
     /// Instantiate a new `BlockReplicationDto` based on the given `Block`.
-    pub fn new(block: &Block, current_identity: &Identity) -> Self {
+    pub fn new(block: &Block, current_identity: &Identity, author_key: String) -> Self {
         BlockReplicationDto {
             data: block.data(),
 
@@ -89,11 +160,86 @@ impl BlockReplicationDto {
             timestamp: block.timestamp(),
             nonce: block.nonce(),
             previous: block.previous(),
+            base_difficulty: block.base_difficulty(),
+            merkle_root: block.merkle_root(),
+            canonicalization: block.canonicalization(),
+            metadata_hash: block.metadata_hash(),
+            supersedes: block.supersedes(),
+            encryption: block.encryption(),
 
             hash: block.hash(),
             height: block.height(),
             author: block.author(),
-            received_from: current_identity.hash()
+            received_from: current_identity.hash(),
+
+            author_key: Some(author_key)
+        }
+    }
+}
+/// Header of a `Block`, without its data.
+///
+/// Used to prove the existence of a block, and of the blocks built on top of it, without having to
+/// disclose their documents.
+#[derive(
+	Debug, Clone,
+	Serialize, Deserialize
+)]
+pub struct BlockHeaderDto {
+    pub data_hash: String,
+    pub signature: String,
+    pub timestamp: u64,
+    pub nonce: u32,
+    pub previous: String,
+
+    /// Retargeted base difficulty of the Proof of Work.
+    #[serde(default = "default_base_difficulty")]
+    pub base_difficulty: u32,
+
+    /// Merkle root of the documents of a batch block, empty for a single document block.
+    #[serde(default)]
+    pub merkle_root: String,
+
+    /// Canonicalization mode of the documents, empty for raw documents.
+    #[serde(default)]
+    pub canonicalization: String,
+
+    /// Checksum of the metadata of an anchor, empty for a block storing documents.
+    #[serde(default)]
+    pub metadata_hash: String,
+
+    /// Checksum of the document superseded by the document of the block, if any.
+    #[serde(default)]
+    pub supersedes: String,
+
+    /// Cipher of the encrypted document of the block, empty for a plaintext document.
+    #[serde(default)]
+    pub encryption: String,
+
+    pub hash: String,
+    pub height: u64,
+    pub author: String
+}
+
+impl BlockHeaderDto {
+
+    /// Instantiate a new `BlockHeaderDto` based on the given `Block`.
+    pub fn new(block: &Block) -> Self {
+        BlockHeaderDto {
+            data_hash: block.data_hash(),
+            signature: block.signature().to_hex(),
+            timestamp: block.timestamp(),
+            nonce: block.nonce(),
+            previous: block.previous(),
+            base_difficulty: block.base_difficulty(),
+            merkle_root: block.merkle_root(),
+            canonicalization: block.canonicalization(),
+            metadata_hash: block.metadata_hash(),
+            supersedes: block.supersedes(),
+            encryption: block.encryption(),
+
+            hash: block.hash(),
+            height: block.height(),
+            author: block.author()
         }
     }
-}
\ No newline at end of file
+}
@@ -2,9 +2,21 @@
 
 use persistence::prelude::*;
 use blockchain::block::Block;
+use blockchain::document::{DocumentEntity, DocumentRepository, FieldEntity, FieldRepository, document_fields};
+use blockchain::algorithm::{add_work, cancel_mining, MAX_BASE_DIFFICULTY};
+use blockchain::network::NetworkConfig;
 
 use sec::hex::ToHex;
 
+/// Maximum number of hashes bound to a single `DELETE` statement, below the SQLite host parameters limit.
+const DELETE_BATCH_SIZE: usize = 500;
+
+/// Default number of blocks returned by a page of the main chain.
+pub const DEFAULT_PAGE_SIZE: i64 = 100;
+
+/// Maximum number of blocks returned by a page of the main chain.
+pub const MAX_PAGE_SIZE: i64 = 1000;
+
 table! {
     blocks(hash) {
         data -> VarChar,
@@ -21,6 +33,15 @@ table! {
         author -> VarChar,
         received_at -> Integer,
         received_from -> VarChar,
+        work -> VarChar,
+        chain_work -> VarChar,
+        orphan -> Bool,
+        base_difficulty -> Integer,
+        merkle_root -> VarChar,
+        canonicalization -> VarChar,
+        metadata_hash -> VarChar,
+        supersedes -> VarChar,
+        encryption -> VarChar,
     }
 }
 
@@ -43,7 +64,16 @@ pub struct BlockEntity {
     pub next: String,
     pub author: String,
     pub received_at: i32,
-    pub received_from: String
+    pub received_from: String,
+    pub work: String,
+    pub chain_work: String,
+    pub orphan: bool,
+    pub base_difficulty: i32,
+    pub merkle_root: String,
+    pub canonicalization: String,
+    pub metadata_hash: String,
+    pub supersedes: String,
+    pub encryption: String
 }
 
 impl BlockEntity {
@@ -64,7 +94,16 @@ impl BlockEntity {
             next: block.next(),
             author: block.author(),
             received_at: block.received_at() as i32,
-            received_from: block.received_from()
+            received_from: block.received_from(),
+            work: block.work(),
+            chain_work: String::new(),
+            orphan: false,
+            base_difficulty: block.base_difficulty() as i32,
+            merkle_root: block.merkle_root(),
+            canonicalization: block.canonicalization(),
+            metadata_hash: block.metadata_hash(),
+            supersedes: block.supersedes(),
+            encryption: block.encryption()
         }
     }
 
@@ -84,7 +123,16 @@ impl BlockEntity {
             next: String::new(),
             author: String::new(),
             received_at: 0,
-            received_from: String::new()
+            received_from: String::new(),
+            work: String::new(),
+            chain_work: String::new(),
+            orphan: false,
+            base_difficulty: MAX_BASE_DIFFICULTY as i32,
+            merkle_root: String::new(),
+            canonicalization: String::new(),
+            metadata_hash: String::new(),
+            supersedes: String::new(),
+            encryption: String::new()
         }
     }
 }
@@ -104,49 +152,394 @@ impl<'pool> BlockRepository<'pool> {
 
     /// Select a `BlockEntity` using its `data_hash` rather than its `hash` primary key.
     ///
-    /// Method used when crawling the blockchain for an existing document.
+    /// Method used when crawling the blockchain for an existing document. A main chain block is
+    /// preferred over the orphan blocks storing the same document.
     pub fn get_by_data_hash(&self, data_hash: &str) -> Option<BlockEntity> {
-        match blocks::table.filter(blocks::data_hash.eq(data_hash)).first(self.connection) {
+        match blocks::table
+            .filter(blocks::data_hash.eq(data_hash))
+            .order(blocks::orphan.asc())
+            .first(self.connection) {
             Ok(entity) => Some(entity),
             Err(_) => None
         }
     }
 
-    /// Select the `BlockEntity` with the greater `height` value.
+    /// Locate the block storing the document identified by its `data_hash`, along with the position
+    /// of the document in the block (always `0` for a single document block).
     ///
-    /// TODO: Select the `BlockEntity` with the greater `height` value and having its `previous` block linked back to it through its `next` column,
-    /// in order to avoid conflicts if a fork were to occur.
+    /// A main chain location is preferred: an orphan block is only returned when the document is
+    /// not stored on the main chain.
+    pub fn locate_document(&self, data_hash: &str) -> Option<(BlockEntity, usize)> {
+        let single = match self.get_by_data_hash(data_hash) {
+            Some(entity) => match entity.merkle_root.is_empty() {
+                true => Some((entity, 0)),
+                false => None
+            },
+            None => None
+        };
+
+        if single.as_ref().map(|&(ref entity, _)| !entity.orphan).unwrap_or(false) {
+            return single;
+        }
+
+        let batch = match DocumentRepository::new(self.connection).get(&String::from(data_hash)) {
+            Some(document) => self.get(&document.block).map(|entity| (entity, document.position as usize)),
+            None => None
+        };
+
+        match batch.as_ref().map(|&(ref entity, _)| !entity.orphan).unwrap_or(false) {
+            true => batch,
+            false => single.or(batch)
+        }
+    }
+
+    /// Select the main chain block storing the revision of the document identified by its
+    /// `data_hash`. The revisions stored in orphan blocks are ignored.
+    pub fn get_superseding(&self, data_hash: &str) -> Option<BlockEntity> {
+        match blocks::table
+            .filter(blocks::supersedes.eq(data_hash))
+            .filter(blocks::orphan.eq(false))
+            .order(blocks::hash.asc())
+            .first(self.connection) {
+            Ok(entity) => Some(entity),
+            Err(_) => None
+        }
+    }
+
+    /// Select every main chain block storing a revision of the document identified by its `data_hash`.
+    pub fn get_revisions(&self, data_hash: &str) -> LocksidianResult<Vec<BlockEntity>> {
+        match blocks::table
+            .filter(blocks::supersedes.eq(data_hash))
+            .filter(blocks::orphan.eq(false))
+            .load(self.connection) {
+            Ok(entities) => Ok(entities),
+            Err(err) => Err(LocksidianError::from_err(err))
+        }
+    }
+
+    /// Select the `HEAD` of the main chain, i.e. the block having the greatest cumulative work.
+    ///
+    /// Ties are broken by selecting the highest block, then the smallest hash, so that every node
+    /// selects the same `HEAD` from the same registry.
     pub fn get_head(&self) -> Option<BlockEntity> {
-        match blocks::table.order(blocks::height.desc()).first(self.connection) {
+        match blocks::table
+            .filter(blocks::chain_work.ne(""))
+            .order((blocks::chain_work.desc(), blocks::height.desc(), blocks::hash.asc()))
+            .first(self.connection) {
             Ok(entity) => Some(entity),
             Err(_) => None
         }
     }
 
-    /// Update the current `HEAD` block to set its `next` column to the `hash` value of a new, persisted, `HEAD` block.
-    pub fn save_head(&self, entity: &BlockEntity) -> LocksidianResult<usize> {
-        match self.get_head() {
-            Some(mut head) => {
-                if head.next.is_empty() {
-					head.next = entity.hash.clone();
-					self.update(&head)?;
-				}
+    /// Select the tip of every known branch (i.e. the blocks linked to the chain with no `next` block),
+    /// from the heaviest to the lightest one.
+    pub fn get_tips(&self) -> Option<Vec<BlockEntity>> {
+        match blocks::table
+            .filter(blocks::chain_work.ne(""))
+            .filter(blocks::next.eq(""))
+            .order((blocks::chain_work.desc(), blocks::height.desc(), blocks::hash.asc()))
+            .load(self.connection) {
+            Ok(entities) => Some(entities),
+            Err(_) => None
+        }
+    }
+
+    /// Select the main chain block located at the given `height`.
+    pub fn get_by_height(&self, height: i32) -> Option<BlockEntity> {
+        match blocks::table
+            .filter(blocks::chain_work.ne(""))
+            .filter(blocks::orphan.eq(false))
+            .filter(blocks::height.eq(height))
+            .first(self.connection) {
+            Ok(entity) => Some(entity),
+            Err(_) => None
+        }
+    }
+
+    /// Select at most `limit` main chain blocks, ordered by height and starting at `from_height`.
+    /// Only the blocks whose timestamp is in the `[since, until]` interval are selected.
+    pub fn get_page(&self, from_height: i32, since: Option<i32>, until: Option<i32>, limit: i64) -> Option<Vec<BlockEntity>> {
+        let mut query = blocks::table
+            .filter(blocks::chain_work.ne(""))
+            .filter(blocks::orphan.eq(false))
+            .filter(blocks::height.ge(from_height))
+            .into_boxed();
+
+        if let Some(since) = since {
+            query = query.filter(blocks::timestamp.ge(since));
+        }
+
+        if let Some(until) = until {
+            query = query.filter(blocks::timestamp.le(until));
+        }
+
+        match query.order(blocks::height.asc()).limit(limit).load(self.connection) {
+            Ok(entities) => Some(entities),
+            Err(_) => None
+        }
+    }
+
+    /// Select the blocks persisted without their work, ordered by height.
+    pub fn get_unindexed(&self) -> Option<Vec<BlockEntity>> {
+        match blocks::table.filter(blocks::work.eq("")).order(blocks::height.asc()).load(self.connection) {
+            Ok(entities) => Some(entities),
+            Err(_) => None
+        }
+    }
+
+    /// Persist a new block in a single transaction, then:
+    ///
+    /// - compute its cumulative work if its previous block is linked to the chain;
+    /// - index the documents of a batch block, and the fields of its JSON documents;
+    /// - link its previous block to it if this previous block had no `next` block yet, otherwise flag
+    ///   it as an orphan block;
+    /// - link the blocks that were waiting for it (received before their previous block), once the
+    ///   checks requiring their ancestors passed against the rules of the network `config`;
+    /// - reorganise the main chain if its branch became the heaviest one;
+    /// - cancel the running mining tasks if the `HEAD` block changed, as they became stale.
+    pub fn save_block(&self, entity: &mut BlockEntity, config: &NetworkConfig) -> LocksidianResult<usize> {
+        let (inserted_rows, head_changed) = transaction(self.connection, || {
+            let former_head = self.get_head();
+            let former_head_hash = former_head.as_ref().map(|head| head.hash.clone());
+
+            entity.next = String::new();
+            self.link(entity)?;
+            let inserted_rows = self.save(&entity)?;
+            self.index_documents(&entity, false)?;
+            self.index_fields(&entity)?;
+
+            self.link_children(&entity, &config)?;
+            self.reorganize(former_head)?;
+
+            Ok((inserted_rows, self.get_head().map(|head| head.hash) != former_head_hash))
+        })?;
+
+        // The blocks being mined on top of the former `HEAD` block are now stale
+        if head_changed {
+            cancel_mining();
+        }
+
+        Ok(inserted_rows)
+    }
+
+    /// Index the position of every document of a batch block. A document already indexed in another
+    /// block is only relocated when that block is an orphan, or when `promoted` is set because the
+    /// given block has just joined the main chain.
+    fn index_documents(&self, entity: &BlockEntity, promoted: bool) -> LocksidianResult<()> {
+        if entity.merkle_root.is_empty() {
+            return Ok(());
+        }
+
+        let repository = DocumentRepository::new(self.connection);
+        let block = Block::from_entity(entity.clone())?;
+
+        for (position, data_hash) in block.document_hashes()?.into_iter().enumerate() {
+            let document = DocumentEntity::new(data_hash, entity.hash.clone(), position);
+
+            match repository.get(&document.data_hash) {
+                Some(ref indexed) if indexed.block == entity.hash => (),
+                Some(indexed) => {
+                    if promoted || self.get(&indexed.block).map(|block| block.orphan).unwrap_or(true) {
+                        repository.update(&document)?;
+                    }
+                },
+                None => {
+                    repository.save(&document)?;
+                }
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Index the fields of every JSON document of a block. Neither the anchors nor the encrypted
+    /// documents are indexed, as their content cannot be read.
+    pub fn index_fields(&self, entity: &BlockEntity) -> LocksidianResult<()> {
+        let repository = FieldRepository::new(self.connection);
+        let block = Block::from_entity(entity.clone())?;
+
+        if block.is_encrypted() {
+            return Ok(());
+        }
+
+        for (document, data_hash) in block.documents()?.iter().zip(block.document_hashes()?) {
+            for (path, value) in document_fields(document.as_ref()) {
+                repository.save(&FieldEntity::new(data_hash.as_ref(), entity.hash.as_ref(), entity.author.as_ref(), entity.timestamp, path, &value))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compute the cumulative work of a block that is already persisted, and link it to the chain.
+    pub fn index(&self, entity: &mut BlockEntity) -> LocksidianResult<usize> {
+        self.link(entity)?;
+        self.update(&entity)
+    }
+
+    /// Relink the `next` pointers of the main chain, from the current `HEAD` back to the block where
+    /// its branch forks from the `former_head` branch. The blocks of the new main chain branch are
+    /// promoted, while the blocks of the former one are flagged as orphans. The documents of the
+    /// promoted blocks are relocated to them, so that they are certified again.
+    ///
+    /// Without a `former_head`, the main chain is relinked all the way back to its `ORIGIN`.
+    pub fn reorganize(&self, former_head: Option<BlockEntity>) -> LocksidianResult<()> {
+        let mut current = match self.get_head() {
+            Some(head) => head,
+            None => return Ok(())
+        };
+        let mut former = former_head.and_then(|head| self.get(&head.hash));
+
+        if !current.next.is_empty() || current.orphan {
+            current.next = String::new();
+            current.orphan = false;
+            self.update(&current)?;
+            self.index_documents(&current, true)?;
+        }
+
+        while !current.previous.is_empty() {
+            former = self.abandon_until(former, current.height)?;
+
+            match former {
+                Some(ref block) if block.hash == current.hash => break,
+                _ => ()
+            };
+
+            match self.get(&current.previous) {
+                Some(mut previous) => {
+                    if previous.next != current.hash || previous.orphan {
+                        info!("Chain reorganisation: linking block {} to {}", previous.hash, current.hash);
+                        previous.next = current.hash.clone();
+                        previous.orphan = false;
+                        self.update(&previous)?;
+                        self.index_documents(&previous, true)?;
+                    }
+
+                    current = previous;
+                },
+                None => break
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compute the cumulative work of the given block from its previous block, and link this previous
+    /// block to it if it had no `next` block yet. The cumulative work is left empty if the previous
+    /// block is unknown or not linked to the chain.
+    ///
+    /// A block whose previous block is linked to another block, or is itself an orphan, is flagged
+    /// as an orphan block.
+    fn link(&self, entity: &mut BlockEntity) -> LocksidianResult<()> {
+        if entity.previous.is_empty() {
+            if entity.height != 1 {
+                return Err(LocksidianError::new(format!("Origin block {} should have a height of 1", entity.hash)));
+            }
+
+            entity.chain_work = entity.work.clone();
+            return Ok(());
+        }
+
+        match self.get(&entity.previous) {
+            Some(mut previous) => {
+                if entity.height != previous.height + 1 {
+                    return Err(LocksidianError::new(format!(
+                        "Block {} height does not follow the height of its previous block {}", entity.hash, previous.hash
+                    )));
+                }
+
+                if !previous.chain_work.is_empty() {
+                    entity.chain_work = add_work(&previous.chain_work, &entity.work)?;
+                }
+
+                entity.orphan = previous.orphan || (!previous.next.is_empty() && previous.next != entity.hash);
+
+                if previous.next.is_empty() {
+                    previous.next = entity.hash.clone();
+                    self.update(&previous)?;
+                }
             },
-            None => ()
+            None => entity.chain_work = String::new()
         };
 
-        self.save(&entity)
+        Ok(())
+    }
+
+    /// Link all the descendants of the given block that were persisted before it. The descendants
+    /// failing the checks that require their ancestors (see `Block::check_linked`) are discarded.
+    fn link_children(&self, entity: &BlockEntity, config: &NetworkConfig) -> LocksidianResult<()> {
+        let mut parents: Vec<String> = vec![entity.hash.clone()];
+
+        while let Some(parent) = parents.pop() {
+            let children: Vec<BlockEntity> = match blocks::table
+                .filter(blocks::previous.eq(&parent))
+                .filter(blocks::chain_work.eq(""))
+                .load(self.connection) {
+                Ok(children) => children,
+                Err(err) => return Err(LocksidianError::from_err(err))
+            };
+
+            for mut child in children {
+                match Block::from_entity(child.clone()).and_then(|block| block.check_linked(&config, self)) {
+                    Ok(_) => (),
+                    Err(err) => {
+                        warn!("Block {} is discarded: {}", child.hash, err.description());
+                        self.delete_all(&[child.hash.clone()])?;
+                        continue;
+                    }
+                };
+
+                match self.index(&mut child) {
+                    Ok(_) if !child.chain_work.is_empty() => parents.push(child.hash),
+                    Ok(_) => (),
+                    Err(err) => warn!("Block {} cannot be linked to the chain: {}", child.hash, err.description())
+                }
+            }
+        }
+
+        Ok(())
     }
-    
-    /// Update the `next` field of a Block in order to link it to the new block.
-    pub fn save_next(&self, entity: &mut BlockEntity, previous: &mut BlockEntity) -> LocksidianResult<usize> {
-        if previous.next.is_empty() {
-            previous.next = entity.hash.clone();
-            self.update(&previous)?;
+
+    /// Walk back the `previous` links of the given former main chain block until reaching the given
+    /// height, flagging every walked block as an orphan.
+    fn abandon_until(&self, block: Option<BlockEntity>, height: i32) -> LocksidianResult<Option<BlockEntity>> {
+        let mut block = block;
+
+        loop {
+            block = match block {
+                Some(mut entity) => match entity.height > height {
+                    true => {
+                        if !entity.orphan {
+                            entity.orphan = true;
+                            self.update(&entity)?;
+                        }
+
+                        self.get(&entity.previous)
+                    },
+                    false => return Ok(Some(entity))
+                },
+                None => return Ok(None)
+            };
         }
-        
-        entity.previous = previous.hash.clone();
-        self.save(&entity)
+    }
+
+    /// Remove all the blocks identified by the given hashes and return the number of deleted rows.
+    ///
+    /// This method should be called inside of a transaction, as the deletion is split in several statements.
+    pub fn delete_all(&self, hashes: &[String]) -> LocksidianResult<usize> {
+        let mut deleted_rows: usize = 0;
+
+        for batch in hashes.chunks(DELETE_BATCH_SIZE) {
+            DocumentRepository::new(self.connection).delete_by_blocks(batch)?;
+            FieldRepository::new(self.connection).delete_by_blocks(batch)?;
+
+            match ::diesel::delete(blocks::table.filter(blocks::hash.eq_any(batch.to_vec()))).execute(self.connection) {
+                Ok(rows) => deleted_rows += rows,
+                Err(err) => return Err(LocksidianError::from_err(err))
+            }
+        }
+
+        Ok(deleted_rows)
     }
 }
 
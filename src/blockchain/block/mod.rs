@@ -5,5 +5,5 @@ mod block_repository;
 mod block_dto;
 
 pub use self::block_domain::Block;
-pub use self::block_repository::{BlockEntity, BlockRepository};
-pub use self::block_dto::{BlockDto, BlockReplicationDto};
\ No newline at end of file
+pub use self::block_repository::{BlockEntity, BlockRepository, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+pub use self::block_dto::{BlockDto, BlockReplicationDto, BlockHeaderDto};
\ No newline at end of file
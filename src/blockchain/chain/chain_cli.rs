@@ -0,0 +1,114 @@
+//! Chain command line interface.
+
+use error::*;
+use persistence::prelude::*;
+
+use blockchain::algorithm::work_to_hex;
+use blockchain::block::{Block, BlockEntity, BlockRepository};
+use blockchain::chain::{Chain, ChainReport, PruneReport};
+use blockchain::consensus::{ConsensusEngine, ProofOfWorkEngine};
+use blockchain::document::FieldRepository;
+use blockchain::network::NetworkConfig;
+
+/// Marker recorded once the fields of the documents persisted by a previous version of the node
+/// have been indexed.
+const FIELDS_INDEXED: &'static str = "fields_indexed";
+
+/// Verify the whole blocks registry of the node against the rules of its network and return the
+/// resulting report as a JSON string.
+///
+/// If `relink` is set, the inconsistent `next` links of the main chain are repaired.
+pub fn verify_chain(relink: bool, config: NetworkConfig) -> LocksidianResult<String> {
+	let connection = get_connection(database_path())?;
+	let report = verify(&connection, &config, relink)?;
+	
+	match ::serde_json::to_string_pretty(&report) {
+		Ok(json) => Ok(json),
+		Err(err) => Err(LocksidianError::from_err(err))
+	}
+}
+
+/// Load the main chain from the registry and verify it.
+pub fn verify(connection: &SqliteConnection, config: &NetworkConfig, relink: bool) -> LocksidianResult<ChainReport> {
+	let repository = BlockRepository::new(&connection);
+	let chain = Chain::load(&repository)?;
+	
+	chain.verify(&connection, &config, relink)
+}
+
+
+/// Load the main chain from the registry and discard every block that is not part of it.
+pub fn prune(connection: &SqliteConnection, dry_run: bool) -> LocksidianResult<PruneReport> {
+	let repository = BlockRepository::new(&connection);
+	let chain = Chain::load(&repository)?;
+	
+	chain.prune(&connection, dry_run)
+}
+
+/// Compute the work and the cumulative work of the blocks persisted by a previous version of the
+/// node, then select and relink the main chain accordingly.
+///
+/// As the previous versions of the node only supported the Proof of Work consensus, the work of
+/// these blocks is computed using the `ProofOfWorkEngine`.
+pub fn index_work(connection: &SqliteConnection) -> LocksidianResult<()> {
+	let repository = BlockRepository::new(&connection);
+	
+	let entities = match repository.get_unindexed() {
+		Some(entities) => entities,
+		None => return Err(LocksidianError::new(String::from("Unable to load the blocks registry")))
+	};
+	
+	if entities.is_empty() {
+		return Ok(());
+	}
+	
+	info!("Indexing the work of {} blocks...", entities.len());
+	transaction(&connection, || {
+		for entity in entities {
+			let block = Block::from_entity(entity)?;
+			let mut entity = BlockEntity::new(&block);
+			entity.work = work_to_hex(&ProofOfWorkEngine.work(&block)?);
+			
+			match repository.index(&mut entity) {
+				Ok(_) => (),
+				Err(err) => warn!("Block {} cannot be linked to the chain: {}", entity.hash, err.description())
+			}
+		}
+		
+		repository.reorganize(None)
+	})
+}
+
+/// Index the fields of the documents persisted by a previous version of the node, which did not
+/// index them. The registry is only scanned once: the `FIELDS_INDEXED` marker is then recorded.
+///
+/// The registries whose fields were indexed before the marker was introduced already have indexed
+/// fields, and are only marked.
+pub fn index_fields(connection: &SqliteConnection) -> LocksidianResult<()> {
+	let markers = MarkerRepository::new(&connection);
+	if markers.is_set(FIELDS_INDEXED) {
+		return Ok(());
+	}
+	
+	if FieldRepository::new(&connection).count()? > 0 {
+		return markers.set(FIELDS_INDEXED);
+	}
+	
+	let repository = BlockRepository::new(&connection);
+	let entities = match repository.get_all() {
+		Some(entities) => entities,
+		None => return Err(LocksidianError::new(String::from("Unable to load the blocks registry")))
+	};
+	
+	if !entities.is_empty() {
+		info!("Indexing the document fields of {} blocks...", entities.len());
+	}
+	
+	transaction(&connection, || {
+		for entity in entities.iter() {
+			repository.index_fields(entity)?;
+		}
+		
+		markers.set(FIELDS_INDEXED)
+	})
+}
@@ -0,0 +1,247 @@
+//! Chain domain structure.
+
+use error::*;
+use persistence::prelude::*;
+
+use std::collections::{HashMap, HashSet};
+
+use blockchain::block::*;
+use blockchain::consensus::ConsensusEngine;
+use blockchain::network::NetworkConfig;
+use blockchain::chain::{ChainReport, ChainIssue, PruneReport};
+use blockchain::identity::identity_cli::get_identity_key;
+
+/// In-memory view of the blocks registry of a node.
+///
+/// The **main chain** is resolved by walking the `previous` links back from the current `HEAD` block
+/// until the `ORIGIN` block (or an unknown block) is reached.
+pub struct Chain {
+	blocks: HashMap<String, BlockEntity>,
+	main: Vec<String>
+}
+
+impl Chain {
+	
+	/// Load the whole blocks registry and resolve its main chain.
+	pub fn load(repository: &BlockRepository) -> LocksidianResult<Self> {
+		let mut blocks: HashMap<String, BlockEntity> = HashMap::new();
+		
+		match repository.get_all() {
+			Some(entities) => for entity in entities {
+				blocks.insert(entity.hash.clone(), entity);
+			},
+			None => return Err(LocksidianError::new(String::from("Unable to load the blocks registry")))
+		}
+		
+		let mut main: Vec<String> = Vec::new();
+		let mut visited: HashSet<String> = HashSet::new();
+		let mut cursor = repository.get_head().map(|head| head.hash);
+		
+		while let Some(hash) = cursor {
+			cursor = match blocks.get(&hash) {
+				Some(entity) => match entity.previous.is_empty() || visited.contains(&entity.previous) {
+					true => None,
+					false => Some(entity.previous.clone())
+				},
+				None => None
+			};
+			
+			if blocks.contains_key(&hash) {
+				visited.insert(hash.clone());
+				main.push(hash);
+			}
+		}
+		
+		main.reverse();
+		
+		Ok(Chain {
+			blocks: blocks,
+			main: main
+		})
+	}
+	
+	/// Walk the main chain from its `ORIGIN` to its `HEAD`, checking every `previous`/`next` link,
+	/// the height continuity, the document checksum, the block hash, the consensus rules of the
+	/// network (e.g. the Proof of Work) and the author signature of each block.
+	///
+	/// If `relink` is set, the inconsistent `next` links of the main chain are repaired.
+	pub fn verify(&self, connection: &SqliteConnection, config: &NetworkConfig, relink: bool) -> LocksidianResult<ChainReport> {
+		let engine = config.consensus_engine()?;
+		let issues = self.main_chain_issues(&*engine, &config, &connection);
+		
+		let relinked = match relink {
+			true => self.relink(&BlockRepository::new(&connection))?,
+			false => Vec::new()
+		};
+		let orphans = self.orphans();
+		
+		Ok(ChainReport {
+			valid: issues.is_empty(),
+			blocks: self.blocks.len(),
+			length: self.main.len(),
+			origin: self.main.first().cloned().unwrap_or(String::new()),
+			head: self.main.last().cloned().unwrap_or(String::new()),
+			
+			first_broken: issues.first().cloned(),
+			issues: issues,
+			orphans: orphans,
+			dangling: self.dangling(),
+			relinked: relinked
+		})
+	}
+	
+	/// Discard every block of the registry that is not part of the main chain, and repair the
+	/// inconsistent `next` links of the main chain, in a single transaction.
+	///
+	/// When `dry_run` is set, the registry is left untouched and the report lists the blocks that
+	/// would be removed and relinked.
+	pub fn prune(&self, connection: &SqliteConnection, dry_run: bool) -> LocksidianResult<PruneReport> {
+		let removed = self.orphans();
+		
+		let relinked = match dry_run {
+			true => self.inconsistent_links().into_iter().map(|(hash, _)| hash).collect(),
+			false => {
+				let repository = BlockRepository::new(&connection);
+				
+				let relinked = transaction(&connection, || {
+					let relinked = self.relink(&repository)?;
+					let deleted_rows = repository.delete_all(&removed)?;
+					
+					match deleted_rows == removed.len() {
+						true => Ok(relinked),
+						false => Err(LocksidianError::new(format!(
+							"An unexpected number of blocks were removed from the registry: {} instead of {}",
+							deleted_rows, removed.len()
+						)))
+					}
+				})?;
+				
+				info!("Registry pruned: {} blocks removed, {} blocks relinked", removed.len(), relinked.len());
+				relinked
+			}
+		};
+		
+		Ok(PruneReport {
+			dry_run: dry_run,
+			kept: self.main.len(),
+			removed: removed,
+			relinked: relinked
+		})
+	}
+	
+	/// Hashes of the blocks of the registry that are not part of the main chain, ordered by height.
+	pub fn orphans(&self) -> Vec<String> {
+		let main: HashSet<&String> = self.main.iter().collect();
+		
+		let mut orphans: Vec<&BlockEntity> = self.blocks.values()
+			.filter(|entity| !main.contains(&entity.hash))
+			.collect();
+		orphans.sort_by_key(|entity| (entity.height, entity.hash.clone()));
+		
+		orphans.iter().map(|entity| entity.hash.clone()).collect()
+	}
+	
+	/// Hashes of the blocks having a `previous` or `next` link pointing to an unknown block.
+	pub fn dangling(&self) -> Vec<String> {
+		let mut dangling: Vec<&BlockEntity> = self.blocks.values()
+			.filter(|entity| self.is_dangling_link(&entity.previous) || self.is_dangling_link(&entity.next))
+			.collect();
+		dangling.sort_by_key(|entity| (entity.height, entity.hash.clone()));
+		
+		dangling.iter().map(|entity| entity.hash.clone()).collect()
+	}
+	
+	/// Collect all the issues of the main chain, ordered from `ORIGIN` to `HEAD`.
+	fn main_chain_issues(&self, engine: &ConsensusEngine, config: &NetworkConfig, connection: &SqliteConnection) -> Vec<ChainIssue> {
+		let mut issues: Vec<ChainIssue> = Vec::new();
+		let mut previous: Option<&BlockEntity> = None;
+		
+		for hash in self.main.iter() {
+			let entity = &self.blocks[hash];
+			let height = entity.height as u64;
+			
+			match previous {
+				Some(previous) => {
+					if entity.height != previous.height + 1 {
+						issues.push(ChainIssue::new(hash, height, format!("Height does not follow the height {} of the previous block", previous.height)));
+					}
+					
+					if previous.next != entity.hash {
+						issues.push(ChainIssue::new(hash, height, format!("Previous block {} is not linked back to this block (next is \"{}\")", previous.hash, previous.next)));
+					}
+				},
+				None => match entity.previous.is_empty() {
+					true => if entity.height != 1 {
+						issues.push(ChainIssue::new(hash, height, String::from("Origin block height should be 1")));
+					},
+					false => issues.push(ChainIssue::new(hash, height, format!("Previous block {} is missing from the registry", entity.previous)))
+				}
+			}
+			
+			match self.verify_block(&entity, engine, &config, &connection) {
+				Ok(_) => (),
+				Err(err) => issues.push(ChainIssue::new(hash, height, String::from(err.description())))
+			}
+			
+			previous = Some(entity);
+		}
+		
+		match previous {
+			Some(head) if !head.next.is_empty() => issues.push(ChainIssue::new(
+				&head.hash, head.height as u64, format!("HEAD block is linked to a next block: {}", head.next)
+			)),
+			_ => ()
+		}
+		
+		issues
+	}
+	
+	/// Recompute and check the checksums, consensus rules, base difficulty and signature of the
+	/// given block.
+	fn verify_block(&self, entity: &BlockEntity, engine: &ConsensusEngine, config: &NetworkConfig, connection: &SqliteConnection) -> LocksidianResult<()> {
+		let block = Block::from_entity(entity.clone())?;
+		let author_key = get_identity_key(block.author().as_ref(), None, &connection)?;
+		
+		block.verify(&author_key, engine, &config)?;
+		block.check_base_difficulty(&config, &BlockRepository::new(&connection))
+	}
+	
+	/// Main chain blocks that are not linked to their successor (or, for the `HEAD` block, that are
+	/// linked to any block), along with the `next` value they should have.
+	fn inconsistent_links(&self) -> Vec<(String, String)> {
+		let mut links: Vec<(String, String)> = Vec::new();
+		
+		for (index, hash) in self.main.iter().enumerate() {
+			let next = self.main.get(index + 1).cloned().unwrap_or(String::new());
+			
+			if self.blocks[hash].next != next {
+				links.push((hash.clone(), next));
+			}
+		}
+		
+		links
+	}
+	
+	/// Update the `next` field of every inconsistent main chain block. Return the hashes of the
+	/// updated blocks.
+	fn relink(&self, repository: &BlockRepository) -> LocksidianResult<Vec<String>> {
+		let mut relinked: Vec<String> = Vec::new();
+		
+		for (hash, next) in self.inconsistent_links() {
+			let mut entity = self.blocks[&hash].clone();
+			
+			info!("Relinking block {} to {}", entity.hash, next);
+			entity.next = next;
+			repository.update(&entity)?;
+			
+			relinked.push(hash);
+		}
+		
+		Ok(relinked)
+	}
+	
+	/// Returns `true` if the given link is not empty and points to an unknown block.
+	fn is_dangling_link(&self, hash: &str) -> bool {
+		!hash.is_empty() && !self.blocks.contains_key(hash)
+	}
+}
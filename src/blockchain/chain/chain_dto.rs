@@ -0,0 +1,91 @@
+//! Chain data transfer objects.
+
+use blockchain::block::BlockEntity;
+
+/// Issue detected on a specific block of the registry.
+#[derive(
+	Debug, Clone,
+	Serialize, Deserialize
+)]
+pub struct ChainIssue {
+	pub hash: String,
+	pub height: u64,
+	pub reason: String
+}
+
+impl ChainIssue {
+	
+	/// Instantiate a new `ChainIssue` for the block identified by `hash`.
+	pub fn new(hash: &str, height: u64, reason: String) -> Self {
+		ChainIssue {
+			hash: String::from(hash),
+			height: height,
+			reason: reason
+		}
+	}
+}
+
+/// Detailed report of a full-chain verification.
+///
+/// - `first_broken` is the first block of the main chain, starting from the origin, that failed
+///   one of the checks;
+/// - `orphans` lists the blocks of the registry that are not part of the main chain;
+/// - `dangling` lists the blocks whose `previous` or `next` link points to an unknown block;
+/// - `relinked` lists the blocks whose `next` link has been repaired, if requested.
+#[derive(
+	Debug, Clone,
+	Serialize, Deserialize
+)]
+pub struct ChainReport {
+	pub valid: bool,
+	pub blocks: usize,
+	pub length: usize,
+	pub origin: String,
+	pub head: String,
+	
+	pub first_broken: Option<ChainIssue>,
+	pub issues: Vec<ChainIssue>,
+	pub orphans: Vec<String>,
+	pub dangling: Vec<String>,
+	pub relinked: Vec<String>
+}
+
+
+/// Report of a registry prune.
+///
+/// - `kept` is the number of blocks of the main chain, which are kept in the registry;
+/// - `removed` lists the blocks that are not part of the main chain;
+/// - `relinked` lists the main chain blocks whose `next` link has been repaired.
+#[derive(
+	Debug, Clone,
+	Serialize, Deserialize
+)]
+pub struct PruneReport {
+	pub dry_run: bool,
+	pub kept: usize,
+	pub removed: Vec<String>,
+	pub relinked: Vec<String>
+}
+
+/// Tip of a branch of the registry, along with the cumulative work of this branch.
+#[derive(
+	Debug, Clone,
+	Serialize, Deserialize
+)]
+pub struct ChainTip {
+	pub hash: String,
+	pub height: u64,
+	pub chain_work: String
+}
+
+impl ChainTip {
+	
+	/// Instantiate a new `ChainTip` based on the given `BlockEntity`.
+	pub fn new(entity: &BlockEntity) -> Self {
+		ChainTip {
+			hash: entity.hash.clone(),
+			height: entity.height as u64,
+			chain_work: entity.chain_work.clone()
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,11 @@
+//! Chain management module.
+//!
+//! Operations spanning the whole blocks registry of a node, such as the audit or the prune of the
+//! main chain.
+
+mod chain_domain;
+mod chain_dto;
+pub mod chain_cli;
+
+pub use self::chain_domain::Chain;
+pub use self::chain_dto::{ChainReport, ChainIssue, PruneReport, ChainTip};
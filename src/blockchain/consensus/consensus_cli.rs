@@ -0,0 +1,61 @@
+//! Consensus command line interface.
+
+use error::*;
+use persistence::prelude::*;
+
+use sec::hex::ToHex;
+
+use blockchain::block::{Block, BlockRepository};
+use blockchain::identity::identity_cli::get_active_identity;
+use blockchain::consensus::{ConsensusConfig, ConsensusKind, ProofOfAuthorityEngine};
+use blockchain::network::NetworkConfig;
+
+/// Sign the comma-separated list of identity hashes allowed on the `network_id` network using the
+/// active identity of the node, acting as the network authority, and return the resulting `consensus`
+/// section of the network configuration.
+pub fn sign_authorities(identities: String, network_id: String) -> LocksidianResult<String> {
+	if network_id.is_empty() {
+		return Err(LocksidianError::new(String::from("The network identifier cannot be empty")));
+	}
+	
+	let connection = get_connection(database_path())?;
+	let identity = get_active_identity(&connection)?;
+	
+	let authorities: Vec<String> = identities.split(',')
+		.map(|hash| String::from(hash.trim()))
+		.filter(|hash| !hash.is_empty())
+		.collect();
+	
+	if authorities.is_empty() {
+		return Err(LocksidianError::new(String::from("The authorities allow-list cannot be empty")));
+	}
+	
+	let signature = identity.key().sign(ProofOfAuthorityEngine::message(network_id.as_ref(), &authorities).as_bytes())?;
+	let config = ConsensusConfig {
+		engine: ConsensusKind::ProofOfAuthority,
+		network_id: Some(network_id),
+		authority_key: Some(identity.public_key_to_hex()?),
+		authorities: authorities,
+		signature: Some(signature.to_hex())
+	};
+	
+	match ::serde_json::to_string_pretty(&config) {
+		Ok(json) => Ok(json),
+		Err(err) => Err(LocksidianError::from_err(err))
+	}
+}
+
+/// Check the consensus configuration of the network against the `ORIGIN` block stored in the
+/// registry, if any: the consensus rules pinned in the chain (e.g. the network authority key) cannot
+/// be changed by editing the network configuration of a single node.
+pub fn check_origin(connection: &SqliteConnection, config: &NetworkConfig) -> LocksidianResult<()> {
+	let engine = config.consensus_engine()?;
+	
+	match BlockRepository::new(&connection).get_by_height(1) {
+		Some(entity) => match engine.verify(&Block::from_entity(entity)?) {
+			Ok(_) => Ok(()),
+			Err(err) => Err(LocksidianError::new(format!("The network configuration does not match the ORIGIN block: {}", err.description())))
+		},
+		None => Ok(())
+	}
+}
@@ -0,0 +1,56 @@
+//! Consensus data transfer objects.
+
+/// Consensus engines available to a network.
+#[derive(
+    Debug, Clone, PartialEq,
+    Serialize, Deserialize
+)]
+pub enum ConsensusKind {
+    #[serde(rename = "proof-of-work")]
+    ProofOfWork,
+
+    #[serde(rename = "proof-of-authority")]
+    ProofOfAuthority
+}
+
+/// `consensus` section of the network configuration.
+///
+/// The `network_id` (identifier of the private network), `authority_key` (hexadecimal PEM-encoded
+/// public key of the network authority), `authorities` (allowed identity hashes) and `signature`
+/// (hexadecimal signature of the allow-list) fields are only used by the `proof-of-authority` engine.
+#[derive(
+    Debug, Clone,
+    Serialize, Deserialize
+)]
+pub struct ConsensusConfig {
+    #[serde(default = "default_engine")]
+    pub engine: ConsensusKind,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_id: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authority_key: Option<String>,
+
+    #[serde(default)]
+    pub authorities: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>
+}
+
+fn default_engine() -> ConsensusKind {
+    ConsensusKind::ProofOfWork
+}
+
+impl Default for ConsensusConfig {
+    fn default() -> Self {
+        ConsensusConfig {
+            engine: default_engine(),
+            network_id: None,
+            authority_key: None,
+            authorities: Vec::new(),
+            signature: None
+        }
+    }
+}
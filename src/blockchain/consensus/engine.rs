@@ -0,0 +1,38 @@
+//! `ConsensusEngine` trait definition.
+
+use error::*;
+use num_bigint::BigUint;
+
+use blockchain::block::Block;
+use blockchain::consensus::*;
+
+/// Consensus rules enforced by every node of a network when appending a block to its chain.
+pub trait ConsensusEngine {
+
+    /// Seal a newly created `Block`, returning its hash and nonce, or `None` if sealing was
+    /// cancelled (e.g. because a new `HEAD` block has been stored in the meantime).
+    fn seal(&self, block: &mut Block) -> LocksidianResult<Option<(String, u32)>>;
+
+    /// Returns an error if the `Block` does not satisfy the consensus rules of the network.
+    fn verify(&self, block: &Block) -> LocksidianResult<()>;
+
+    /// Work represented by the `Block`, used to select the heaviest branch of the chain.
+    fn work(&self, block: &Block) -> LocksidianResult<BigUint>;
+}
+
+/// Instantiate the `ConsensusEngine` described by the `consensus` section of the network configuration.
+pub fn build_engine(config: &ConsensusConfig) -> LocksidianResult<Box<ConsensusEngine>> {
+    let engine: Box<ConsensusEngine> = match config.engine {
+        ConsensusKind::ProofOfWork => Box::new(ProofOfWorkEngine),
+        ConsensusKind::ProofOfAuthority => match (config.network_id.as_ref(), config.authority_key.as_ref(), config.signature.as_ref()) {
+            (Some(network_id), Some(key), Some(signature)) => Box::new(
+                ProofOfAuthorityEngine::new(network_id, key, config.authorities.clone(), signature)?
+            ),
+            _ => return Err(LocksidianError::new(String::from(
+                "The proof-of-authority consensus engine requires the network_id, authority_key and signature fields"
+            )))
+        }
+    };
+
+    Ok(engine)
+}
\ No newline at end of file
@@ -0,0 +1,20 @@
+//! Consensus module.
+//!
+//! A `ConsensusEngine` decides which blocks may be appended to the chain of a network:
+//!
+//! - the `ProofOfWorkEngine` (default) requires every block to solve the size-based Proof of Work;
+//! - the `ProofOfAuthorityEngine` only allows the identities listed in an allow-list, signed by the
+//!   network authority, to append blocks.
+//!
+//! The engine is chosen per network using the `consensus` section of the network configuration.
+
+mod engine;
+mod proof_of_work;
+mod proof_of_authority;
+mod consensus_dto;
+pub mod consensus_cli;
+
+pub use self::engine::{ConsensusEngine, build_engine};
+pub use self::proof_of_work::ProofOfWorkEngine;
+pub use self::proof_of_authority::ProofOfAuthorityEngine;
+pub use self::consensus_dto::{ConsensusConfig, ConsensusKind};
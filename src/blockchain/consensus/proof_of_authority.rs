@@ -0,0 +1,165 @@
+//! Proof of Authority consensus engine.
+
+use error::*;
+use num_bigint::{BigUint, ToBigUint};
+
+use sec::hex::FromHex;
+use sec::rsa::Rsa;
+
+use blockchain::block::Block;
+use blockchain::consensus::ConsensusEngine;
+use blockchain::identity::identity_cli::compute_key_hash;
+
+/// Consensus engine used by private networks: only the identities listed in an allow-list may
+/// append blocks to the chain.
+///
+/// The allow-list is signed by the network authority, whose public key is shared by all the nodes of
+/// the network, so that it cannot be altered on a single node. The signature covers the identifier
+/// of the network, so that an allow-list signed for a network cannot be replayed on another network
+/// run by the same authority.
+///
+/// The authority key is pinned in the chain itself: the `ORIGIN` block of the network must be
+/// authored by the network authority. A node whose configuration announces another authority key
+/// rejects the `ORIGIN` block of the network, and refuses to start once it is stored.
+pub struct ProofOfAuthorityEngine {
+    authority: String,
+    authorities: Vec<String>
+}
+
+impl ProofOfAuthorityEngine {
+
+    /// Instantiate a new `ProofOfAuthorityEngine` once the hexadecimal `signature` of the
+    /// `authorities` allow-list of the `network_id` network has been verified using the hexadecimal
+    /// PEM-encoded `authority_key`.
+    pub fn new(network_id: &str, authority_key: &str, authorities: Vec<String>, signature: &str) -> LocksidianResult<Self> {
+        if network_id.is_empty() {
+            return Err(LocksidianError::new(String::from("The network identifier cannot be empty")));
+        }
+
+        let key = match authority_key.from_hex() {
+            Ok(pem) => Rsa::from_public_key(pem.as_slice())?,
+            Err(err) => return Err(LocksidianError::from_err(err))
+        };
+
+        let signature = match signature.from_hex() {
+            Ok(signature) => signature,
+            Err(err) => return Err(LocksidianError::from_err(err))
+        };
+
+        match key.verify_signature(ProofOfAuthorityEngine::message(network_id, &authorities).as_bytes(), signature.as_slice()) {
+            Ok(true) => Ok(ProofOfAuthorityEngine {
+                authority: compute_key_hash(&key)?,
+                authorities: authorities
+            }),
+            Ok(false) => Err(LocksidianError::new(String::from("The authorities allow-list signature does not match the network authority key"))),
+            Err(err) => Err(LocksidianError::new(format!("Unable to verify the authorities allow-list signature: {}", err.description())))
+        }
+    }
+
+    /// Message signed by the network authority: the network identifier followed by the list of the
+    /// allowed identity hashes, one per line.
+    pub fn message(network_id: &str, authorities: &[String]) -> String {
+        format!("{}\n{}", network_id, authorities.join("\n"))
+    }
+
+    /// Returns an error if the given identity is not part of the allow-list.
+    fn check_authority(&self, identity: &str) -> LocksidianResult<()> {
+        match self.authorities.iter().any(|authority| authority == identity) {
+            true => Ok(()),
+            false => Err(LocksidianError::new(format!("Identity {} is not allowed to append blocks to this network", identity)))
+        }
+    }
+
+    /// Returns an error if the given identity is not allowed to author a block built on top of the
+    /// `previous` one: the `ORIGIN` block (without `previous` block) can only be authored by the
+    /// network authority.
+    fn check_author(&self, identity: &str, previous: &str) -> LocksidianResult<()> {
+        match (previous.is_empty(), identity == self.authority) {
+            (false, _) => self.check_authority(identity),
+            (true, true) => Ok(()),
+            (true, false) => Err(LocksidianError::new(format!(
+                "The ORIGIN block should be authored by the network authority {}, not by {}", self.authority, identity
+            )))
+        }
+    }
+}
+
+impl ConsensusEngine for ProofOfAuthorityEngine {
+
+    /// No Proof of Work is required: the block hash is directly computed using a `0` nonce.
+    fn seal(&self, block: &mut Block) -> LocksidianResult<Option<(String, u32)>> {
+        self.check_author(block.author().as_ref(), block.previous().as_ref())?;
+        Ok(Some((block.calculate_hash(), 0)))
+    }
+
+    /// Returns an error if the block author is not part of the allow-list, or if the `ORIGIN` block
+    /// is not authored by the network authority.
+    fn verify(&self, block: &Block) -> LocksidianResult<()> {
+        self.check_author(block.author().as_ref(), block.previous().as_ref())
+    }
+
+    /// Every block represents the same amount of work: the heaviest branch is the longest one.
+    fn work(&self, _: &Block) -> LocksidianResult<BigUint> {
+        match 1.to_biguint() {
+            Some(work) => Ok(work),
+            None => Err(LocksidianError::new(String::from("Unable to compute the block work")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sec::hex::ToHex;
+
+    fn signed_engine(authority: &Rsa, authorities: Vec<String>) -> LocksidianResult<ProofOfAuthorityEngine> {
+        let key = authority.export_public_key().unwrap().to_hex();
+        let signature = authority.sign(ProofOfAuthorityEngine::message("private", &authorities).as_bytes()).unwrap().to_hex();
+
+        ProofOfAuthorityEngine::new("private", key.as_ref(), authorities, signature.as_ref())
+    }
+
+    #[test]
+    fn allow_list_signed_by_the_authority_should_be_accepted() {
+        let authority = Rsa::generate(2048).unwrap();
+        let engine = signed_engine(&authority, vec![String::from("alice"), String::from("bob")]).unwrap();
+
+        assert!(engine.check_authority("alice").is_ok());
+        assert!(engine.check_authority("bob").is_ok());
+        assert!(engine.check_authority("mallory").is_err());
+    }
+
+    #[test]
+    fn origin_block_should_be_authored_by_the_authority() {
+        let authority = Rsa::generate(2048).unwrap();
+        let hash = compute_key_hash(&authority).unwrap();
+        let engine = signed_engine(&authority, vec![String::from("alice")]).unwrap();
+
+        assert!(engine.check_author(hash.as_ref(), "").is_ok());
+        assert!(engine.check_author("alice", "").is_err());
+        assert!(engine.check_author("alice", "previous").is_ok());
+        assert!(engine.check_author("mallory", "previous").is_err());
+    }
+
+    #[test]
+    fn altered_allow_list_should_be_rejected() {
+        let authority = Rsa::generate(2048).unwrap();
+        let key = authority.export_public_key().unwrap().to_hex();
+        let signature = authority.sign(ProofOfAuthorityEngine::message("private", &vec![String::from("alice")]).as_bytes()).unwrap().to_hex();
+
+        let engine = ProofOfAuthorityEngine::new("private", key.as_ref(), vec![String::from("alice"), String::from("mallory")], signature.as_ref());
+        assert!(engine.is_err());
+    }
+
+    #[test]
+    fn allow_list_signed_for_another_network_should_be_rejected() {
+        let authority = Rsa::generate(2048).unwrap();
+        let key = authority.export_public_key().unwrap().to_hex();
+        let authorities = vec![String::from("alice")];
+        let signature = authority.sign(ProofOfAuthorityEngine::message("staging", &authorities).as_bytes()).unwrap().to_hex();
+
+        assert!(ProofOfAuthorityEngine::new("staging", key.as_ref(), authorities.clone(), signature.as_ref()).is_ok());
+        assert!(ProofOfAuthorityEngine::new("production", key.as_ref(), authorities.clone(), signature.as_ref()).is_err());
+        assert!(ProofOfAuthorityEngine::new("", key.as_ref(), authorities, signature.as_ref()).is_err());
+    }
+}
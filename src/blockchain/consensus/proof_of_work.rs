@@ -0,0 +1,33 @@
+//! Proof of Work consensus engine.
+
+use error::*;
+use num_bigint::BigUint;
+
+use blockchain::algorithm::ProofOfWork;
+use blockchain::block::Block;
+use blockchain::consensus::ConsensusEngine;
+
+/// Default consensus engine: every block has to solve a Proof of Work whose difficulty depends on
+/// the size of its data.
+pub struct ProofOfWorkEngine;
+
+impl ConsensusEngine for ProofOfWorkEngine {
+
+    /// Compute the block nonce using the Proof of Work algorithm.
+    fn seal(&self, block: &mut Block) -> LocksidianResult<Option<(String, u32)>> {
+        block.compute()
+    }
+
+    /// Returns an error if the block hash is not lower than its Proof of Work target.
+    fn verify(&self, block: &Block) -> LocksidianResult<()> {
+        match block.validate()? {
+            Some(_) => Ok(()),
+            None => Err(LocksidianError::new(String::from("Block hash does not satisfy the Proof of Work target")))
+        }
+    }
+
+    /// The work of a block is the expected number of hashes required to solve its Proof of Work.
+    fn work(&self, block: &Block) -> LocksidianResult<BigUint> {
+        ProofOfWork::work(block)
+    }
+}
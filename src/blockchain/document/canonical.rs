@@ -0,0 +1,178 @@
+//! Canonicalisation of the JSON documents.
+//!
+//! By default, a document is certified byte-for-byte: `{"a":1,"b":2}` and `{"b":2, "a":1}` are two
+//! different documents. Using the JSON Canonicalization Scheme (JCS, RFC 8785), a document must be
+//! valid JSON and is canonicalised before being hashed: its object members are sorted by key, its
+//! insignificant whitespaces are removed and its numbers are serialized as ECMAScript does.
+
+use error::*;
+
+use serde_json::Value;
+
+/// Canonicalisation mode applied to the documents before they are hashed.
+#[derive(
+    Debug, Clone, Copy, PartialEq,
+    Serialize, Deserialize
+)]
+pub enum Canonicalization {
+
+    /// Documents are stored and hashed as submitted.
+    #[serde(rename = "raw")]
+    Raw,
+
+    /// Documents must be valid JSON, and are canonicalised using RFC 8785.
+    #[serde(rename = "jcs")]
+    Jcs
+}
+
+impl Default for Canonicalization {
+    fn default() -> Self {
+        Canonicalization::Raw
+    }
+}
+
+impl Canonicalization {
+
+    /// Returns the mode recorded in the block header, empty for the `Raw` mode so that the hash of
+    /// the blocks storing raw documents remains unchanged.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Canonicalization::Raw => "",
+            Canonicalization::Jcs => "jcs"
+        }
+    }
+
+    /// Parse the mode recorded in a block header.
+    pub fn from_str(mode: &str) -> LocksidianResult<Self> {
+        match mode {
+            "" => Ok(Canonicalization::Raw),
+            "jcs" => Ok(Canonicalization::Jcs),
+            _ => Err(LocksidianError::new(format!("Unknown document canonicalization mode: {}", mode)))
+        }
+    }
+
+    /// Apply the canonicalisation mode to the given `document`. Returns an error if the mode
+    /// requires a JSON document and the `document` cannot be parsed.
+    pub fn apply(&self, document: &str) -> LocksidianResult<String> {
+        match *self {
+            Canonicalization::Raw => Ok(String::from(document)),
+            Canonicalization::Jcs => match ::serde_json::from_str::<Value>(document) {
+                Ok(value) => canonicalize(&value),
+                Err(err) => Err(LocksidianError::new(format!("The document is not valid JSON: {}", err)))
+            }
+        }
+    }
+}
+
+/// Serialize a JSON `value` using the JSON Canonicalization Scheme.
+fn canonicalize(value: &Value) -> LocksidianResult<String> {
+    match *value {
+        Value::Null | Value::Bool(_) | Value::String(_) => match ::serde_json::to_string(value) {
+            Ok(json) => Ok(json),
+            Err(err) => Err(LocksidianError::from_err(err))
+        },
+        Value::Number(ref number) => match number.as_f64() {
+            Some(number) => Ok(format_number(number)),
+            None => Err(LocksidianError::new(format!("Unable to canonicalize the number {}", number)))
+        },
+        Value::Array(ref values) => {
+            let mut members = Vec::new();
+            for value in values.iter() {
+                members.push(canonicalize(value)?);
+            }
+
+            Ok(format!("[{}]", members.join(",")))
+        },
+        Value::Object(ref object) => {
+            // Object members are sorted by the UTF-16 code units of their keys
+            let mut keys: Vec<&String> = object.keys().collect();
+            keys.sort_by_key(|key| key.encode_utf16().collect::<Vec<u16>>());
+
+            let mut members = Vec::new();
+            for key in keys {
+                let value = match object.get(key) {
+                    Some(value) => canonicalize(value)?,
+                    None => continue
+                };
+
+                members.push(format!("{}:{}", canonicalize(&Value::String(key.clone()))?, value));
+            }
+
+            Ok(format!("{{{}}}", members.join(",")))
+        }
+    }
+}
+
+/// Serialize a number as ECMAScript does: using the shortest representation that round-trips,
+/// without any exponent for the numbers between `1e-7` and `1e21`.
+fn format_number(number: f64) -> String {
+    if number == 0.0 {
+        return String::from("0");
+    }
+
+    // The shortest round-trip digits, along with the decimal exponent
+    let scientific = format!("{:e}", number.abs());
+    let (mantissa, exponent) = match scientific.find('e') {
+        Some(index) => (&scientific[..index], scientific[index + 1..].parse::<i32>().unwrap_or(0)),
+        None => (&scientific[..], 0)
+    };
+
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    let n = exponent + 1;
+
+    let formatted = if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat(-n as usize), digits)
+    } else {
+        let sign = match n - 1 >= 0 {
+            true => "+",
+            false => "-"
+        };
+
+        match k {
+            1 => format!("{}e{}{}", digits, sign, (n - 1).abs()),
+            _ => format!("{}.{}e{}{}", &digits[..1], &digits[1..], sign, (n - 1).abs())
+        }
+    };
+
+    match number < 0.0 {
+        true => format!("-{}", formatted),
+        false => formatted
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn jcs_should_not_depend_on_the_members_order_nor_whitespaces() {
+        let first = Canonicalization::Jcs.apply(r#"{"a":1,"b":2}"#).unwrap();
+        let second = Canonicalization::Jcs.apply("{\"b\": 2,\n \"a\": 1}").unwrap();
+
+        assert_eq!(first, r#"{"a":1,"b":2}"#);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn jcs_should_serialize_numbers_as_ecmascript() {
+        assert_eq!(format_number(1.0), "1");
+        assert_eq!(format_number(-0.5), "-0.5");
+        assert_eq!(format_number(123.456), "123.456");
+        assert_eq!(format_number(0.000001), "0.000001");
+        assert_eq!(format_number(0.0000001), "1e-7");
+        assert_eq!(format_number(1e21), "1e+21");
+        assert_eq!(format_number(1e20), "100000000000000000000");
+        assert_eq!(format_number(4.5e-10), "4.5e-10");
+    }
+
+    #[test]
+    fn jcs_should_reject_invalid_json() {
+        assert!(Canonicalization::Jcs.apply("not json").is_err());
+        assert_eq!(Canonicalization::Raw.apply("not json").unwrap(), "not json");
+    }
+}
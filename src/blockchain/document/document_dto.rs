@@ -0,0 +1,362 @@
+//! Document data transfer objects.
+
+use error::*;
+
+use serde_json::Value;
+use std::collections::HashSet;
+use sec::merkle::{MerkleStep, merkle_proof, verify_merkle_proof};
+use sec::sha::sha512;
+
+use blockchain::block::{Block, BlockEntity, BlockRepository};
+use blockchain::document::Canonicalization;
+
+/// Merkle inclusion proof of a document, proving that it is committed in the header of a block
+/// without having to download the other documents of the block.
+///
+/// Hashing the `data_hash` leaf along with each step of the `path` leads to the `merkle_root`. For a
+/// block storing a single document, the `path` is empty and the `merkle_root` is the `data_hash`.
+#[derive(
+    Debug, Clone,
+    Serialize, Deserialize
+)]
+pub struct DocumentProofDto {
+    pub data_hash: String,
+    pub block: String,
+    pub position: usize,
+    pub merkle_root: String,
+    pub path: Vec<MerkleStep>
+}
+
+impl DocumentProofDto {
+
+    /// Build the inclusion proof of the document stored at the given `position` of the `Block`,
+    /// making sure that it leads to the Merkle root of the block header.
+    pub fn new(block: &Block, position: usize) -> LocksidianResult<Self> {
+        let leaves = block.document_hashes()?;
+
+        let (data_hash, path) = match (leaves.get(position), merkle_proof(&leaves, position)) {
+            (Some(data_hash), Some(path)) => (data_hash.clone(), path),
+            _ => return Err(LocksidianError::new(format!("Block {} has no document at position {}", block.hash(), position)))
+        };
+
+        let merkle_root = match block.merkle_root().is_empty() {
+            true => block.data_hash(),
+            false => block.merkle_root()
+        };
+
+        let proof = DocumentProofDto {
+            data_hash: data_hash,
+            block: block.hash(),
+            position: position,
+            merkle_root: merkle_root,
+            path: path
+        };
+
+        match proof.is_committed_in(&block) {
+            true => Ok(proof),
+            false => Err(LocksidianError::new(format!("The documents of block {} do not match its Merkle root", block.hash())))
+        }
+    }
+
+    /// Returns `true` if the proof leads to the documents commitment of the `Block` header: the
+    /// Merkle root of a batch block, or the checksum of the single document of any other block.
+    pub fn is_committed_in(&self, block: &Block) -> bool {
+        if self.block != block.hash() {
+            return false;
+        }
+
+        match block.merkle_root().is_empty() {
+            true => self.path.is_empty() && self.merkle_root == block.data_hash() && self.data_hash == block.data_hash(),
+            false => self.merkle_root == block.merkle_root()
+                && verify_merkle_proof(self.data_hash.as_ref(), &self.path, self.merkle_root.as_ref())
+        }
+    }
+}
+
+/// Location of a document in the blockchain: the block storing it, the position of the document in
+/// the block and the position of the block in the chain.
+#[derive(
+    Debug, Clone,
+    Serialize, Deserialize
+)]
+pub struct DocumentLocationDto {
+    pub data_hash: String,
+    pub block: String,
+    pub position: usize,
+    pub height: u64,
+    pub timestamp: u64,
+    pub author: String,
+
+    /// `true` if the document is anchored through its checksum only.
+    pub anchor: bool,
+
+    /// `false` if the block storing the document is an orphan block.
+    pub main_chain: bool,
+
+    /// Number of main chain blocks confirming the document, i.e. the blocks from the one storing it
+    /// up to the `HEAD` block (`0` for an orphan block).
+    pub confirmations: u64,
+
+    /// Checksum of the document superseded by this revision, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub supersedes: Option<String>
+}
+
+impl DocumentLocationDto {
+
+    /// Locate the document identified by its `data_hash` in the registry. Returns `None` if the
+    /// document is not stored in any block.
+    pub fn locate(data_hash: &str, repository: &BlockRepository) -> Option<Self> {
+        match repository.locate_document(data_hash) {
+            Some((entity, position)) => {
+                let head_height = repository.get_head().map(|head| head.height as u64).unwrap_or(0);
+                Some(DocumentLocationDto::new(data_hash, &entity, position, head_height))
+            },
+            None => None
+        }
+    }
+
+    /// Instantiate a new `DocumentLocationDto` based on the given `BlockEntity`.
+    fn new(data_hash: &str, entity: &BlockEntity, position: usize, head_height: u64) -> Self {
+        let height = entity.height as u64;
+
+        DocumentLocationDto {
+            data_hash: String::from(data_hash),
+            block: entity.hash.clone(),
+            position: position,
+            height: height,
+            timestamp: entity.timestamp as u64,
+            author: entity.author.clone(),
+
+            anchor: !entity.metadata_hash.is_empty(),
+            main_chain: !entity.orphan,
+            confirmations: match entity.orphan || head_height < height {
+                true => 0,
+                false => head_height - height + 1
+            },
+            supersedes: match entity.supersedes.is_empty() {
+                true => None,
+                false => Some(entity.supersedes.clone())
+            }
+        }
+    }
+}
+
+/// Revision chain of a document, from its original version to its latest revision:
+///
+/// ```json
+/// {
+///     "data_hash": "{checksum of the requested document}",
+///     "versions": [{location of each version, from the original one to the latest one}]
+/// }
+/// ```
+#[derive(
+    Debug, Clone,
+    Serialize, Deserialize
+)]
+pub struct DocumentHistoryDto {
+    pub data_hash: String,
+    pub versions: Vec<DocumentLocationDto>
+}
+
+impl DocumentHistoryDto {
+
+    /// Walk the revision chain of the document identified by its `data_hash`, both ways. Only the
+    /// versions stored in the main chain by the author of the document are followed. Returns `None` if the document is
+    /// not stored in any block.
+    pub fn new(data_hash: &str, repository: &BlockRepository) -> Option<Self> {
+        let location = match DocumentLocationDto::locate(data_hash, &repository) {
+            Some(location) => location,
+            None => return None
+        };
+
+        let author = location.author.clone();
+        let mut visited: HashSet<String> = HashSet::new();
+        visited.insert(location.data_hash.clone());
+
+        // Previous versions, up to the original one
+        let mut previous = location.supersedes.clone();
+        let mut versions = vec![location];
+
+        while let Some(supersedes) = previous {
+            previous = None;
+
+            if let Some(version) = DocumentLocationDto::locate(supersedes.as_ref(), &repository) {
+                if version.author == author && version.main_chain && visited.insert(version.data_hash.clone()) {
+                    previous = version.supersedes.clone();
+                    versions.insert(0, version);
+                }
+            }
+        }
+
+        // Next versions, up to the latest one
+        let mut latest = String::from(data_hash);
+
+        loop {
+            let next = match repository.get_superseding(latest.as_ref()) {
+                Some(entity) => DocumentLocationDto::locate(entity.data_hash.as_ref(), &repository),
+                None => None
+            };
+
+            match next {
+                Some(version) => match version.author == author && version.main_chain && visited.insert(version.data_hash.clone()) {
+                    true => {
+                        latest = version.data_hash.clone();
+                        versions.push(version);
+                    },
+                    false => break
+                },
+                None => break
+            }
+        }
+
+        Some(DocumentHistoryDto {
+            data_hash: String::from(data_hash),
+            versions: versions
+        })
+    }
+}
+
+/// Hash-only anchoring request of a confidential document, which never leaves the premises of its
+/// author:
+///
+/// ```json
+/// {
+///     "data_hash": "{sha512 checksum of the document}",
+///     "metadata": {optional JSON metadata}
+/// }
+/// ```
+#[derive(
+    Debug, Clone,
+    Serialize, Deserialize
+)]
+pub struct AnchorDto {
+    pub data_hash: String,
+
+    #[serde(default)]
+    pub metadata: Option<Value>
+}
+
+/// Result of the verification of a raw document against the registry:
+///
+/// ```json
+/// {
+///     "data_hash": "{sha512 checksum of the document}",
+///     "certified": true,
+///     "location": {location of the document, even in an orphan block}
+/// }
+/// ```
+#[derive(
+    Debug, Clone,
+    Serialize, Deserialize
+)]
+pub struct DocumentVerificationDto {
+    pub data_hash: String,
+    pub certified: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<DocumentLocationDto>
+}
+
+impl DocumentVerificationDto {
+
+    /// Search the registry for the document identified by its `data_hash`. The document is only
+    /// certified when it is stored on the main chain.
+    pub fn new(data_hash: String, repository: &BlockRepository) -> Self {
+        let location = DocumentLocationDto::locate(data_hash.as_ref(), &repository);
+
+        DocumentVerificationDto {
+            data_hash: data_hash,
+            certified: location.as_ref().map(|location| location.main_chain).unwrap_or(false),
+            location: location
+        }
+    }
+
+    /// Search the registry for the given raw `document`. When the network canonicalises the
+    /// documents, the checksum of its canonical form is used, except for the anchors: their checksum
+    /// is computed by their author on the document as it is, which may not even be JSON.
+    pub fn verify(document: &str, mode: Canonicalization, repository: &BlockRepository) -> LocksidianResult<Self> {
+        let raw = DocumentVerificationDto::new(sha512(document.as_bytes()), &repository);
+
+        match (mode, raw.location.as_ref().map(|location| location.anchor).unwrap_or(false)) {
+            (Canonicalization::Raw, _) | (_, true) => Ok(raw),
+            _ => {
+                let canonical = mode.apply(document)?;
+                Ok(DocumentVerificationDto::new(sha512(canonical.as_bytes()), &repository))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use persistence::prelude::*;
+
+    #[test]
+    fn confirmations_should_count_the_main_chain_blocks_up_to_the_head() {
+        let mut entity = BlockEntity::empty();
+        entity.height = 3;
+
+        assert_eq!(DocumentLocationDto::new("", &entity, 0, 3).confirmations, 1);
+        assert_eq!(DocumentLocationDto::new("", &entity, 0, 7).confirmations, 5);
+
+        entity.orphan = true;
+        assert_eq!(DocumentLocationDto::new("", &entity, 0, 7).confirmations, 0);
+    }
+
+    #[test]
+    fn anchors_should_be_verified_on_a_jcs_network() {
+        let connection = get_connection(String::from(":memory:")).unwrap();
+        setup_database(&connection).unwrap();
+        let repository = BlockRepository::new(&connection);
+
+        // The author anchors the checksum of the document as it is, not of its canonical form
+        let document = r#"{"b": 2, "a": 1}"#;
+        let mut anchor = BlockEntity::empty();
+        anchor.hash = String::from("anchor");
+        anchor.data_hash = sha512(document.as_bytes());
+        anchor.metadata_hash = sha512(b"");
+        anchor.height = 1;
+        repository.save(&anchor).unwrap();
+
+        let verification = DocumentVerificationDto::verify(document, Canonicalization::Jcs, &repository).unwrap();
+        assert!(verification.certified);
+        assert_eq!(verification.data_hash, anchor.data_hash);
+
+        // A stored document is only found through its canonical form
+        let canonical = DocumentVerificationDto::verify(r#"{"a": 1, "b": 2}"#, Canonicalization::Jcs, &repository).unwrap();
+        assert!(!canonical.certified);
+        assert!(DocumentVerificationDto::verify("not json", Canonicalization::Jcs, &repository).is_err());
+    }
+
+    #[test]
+    fn documents_of_orphan_blocks_should_not_be_certified() {
+        let connection = get_connection(String::from(":memory:")).unwrap();
+        setup_database(&connection).unwrap();
+        let repository = BlockRepository::new(&connection);
+
+        let mut orphan = BlockEntity::empty();
+        orphan.hash = String::from("orphan");
+        orphan.data_hash = sha512(b"document");
+        orphan.height = 2;
+        orphan.orphan = true;
+        repository.save(&orphan).unwrap();
+
+        let verification = DocumentVerificationDto::new(orphan.data_hash.clone(), &repository);
+        assert!(!verification.certified);
+        assert!(!verification.location.unwrap().main_chain);
+        assert!(Block::assert_document_uniqueness(&orphan.data_hash, &repository).is_ok());
+
+        // The document mined again on the main chain is located there
+        let mut block = orphan.clone();
+        block.hash = String::from("block");
+        block.orphan = false;
+        repository.save(&block).unwrap();
+
+        let verification = DocumentVerificationDto::new(orphan.data_hash.clone(), &repository);
+        assert!(verification.certified);
+        assert_eq!(verification.location.unwrap().block, block.hash);
+        assert!(Block::assert_document_uniqueness(&orphan.data_hash, &repository).is_err());
+    }
+}
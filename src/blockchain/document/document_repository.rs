@@ -0,0 +1,61 @@
+//! Document Repository module.
+
+use persistence::prelude::*;
+
+table! {
+    documents(data_hash) {
+        data_hash -> VarChar,
+        block -> VarChar,
+        position -> Integer,
+    }
+}
+
+/// Position of a document inside of a batch block.
+#[derive(
+    Debug, Clone,
+    Queryable, Insertable, AsChangeset
+)]
+#[table_name = "documents"]
+pub struct DocumentEntity {
+    pub data_hash: String,
+    pub block: String,
+    pub position: i32
+}
+
+impl DocumentEntity {
+
+    /// Instantiate a new `DocumentEntity` locating the document at the given `position` of a block.
+    pub fn new(data_hash: String, block: String, position: usize) -> Self {
+        DocumentEntity {
+            data_hash: data_hash,
+            block: block,
+            position: position as i32
+        }
+    }
+}
+
+pub struct DocumentRepository<'pool> {
+    connection: &'pool SqliteConnection
+}
+
+impl<'pool> DocumentRepository<'pool> {
+
+    /// Instantiate a new `DocumentRepository` whose lifetime is bound to its pooled connection.
+    pub fn new(connection: &SqliteConnection) -> DocumentRepository {
+        DocumentRepository {
+            connection: connection
+        }
+    }
+
+    /// Remove the documents of the given blocks and return the number of deleted rows.
+    ///
+    /// The number of block hashes should stay below the SQLite host parameters limit.
+    pub fn delete_by_blocks(&self, blocks: &[String]) -> LocksidianResult<usize> {
+        match ::diesel::delete(documents::table.filter(documents::block.eq_any(blocks.to_vec()))).execute(self.connection) {
+            Ok(deleted_rows) => Ok(deleted_rows),
+            Err(err) => Err(LocksidianError::from_err(err))
+        }
+    }
+}
+
+crud_repository!(documents, DocumentEntity, String, data_hash, DocumentRepository<'pool>);
@@ -0,0 +1,273 @@
+//! Documents encrypted for a set of recipient identities.
+//!
+//! The document is encrypted using a random AES-256-GCM key, which is then wrapped using the RSA
+//! public key of each recipient (OAEP padding). The block stores the resulting envelope instead of
+//! the document, and records the cipher in its `encryption` header field:
+//!
+//! ```json
+//! {
+//!     "encrypted": {
+//!         "cipher": "aes-256-gcm",
+//!         "iv": "{hexadecimal initialization vector}",
+//!         "tag": "{hexadecimal authentication tag}",
+//!         "ciphertext": "{hexadecimal encrypted document}",
+//!         "recipients": [{"identity": "{identity hash}", "key": "{hexadecimal wrapped key}"}]
+//!     }
+//! }
+//! ```
+//!
+//! The envelope is certified like any other document, while only the recipients are able to read
+//! the document it carries. The nodes replicating an encrypted block cannot check the plaintext
+//! against the network schemas, so they check the structure of the envelope instead (see
+//! `EncryptedDocumentDto::from_envelope`).
+
+use error::*;
+
+use std::collections::HashSet;
+
+use sec::aes;
+use sec::rsa::Rsa;
+use sec::hex::{FromHex, ToHex};
+
+/// Symmetric key of an encrypted document, wrapped for one of its recipients.
+#[derive(
+    Debug, Clone,
+    Serialize, Deserialize
+)]
+#[serde(deny_unknown_fields)]
+pub struct RecipientKeyDto {
+    pub identity: String,
+    pub key: String
+}
+
+/// Document encrypted for a set of recipient identities.
+#[derive(
+    Debug, Clone,
+    Serialize, Deserialize
+)]
+#[serde(deny_unknown_fields)]
+pub struct EncryptedDocumentDto {
+    pub cipher: String,
+    pub iv: String,
+    pub tag: String,
+    pub ciphertext: String,
+    pub recipients: Vec<RecipientKeyDto>
+}
+
+/// Envelope stored in the block in place of the encrypted document.
+#[derive(
+    Debug, Clone,
+    Serialize, Deserialize
+)]
+#[serde(deny_unknown_fields)]
+struct EnvelopeDto {
+    encrypted: EncryptedDocumentDto
+}
+
+impl EncryptedDocumentDto {
+
+    /// Encrypt the `document` for the given `(identity hash, public key)` recipients.
+    pub fn seal(document: &str, recipients: &[(String, Rsa)]) -> LocksidianResult<Self> {
+        if recipients.is_empty() {
+            return Err(LocksidianError::new(String::from("An encrypted document must have at least one recipient")));
+        }
+
+        let key = aes::random_bytes(aes::KEY_SIZE)?;
+        let iv = aes::random_bytes(aes::IV_SIZE)?;
+        let (ciphertext, tag) = aes::encrypt(&key, &iv, document.as_bytes())?;
+
+        let mut wrapped_keys = Vec::new();
+        for &(ref identity, ref public_key) in recipients.iter() {
+            wrapped_keys.push(RecipientKeyDto {
+                identity: identity.clone(),
+                key: public_key.encrypt(&key)?.to_hex()
+            });
+        }
+
+        Ok(EncryptedDocumentDto {
+            cipher: String::from(aes::CIPHER),
+            iv: iv.to_hex(),
+            tag: tag.to_hex(),
+            ciphertext: ciphertext.to_hex(),
+            recipients: wrapped_keys
+        })
+    }
+
+    /// Parse and validate the envelope of a `document` stored in a block flagged as encrypted using
+    /// the given `cipher`. Returns an error if the document is not a well-formed envelope.
+    pub fn from_envelope(document: &str, cipher: &str) -> LocksidianResult<Self> {
+        let envelope = match ::serde_json::from_str::<EnvelopeDto>(document) {
+            Ok(envelope) => envelope.encrypted,
+            Err(err) => return Err(LocksidianError::new(format!("Invalid encrypted document envelope: {}", err)))
+        };
+
+        envelope.validate(cipher)?;
+        Ok(envelope)
+    }
+
+    /// Returns an error if the envelope does not use the given `cipher`, if its initialization
+    /// vector, authentication tag, ciphertext or wrapped keys are not hexadecimal values of the
+    /// expected size, or if it does not have a single key for each of its recipients.
+    fn validate(&self, cipher: &str) -> LocksidianResult<()> {
+        if self.cipher != cipher || self.cipher != aes::CIPHER {
+            return Err(LocksidianError::new(format!("Unsupported document cipher: {}", self.cipher)));
+        }
+
+        check_hex("initialization vector", self.iv.as_ref(), Some(aes::IV_SIZE))?;
+        check_hex("authentication tag", self.tag.as_ref(), Some(aes::TAG_SIZE))?;
+        check_hex("ciphertext", self.ciphertext.as_ref(), None)?;
+
+        if self.recipients.is_empty() {
+            return Err(LocksidianError::new(String::from("An encrypted document must have at least one recipient")));
+        }
+
+        let mut identities = HashSet::new();
+        for recipient in self.recipients.iter() {
+            if recipient.identity.is_empty() || !identities.insert(recipient.identity.as_str()) {
+                return Err(LocksidianError::new(format!("Invalid or duplicate recipient identity: \"{}\"", recipient.identity)));
+            }
+
+            check_hex("wrapped key", recipient.key.as_ref(), None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Serialize the envelope to be stored in the block.
+    pub fn to_json(&self) -> LocksidianResult<String> {
+        let envelope = EnvelopeDto {
+            encrypted: self.clone()
+        };
+
+        match ::serde_json::to_string(&envelope) {
+            Ok(json) => Ok(json),
+            Err(err) => Err(LocksidianError::from_err(err))
+        }
+    }
+
+    /// Returns `true` if the document was encrypted for the given `identity`.
+    pub fn is_recipient(&self, identity: &str) -> bool {
+        self.recipients.iter().any(|recipient| recipient.identity == identity)
+    }
+
+    /// Decrypt the document using the private `key` of the recipient `identity`.
+    pub fn open(&self, identity: &str, key: &Rsa) -> LocksidianResult<String> {
+        if self.cipher != aes::CIPHER {
+            return Err(LocksidianError::new(format!("Unsupported document cipher: {}", self.cipher)));
+        }
+
+        let wrapped_key = match self.recipients.iter().find(|recipient| recipient.identity == identity) {
+            Some(recipient) => from_hex(recipient.key.as_ref())?,
+            None => return Err(LocksidianError::new(format!("The document was not encrypted for identity {}", identity)))
+        };
+
+        let symmetric_key = key.decrypt(&wrapped_key)?;
+        let plaintext = aes::decrypt(
+            &symmetric_key,
+            &from_hex(self.iv.as_ref())?,
+            &from_hex(self.ciphertext.as_ref())?,
+            &from_hex(self.tag.as_ref())?
+        )?;
+
+        match String::from_utf8(plaintext) {
+            Ok(document) => Ok(document),
+            Err(err) => Err(LocksidianError::from_err(err))
+        }
+    }
+}
+
+/// Returns an error if the `name`d envelope field is not a non-empty hexadecimal value, of `size`
+/// bytes when given.
+fn check_hex(name: &str, hexadecimal: &str, size: Option<usize>) -> LocksidianResult<()> {
+    let bytes = from_hex(hexadecimal)?;
+
+    match (bytes.is_empty(), size) {
+        (true, _) => Err(LocksidianError::new(format!("The {} of the envelope cannot be empty", name))),
+        (false, Some(size)) if bytes.len() != size => Err(LocksidianError::new(format!("The {} of the envelope must be {} bytes long", name, size))),
+        _ => Ok(())
+    }
+}
+
+fn from_hex(hexadecimal: &str) -> LocksidianResult<Vec<u8>> {
+    match hexadecimal.from_hex() {
+        Ok(bytes) => Ok(bytes),
+        Err(err) => Err(LocksidianError::from_err(err))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sec::rsa::Rsa;
+
+    #[test]
+    fn only_the_recipients_should_decrypt_the_document() {
+        let alice = Rsa::generate(2048).unwrap();
+        let bob = Rsa::generate(2048).unwrap();
+        let document = r#"{"patient":"John Doe"}"#;
+
+        let sealed = EncryptedDocumentDto::seal(document, &[(String::from("alice"), alice)]).unwrap();
+        let envelope = sealed.to_json().unwrap();
+        assert!(!envelope.contains("John Doe"));
+
+        let parsed = EncryptedDocumentDto::from_envelope(envelope.as_ref(), aes::CIPHER).unwrap();
+        assert!(parsed.is_recipient("alice"));
+        assert!(!parsed.is_recipient("bob"));
+        assert!(parsed.open("bob", &bob).is_err());
+
+        let mallory = Rsa::generate(2048).unwrap();
+        assert!(parsed.open("alice", &mallory).is_err());
+    }
+
+    #[test]
+    fn sealed_document_should_be_opened_by_each_recipient() {
+        let alice = Rsa::generate(2048).unwrap();
+        let bob = Rsa::generate(2048).unwrap();
+        let alice_pem = alice.export_private_key().unwrap();
+        let bob_pem = bob.export_private_key().unwrap();
+        let document = r#"{"patient":"John Doe"}"#;
+
+        let sealed = EncryptedDocumentDto::seal(document, &[
+            (String::from("alice"), alice),
+            (String::from("bob"), bob)
+        ]).unwrap();
+
+        let alice = Rsa::from_private_key(alice_pem.as_slice(), "").unwrap();
+        let bob = Rsa::from_private_key(bob_pem.as_slice(), "").unwrap();
+        assert_eq!(sealed.open("alice", &alice).unwrap(), document);
+        assert_eq!(sealed.open("bob", &bob).unwrap(), document);
+    }
+
+    #[test]
+    fn plain_documents_should_not_be_parsed_as_envelopes() {
+        assert!(EncryptedDocumentDto::from_envelope("Hello World!", aes::CIPHER).is_err());
+        assert!(EncryptedDocumentDto::from_envelope(r#"{"encrypted":true}"#, aes::CIPHER).is_err());
+    }
+
+    #[test]
+    fn malformed_envelopes_should_be_rejected() {
+        let alice = Rsa::generate(2048).unwrap();
+        let sealed = EncryptedDocumentDto::seal("{}", &[(String::from("alice"), alice)]).unwrap();
+        let envelope = sealed.to_json().unwrap();
+        assert!(EncryptedDocumentDto::from_envelope(envelope.as_ref(), aes::CIPHER).is_ok());
+        assert!(EncryptedDocumentDto::from_envelope(envelope.as_ref(), "aes-128-cbc").is_err());
+        assert!(EncryptedDocumentDto::from_envelope(r#"{"patient":"John Doe"}"#, aes::CIPHER).is_err());
+
+        let mut short_iv = sealed.clone();
+        short_iv.iv = String::from("00");
+        assert!(EncryptedDocumentDto::from_envelope(short_iv.to_json().unwrap().as_ref(), aes::CIPHER).is_err());
+
+        let mut no_recipient = sealed.clone();
+        no_recipient.recipients.clear();
+        assert!(EncryptedDocumentDto::from_envelope(no_recipient.to_json().unwrap().as_ref(), aes::CIPHER).is_err());
+
+        let mut duplicate = sealed.clone();
+        let recipient = duplicate.recipients[0].clone();
+        duplicate.recipients.push(recipient);
+        assert!(EncryptedDocumentDto::from_envelope(duplicate.to_json().unwrap().as_ref(), aes::CIPHER).is_err());
+
+        let mut plaintext = sealed.clone();
+        plaintext.ciphertext = String::from("John Doe");
+        assert!(EncryptedDocumentDto::from_envelope(plaintext.to_json().unwrap().as_ref(), aes::CIPHER).is_err());
+    }
+}
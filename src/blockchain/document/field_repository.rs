@@ -0,0 +1,220 @@
+//! Field Repository module.
+
+use persistence::prelude::*;
+
+use diesel::expression::dsl::sql;
+use diesel::types::Bool;
+use serde_json::Value;
+
+use sec::sha::sha512;
+use blockchain::document::{DocumentQuery, FieldOperator};
+
+/// Only keep the field of the first block storing a document (by timestamp, then by hash), so that
+/// a document stored in several competing blocks is matched once.
+const FIRST_STORED: &'static str = "NOT EXISTS (SELECT 1 FROM `fields` AS `first` WHERE `first`.`data_hash` = `fields`.`data_hash` \
+    AND `first`.`path` = `fields`.`path` AND (`first`.`timestamp` < `fields`.`timestamp` \
+    OR (`first`.`timestamp` = `fields`.`timestamp` AND `first`.`block` < `fields`.`block`)))";
+
+table! {
+    fields(id) {
+        id -> VarChar,
+        data_hash -> VarChar,
+        block -> VarChar,
+        path -> VarChar,
+        kind -> VarChar,
+        value -> VarChar,
+        number -> Nullable<Double>,
+        author -> VarChar,
+        timestamp -> Integer,
+    }
+}
+
+/// Indexed value of a field of a JSON document, along with the author and the timestamp of the
+/// block storing the document.
+#[derive(
+    Debug, Clone,
+    Queryable, Insertable, AsChangeset
+)]
+#[table_name = "fields"]
+pub struct FieldEntity {
+    pub id: String,
+    pub data_hash: String,
+    pub block: String,
+    pub path: String,
+    pub kind: String,
+    pub value: String,
+    pub number: Option<f64>,
+    pub author: String,
+    pub timestamp: i32
+}
+
+impl FieldEntity {
+
+    /// Instantiate a new `FieldEntity` indexing the `value` located at `path` in the document
+    /// identified by its `data_hash`, stored in the given block.
+    pub fn new(data_hash: &str, block: &str, author: &str, timestamp: i32, path: String, value: &Value) -> Self {
+        let (kind, text, number) = match *value {
+            Value::Object(_) => ("object", String::new(), None),
+            Value::Array(_) => ("array", String::new(), None),
+            Value::String(ref string) => ("string", string.clone(), None),
+            Value::Number(ref number) => ("number", number.to_string(), number.as_f64()),
+            Value::Bool(boolean) => ("boolean", boolean.to_string(), None),
+            Value::Null => ("null", String::from("null"), None)
+        };
+
+        FieldEntity {
+            id: sha512(format!("{}{}{}", block, data_hash, path).as_bytes()),
+            data_hash: String::from(data_hash),
+            block: String::from(block),
+            path: path,
+            kind: String::from(kind),
+            value: text,
+            number: number,
+            author: String::from(author),
+            timestamp: timestamp
+        }
+    }
+}
+
+pub struct FieldRepository<'pool> {
+    connection: &'pool SqliteConnection
+}
+
+impl<'pool> FieldRepository<'pool> {
+
+    /// Instantiate a new `FieldRepository` whose lifetime is bound to its pooled connection.
+    pub fn new(connection: &SqliteConnection) -> FieldRepository {
+        FieldRepository {
+            connection: connection
+        }
+    }
+
+    /// Select the checksums of the documents matching the `query`, ordered by the timestamp of the
+    /// blocks storing them.
+    ///
+    /// A document stored in several competing blocks is only matched through the field of the first
+    /// block storing it, so that the documents are deduplicated before being paginated.
+    pub fn search(&self, query: &DocumentQuery) -> LocksidianResult<Vec<String>> {
+        // Every indexed document has a `$` root field: the documents lacking the queried field are
+        // selected among them, by the database
+        let (path, missing) = match query.exists {
+            true => (query.path.clone(), None),
+            false => (String::from("$"), Some(format!(
+                "NOT EXISTS (SELECT 1 FROM `fields` AS `present` WHERE `present`.`data_hash` = `fields`.`data_hash` AND `present`.`path` = {})",
+                sql_string(query.path.as_ref())
+            )))
+        };
+
+        let mut statement = fields::table
+            .filter(fields::path.eq(path))
+            .filter(sql::<Bool>(FIRST_STORED))
+            .select(fields::data_hash)
+            .into_boxed();
+
+        if let Some(missing) = missing {
+            statement = statement.filter(sql::<Bool>(missing.as_ref()));
+        }
+
+        for &(operator, ref operand) in query.conditions.iter() {
+            statement = match operand.parse::<f64>() {
+                Ok(number) => match operator {
+                    FieldOperator::Eq => statement.filter(fields::number.eq(number)),
+                    FieldOperator::Gt => statement.filter(fields::number.gt(number)),
+                    FieldOperator::Gte => statement.filter(fields::number.ge(number)),
+                    FieldOperator::Lt => statement.filter(fields::number.lt(number)),
+                    FieldOperator::Lte => statement.filter(fields::number.le(number))
+                },
+                Err(_) => {
+                    let statement = statement.filter(fields::kind.eq_any(vec!["string", "boolean", "null"]));
+
+                    match operator {
+                        FieldOperator::Eq => statement.filter(fields::value.eq(operand.clone())),
+                        FieldOperator::Gt => statement.filter(fields::value.gt(operand.clone())),
+                        FieldOperator::Gte => statement.filter(fields::value.ge(operand.clone())),
+                        FieldOperator::Lt => statement.filter(fields::value.lt(operand.clone())),
+                        FieldOperator::Lte => statement.filter(fields::value.le(operand.clone()))
+                    }
+                }
+            };
+        }
+
+        if let Some(ref author) = query.author {
+            statement = statement.filter(fields::author.eq(author.clone()));
+        }
+
+        if let Some(since) = query.since {
+            statement = statement.filter(fields::timestamp.ge(since));
+        }
+
+        if let Some(until) = query.until {
+            statement = statement.filter(fields::timestamp.le(until));
+        }
+
+        match statement
+            .order((fields::timestamp.asc(), fields::data_hash.asc()))
+            .limit(query.limit)
+            .offset(query.offset)
+            .load(self.connection) {
+            Ok(data_hashes) => Ok(data_hashes),
+            Err(err) => Err(LocksidianError::from_err(err))
+        }
+    }
+
+    /// Remove the fields of the documents of the given blocks and return the number of deleted rows.
+    ///
+    /// The number of block hashes should stay below the SQLite host parameters limit.
+    pub fn delete_by_blocks(&self, blocks: &[String]) -> LocksidianResult<usize> {
+        match ::diesel::delete(fields::table.filter(fields::block.eq_any(blocks.to_vec()))).execute(self.connection) {
+            Ok(deleted_rows) => Ok(deleted_rows),
+            Err(err) => Err(LocksidianError::from_err(err))
+        }
+    }
+}
+
+/// Quote the given `value` as an SQL string literal.
+fn sql_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+crud_repository!(fields, FieldEntity, String, id, FieldRepository<'pool>);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use blockchain::document::document_fields;
+
+    fn index(repository: &FieldRepository, block: &str, timestamp: i32, document: &str) {
+        let data_hash = sha512(document.as_bytes());
+
+        for (path, value) in document_fields(document) {
+            repository.save(&FieldEntity::new(data_hash.as_ref(), block, "alice", timestamp, path, &value)).unwrap();
+        }
+    }
+
+    #[test]
+    fn search_should_deduplicate_the_documents_before_paginating() {
+        let connection = get_connection(String::from(":memory:")).unwrap();
+        setup_database(&connection).unwrap();
+        let repository = FieldRepository::new(&connection);
+
+        // The first document is stored in two competing blocks
+        index(&repository, "a", 1, r#"{"id":1,"status":"open"}"#);
+        index(&repository, "b", 2, r#"{"id":1,"status":"open"}"#);
+        index(&repository, "c", 3, r#"{"id":2,"status":true}"#);
+        index(&repository, "d", 4, r#"{"id":3}"#);
+
+        let page = DocumentQuery::new("$.id").unwrap().with_page(Some("2"), None).unwrap();
+        let data_hashes = repository.search(&page).unwrap();
+        assert_eq!(data_hashes, vec![sha512(br#"{"id":1,"status":"open"}"#), sha512(br#"{"id":2,"status":true}"#)]);
+
+        let missing = DocumentQuery::new("$.status").unwrap().with_exists("false").unwrap();
+        assert_eq!(repository.search(&missing).unwrap(), vec![sha512(br#"{"id":3}"#)]);
+
+        // A text operand never matches a number, an object or an array
+        let text = DocumentQuery::new("$").unwrap().with_condition(FieldOperator::Gte, String::from("")).unwrap();
+        assert!(repository.search(&text).unwrap().is_empty());
+
+        let boolean = DocumentQuery::new("$.status").unwrap().with_condition(FieldOperator::Eq, String::from("true")).unwrap();
+        assert_eq!(repository.search(&boolean).unwrap(), vec![sha512(br#"{"id":2,"status":true}"#)]);
+    }
+}
@@ -0,0 +1,27 @@
+//! Documents stored in the blockchain.
+//!
+//! A block either stores a single document, or a batch of documents committed through the Merkle
+//! root of their checksums. The documents of the batch blocks are indexed by checksum, so that the
+//! block storing a given document can be found along with its position in the batch.
+//!
+//! A document can also be encrypted for a set of recipient identities, in which case the block
+//! stores its encrypted envelope.
+//!
+//! The fields of the stored JSON documents are indexed by path, so that the documents can be
+//! queried by the value of their fields.
+
+mod canonical;
+mod encryption;
+mod document_dto;
+mod document_repository;
+mod field_repository;
+mod query;
+mod schema;
+
+pub use self::canonical::Canonicalization;
+pub use self::encryption::{EncryptedDocumentDto, RecipientKeyDto};
+pub use self::document_dto::{DocumentProofDto, DocumentLocationDto, DocumentHistoryDto, DocumentVerificationDto, AnchorDto};
+pub use self::document_repository::{DocumentEntity, DocumentRepository};
+pub use self::field_repository::{FieldEntity, FieldRepository};
+pub use self::query::{DocumentQuery, FieldOperator, document_fields};
+pub use self::schema::{SchemaViolation, check_schema, schema_violations};
@@ -0,0 +1,321 @@
+//! Queries on the field values of the stored JSON documents.
+//!
+//! Every field of a stored JSON document is indexed under its path, written using a subset of the
+//! JSONPath syntax: `$` stands for the document itself, `.name` selects an object member and `[0]`
+//! an array item. The members whose name is not made of alphanumeric characters and underscores
+//! are selected using a quoted name, e.g. `$["first name"]`.
+//!
+//! For example, the `{"customer": {"id": 42}, "items": [{"sku": "A-1"}]}` document is indexed as:
+//!
+//! - `$` (object)
+//! - `$.customer` (object)
+//! - `$.customer.id` (number `42`)
+//! - `$.items` (array)
+//! - `$.items[0]` (object)
+//! - `$.items[0].sku` (string `"A-1"`)
+
+use error::*;
+
+use serde_json::Value;
+
+/// Maximum number of fields indexed for a single document.
+pub const MAX_INDEXED_FIELDS: usize = 1024;
+
+/// Default number of documents returned by a query.
+pub const DEFAULT_QUERY_LIMIT: i64 = 100;
+
+/// Maximum number of documents returned by a query.
+pub const MAX_QUERY_LIMIT: i64 = 1000;
+
+/// Comparison applied to the value of the queried field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldOperator {
+    Eq,
+    Gt,
+    Gte,
+    Lt,
+    Lte
+}
+
+impl FieldOperator {
+
+    /// Returns the operator named by the given query parameter, if any.
+    pub fn from_str(name: &str) -> Option<Self> {
+        match name {
+            "eq" => Some(FieldOperator::Eq),
+            "gt" => Some(FieldOperator::Gt),
+            "gte" => Some(FieldOperator::Gte),
+            "lt" => Some(FieldOperator::Lt),
+            "lte" => Some(FieldOperator::Lte),
+            _ => None
+        }
+    }
+}
+
+/// Query selecting the stored documents by the value of one of their fields.
+///
+/// Numbers are compared numerically when the operand is a number. Otherwise, the strings, booleans
+/// and nulls are compared as text (the `true`, `false` and `null` literals included), while the
+/// other values never match. The `exists` flag selects the
+/// documents having (or lacking) the field, whatever its value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentQuery {
+    pub path: String,
+    pub conditions: Vec<(FieldOperator, String)>,
+    pub exists: bool,
+    pub author: Option<String>,
+    pub since: Option<i32>,
+    pub until: Option<i32>,
+    pub limit: i64,
+    pub offset: i64
+}
+
+impl DocumentQuery {
+
+    /// Instantiate a new `DocumentQuery` on the field located at the given `path`, returning every
+    /// document having this field.
+    pub fn new(path: &str) -> LocksidianResult<Self> {
+        Ok(DocumentQuery {
+            path: parse_path(path)?,
+            conditions: Vec::new(),
+            exists: true,
+            author: None,
+            since: None,
+            until: None,
+            limit: DEFAULT_QUERY_LIMIT,
+            offset: 0
+        })
+    }
+
+    /// Add a condition on the value of the queried field.
+    pub fn with_condition(mut self, operator: FieldOperator, operand: String) -> LocksidianResult<Self> {
+        if !self.exists {
+            return Err(LocksidianError::new(String::from("A missing field cannot be compared")));
+        }
+
+        self.conditions.push((operator, operand));
+        Ok(self)
+    }
+
+    /// Select the documents having the queried field, or the ones lacking it.
+    pub fn with_exists(mut self, exists: &str) -> LocksidianResult<Self> {
+        self.exists = match exists {
+            "true" => true,
+            "false" => false,
+            _ => return Err(LocksidianError::new(format!("Invalid exists value {}, expected true or false", exists)))
+        };
+
+        match self.exists || self.conditions.is_empty() {
+            true => Ok(self),
+            false => Err(LocksidianError::new(String::from("A missing field cannot be compared")))
+        }
+    }
+
+    /// Only select the documents stored by the given identity.
+    pub fn with_author(mut self, author: String) -> Self {
+        self.author = Some(author);
+        self
+    }
+
+    /// Only select the documents stored in the blocks created in the `[since, until]` interval.
+    pub fn with_period(mut self, since: Option<&str>, until: Option<&str>) -> LocksidianResult<Self> {
+        self.since = match since {
+            Some(since) => Some(parse_integer("since", since)? as i32),
+            None => None
+        };
+        self.until = match until {
+            Some(until) => Some(parse_integer("until", until)? as i32),
+            None => None
+        };
+
+        Ok(self)
+    }
+
+    /// Paginate the selected documents.
+    pub fn with_page(mut self, limit: Option<&str>, offset: Option<&str>) -> LocksidianResult<Self> {
+        self.limit = match limit {
+            Some(limit) => match parse_integer("limit", limit)? {
+                0 => return Err(LocksidianError::new(String::from("The limit should be greater than 0"))),
+                limit if limit > MAX_QUERY_LIMIT => MAX_QUERY_LIMIT,
+                limit => limit
+            },
+            None => DEFAULT_QUERY_LIMIT
+        };
+        self.offset = match offset {
+            Some(offset) => parse_integer("offset", offset)?,
+            None => 0
+        };
+
+        Ok(self)
+    }
+}
+
+/// Parse a positive integer query parameter.
+fn parse_integer(name: &str, value: &str) -> LocksidianResult<i64> {
+    match value.parse::<i64>() {
+        Ok(integer) if integer >= 0 && integer <= ::std::i32::MAX as i64 => Ok(integer),
+        _ => Err(LocksidianError::new(format!("Invalid {} value {}, expected a positive integer", name, value)))
+    }
+}
+
+/// Parse the path of a field and return it in the form it is indexed under: `customer.id`,
+/// `$.customer.id` and `$["customer"]["id"]` are all indexed as `$.customer.id`.
+pub fn parse_path(path: &str) -> LocksidianResult<String> {
+    let invalid = |reason: &str| Err(LocksidianError::new(format!("Invalid field path {}: {}", path, reason)));
+
+    let mut remaining = match path.starts_with('$') {
+        true => &path[1..],
+        false if path.is_empty() => return invalid("the path cannot be empty"),
+        false => path
+    };
+
+    // The leading dot can be omitted when the path does not start with `$`
+    let mut normalized = String::from("$");
+    let mut separated = path != remaining;
+
+    while !remaining.is_empty() {
+        if remaining.starts_with('[') {
+            let (segment, rest) = match parse_bracket(&remaining[1..]) {
+                Some(parsed) => parsed,
+                None => return invalid("expected an array index or a quoted member name between brackets")
+            };
+
+            normalized.push_str(segment.as_ref());
+            remaining = rest;
+        } else {
+            if remaining.starts_with('.') {
+                remaining = &remaining[1..];
+            } else if separated {
+                return invalid("expected . or [");
+            }
+
+            let end = remaining.find(|c: char| c == '.' || c == '[').unwrap_or(remaining.len());
+            let name = &remaining[..end];
+
+            if name.is_empty() {
+                return invalid("empty member name");
+            }
+
+            normalized.push_str(member_path(name).as_ref());
+            remaining = &remaining[end..];
+        }
+
+        separated = true;
+    }
+
+    Ok(normalized)
+}
+
+/// Parse the content of a bracket, either an array index or a quoted member name, and return the
+/// normalized path segment along with the rest of the path.
+fn parse_bracket(path: &str) -> Option<(String, &str)> {
+    if path.starts_with('"') {
+        let mut escaped = false;
+
+        for (index, c) in path.char_indices().skip(1) {
+            match c {
+                '\\' if !escaped => escaped = true,
+                '"' if !escaped => return match (::serde_json::from_str::<String>(&path[..index + 1]), path[index + 1..].starts_with(']')) {
+                    (Ok(name), true) => Some((member_path(name.as_ref()), &path[index + 2..])),
+                    _ => None
+                },
+                _ => escaped = false
+            }
+        }
+
+        None
+    } else {
+        match path.find(']') {
+            Some(end) => match path[..end].parse::<usize>() {
+                Ok(index) => Some((format!("[{}]", index), &path[end + 1..])),
+                Err(_) => None
+            },
+            None => None
+        }
+    }
+}
+
+/// Returns the path segment selecting the object member of the given `name`.
+fn member_path(name: &str) -> String {
+    match !name.is_empty() && name.chars().all(|c| c == '_' || c.is_alphanumeric()) {
+        true => format!(".{}", name),
+        false => format!("[{}]", Value::String(String::from(name)))
+    }
+}
+
+/// Returns the indexed fields of a JSON `document`, along with their paths. Nothing is indexed if
+/// the document is not valid JSON.
+pub fn document_fields(document: &str) -> Vec<(String, Value)> {
+    let mut fields = Vec::new();
+
+    if let Ok(value) = ::serde_json::from_str::<Value>(document) {
+        collect_fields(String::from("$"), value, &mut fields);
+    }
+
+    fields
+}
+
+/// Push the field located at `path` into `fields`, followed by its members or items.
+fn collect_fields(path: String, value: Value, fields: &mut Vec<(String, Value)>) {
+    if fields.len() >= MAX_INDEXED_FIELDS {
+        return;
+    }
+
+    match value {
+        Value::Object(object) => {
+            fields.push((path.clone(), Value::Object(::serde_json::Map::new())));
+
+            for (name, member) in object {
+                collect_fields(format!("{}{}", path, member_path(name.as_ref())), member, fields);
+            }
+        },
+        Value::Array(items) => {
+            fields.push((path.clone(), Value::Array(Vec::new())));
+
+            for (index, item) in items.into_iter().enumerate() {
+                collect_fields(format!("{}[{}]", path, index), item, fields);
+            }
+        },
+        scalar => fields.push((path, scalar))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fields_should_be_indexed_by_path() {
+        let fields = document_fields(r#"{"customer":{"id":42},"items":[{"sku":"A-1"}],"first name":null}"#);
+        let paths: Vec<&str> = fields.iter().map(|&(ref path, _)| path.as_str()).collect();
+
+        assert!(paths.contains(&"$"));
+        assert!(paths.contains(&"$.customer.id"));
+        assert!(paths.contains(&"$.items[0].sku"));
+        assert!(paths.contains(&r#"$["first name"]"#));
+        assert!(fields.contains(&(String::from("$.customer.id"), json!(42))));
+        assert!(document_fields("not json").is_empty());
+    }
+
+    #[test]
+    fn paths_should_be_normalized() {
+        assert_eq!(parse_path("customer.id").unwrap(), "$.customer.id");
+        assert_eq!(parse_path("$.customer.id").unwrap(), "$.customer.id");
+        assert_eq!(parse_path(r#"$["customer"]["id"]"#).unwrap(), "$.customer.id");
+        assert_eq!(parse_path("$.items[0].sku").unwrap(), "$.items[0].sku");
+        assert_eq!(parse_path(r#"$["first name"]"#).unwrap(), r#"$["first name"]"#);
+        assert_eq!(parse_path("$").unwrap(), "$");
+
+        assert!(parse_path("").is_err());
+        assert!(parse_path("$customer").is_err());
+        assert!(parse_path("$.items[first]").is_err());
+        assert!(parse_path("$..id").is_err());
+    }
+
+    #[test]
+    fn missing_fields_should_not_be_compared() {
+        let query = DocumentQuery::new("$.customer.id").unwrap();
+        assert!(query.clone().with_exists("false").unwrap().with_condition(FieldOperator::Eq, String::from("42")).is_err());
+        assert!(query.with_condition(FieldOperator::Eq, String::from("42")).unwrap().with_exists("false").is_err());
+    }
+}
@@ -0,0 +1,357 @@
+//! JSON Schema validation of the documents.
+//!
+//! A network may restrict the documents it accepts to a set of named JSON Schemas: a document is
+//! accepted if it matches at least one of them. The following subset of the JSON Schema keywords is
+//! supported: `type`, `enum`, `const`, `properties`, `required`, `additionalProperties`,
+//! `minProperties`, `maxProperties`, `items`, `minItems`, `maxItems`, `uniqueItems`, `minLength`,
+//! `maxLength`, `minimum`, `maximum`, `exclusiveMinimum`, `exclusiveMaximum`, `multipleOf`,
+//! `allOf`, `anyOf`, `oneOf` and `not`. The annotation keywords (`$schema`, `$id`, `$comment`,
+//! `title`, `description`, `default` and `examples`) are ignored, and the schemas using any other
+//! keyword (including a misspelled one) are rejected when the network configuration is loaded,
+//! rather than partially enforced.
+
+use error::*;
+
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+/// Keywords enforced by the validation.
+const SUPPORTED_KEYWORDS: &'static [&'static str] = &[
+    "type", "enum", "const", "properties", "required", "additionalProperties", "minProperties",
+    "maxProperties", "items", "minItems", "maxItems", "uniqueItems", "minLength", "maxLength",
+    "minimum", "maximum", "exclusiveMinimum", "exclusiveMaximum", "multipleOf", "allOf", "anyOf",
+    "oneOf", "not"
+];
+
+/// Annotation keywords, which do not constrain the documents and are ignored.
+const ANNOTATION_KEYWORDS: &'static [&'static str] = &[
+    "$schema", "$id", "id", "$comment", "title", "description", "default", "examples"
+];
+
+/// Violation of a JSON Schema by a document.
+#[derive(
+    Debug, Clone, PartialEq,
+    Serialize, Deserialize
+)]
+pub struct SchemaViolation {
+
+    /// Name of the violated schema.
+    pub schema: String,
+
+    /// JSON Pointer of the invalid value in the document.
+    pub path: String,
+
+    pub message: String
+}
+
+/// Returns an error if the `schema` uses a keyword that is neither supported nor an annotation.
+pub fn check_schema(name: &str, schema: &Value) -> LocksidianResult<()> {
+    match *schema {
+        Value::Bool(_) => Ok(()),
+        Value::Object(ref keywords) => {
+            for (keyword, value) in keywords.iter() {
+                if !SUPPORTED_KEYWORDS.contains(&keyword.as_str()) && !ANNOTATION_KEYWORDS.contains(&keyword.as_str()) {
+                    return Err(LocksidianError::new(format!("Schema {} uses the unsupported keyword {}", name, keyword)));
+                }
+
+                match (keyword.as_str(), value) {
+                    ("properties", &Value::Object(ref properties)) => for property in properties.values() {
+                        check_schema(name, property)?;
+                    },
+                    ("allOf", &Value::Array(ref schemas)) | ("anyOf", &Value::Array(ref schemas)) | ("oneOf", &Value::Array(ref schemas)) |
+                    ("items", &Value::Array(ref schemas)) => for schema in schemas.iter() {
+                        check_schema(name, schema)?;
+                    },
+                    ("additionalProperties", _) | ("items", _) | ("not", _) => check_schema(name, value)?,
+                    _ => ()
+                }
+            }
+
+            Ok(())
+        },
+        _ => Err(LocksidianError::new(format!("Schema {} should be a JSON object or a boolean", name)))
+    }
+}
+
+/// Validate the `document` against the `schemas` of the network. The document is valid if there is
+/// no schema, or if it matches at least one of them: otherwise, the violations of every schema are
+/// returned.
+pub fn schema_violations(document: &str, schemas: &BTreeMap<String, Value>) -> Vec<SchemaViolation> {
+    if schemas.is_empty() {
+        return Vec::new();
+    }
+
+    let value = match ::serde_json::from_str::<Value>(document) {
+        Ok(value) => value,
+        Err(err) => return vec![SchemaViolation {
+            schema: String::new(),
+            path: String::new(),
+            message: format!("The document is not valid JSON: {}", err)
+        }]
+    };
+
+    let mut violations = Vec::new();
+    for (name, schema) in schemas.iter() {
+        let mut errors = Vec::new();
+        validate(&value, schema, "", &mut errors);
+
+        if errors.is_empty() {
+            return Vec::new();
+        }
+
+        violations.extend(errors.into_iter().map(|(path, message)| SchemaViolation {
+            schema: name.clone(),
+            path: path,
+            message: message
+        }));
+    }
+
+    violations
+}
+
+/// Validate the `value` located at the JSON Pointer `path` against the `schema`, pushing the
+/// `(path, message)` couple of each violation into `errors`.
+fn validate(value: &Value, schema: &Value, path: &str, errors: &mut Vec<(String, String)>) {
+    let keywords = match *schema {
+        Value::Bool(true) => return,
+        Value::Object(ref keywords) => keywords,
+        _ => return errors.push((String::from(path), String::from("No value is allowed")))
+    };
+
+    for (keyword, constraint) in keywords.iter() {
+        match keyword.as_str() {
+            "type" => if !matches_type(value, constraint) {
+                errors.push((String::from(path), format!("Expected a value of type {}", constraint)));
+            },
+            "enum" => if !constraint.as_array().map(|values| values.contains(value)).unwrap_or(true) {
+                errors.push((String::from(path), format!("Expected one of {}", constraint)));
+            },
+            "const" => if value != constraint {
+                errors.push((String::from(path), format!("Expected {}", constraint)));
+            },
+            "allOf" | "anyOf" | "oneOf" => validate_combination(keyword.as_str(), value, constraint, path, errors),
+            "not" => {
+                let mut nested = Vec::new();
+                validate(value, constraint, path, &mut nested);
+
+                if nested.is_empty() {
+                    errors.push((String::from(path), String::from("Value should not match the schema")));
+                }
+            },
+            _ => ()
+        }
+    }
+
+    match *value {
+        Value::Object(ref object) => validate_object(object, keywords, path, errors),
+        Value::Array(ref items) => validate_array(items, keywords, path, errors),
+        Value::String(ref string) => validate_bounds(string.chars().count() as f64, keywords, "minLength", "maxLength", "length", path, errors),
+        Value::Number(ref number) => validate_number(number.as_f64().unwrap_or(0.0), keywords, path, errors),
+        _ => ()
+    }
+}
+
+/// Validate the `allOf`, `anyOf` and `oneOf` keywords.
+fn validate_combination(keyword: &str, value: &Value, schemas: &Value, path: &str, errors: &mut Vec<(String, String)>) {
+    let schemas = match schemas.as_array() {
+        Some(schemas) => schemas,
+        None => return
+    };
+
+    let mut nested = Vec::new();
+    let mut matching = 0;
+    for schema in schemas.iter() {
+        let mut schema_errors = Vec::new();
+        validate(value, schema, path, &mut schema_errors);
+
+        if schema_errors.is_empty() {
+            matching += 1;
+        }
+        nested.extend(schema_errors);
+    }
+
+    match keyword {
+        "allOf" => errors.extend(nested),
+        "anyOf" if matching == 0 => errors.push((String::from(path), String::from("Value should match at least one of the anyOf schemas"))),
+        "oneOf" if matching != 1 => errors.push((String::from(path), format!("Value should match exactly one of the oneOf schemas, {} matched", matching))),
+        _ => ()
+    }
+}
+
+/// Validate the keywords applying to a JSON object.
+fn validate_object(object: &::serde_json::Map<String, Value>, keywords: &::serde_json::Map<String, Value>, path: &str, errors: &mut Vec<(String, String)>) {
+    if let Some(required) = keywords.get("required").and_then(|required| required.as_array()) {
+        for property in required.iter().filter_map(|property| property.as_str()) {
+            if !object.contains_key(property) {
+                errors.push((String::from(path), format!("Missing required property {}", property)));
+            }
+        }
+    }
+
+    let properties = keywords.get("properties").and_then(|properties| properties.as_object());
+    for (property, value) in object.iter() {
+        let property_path = format!("{}/{}", path, property.replace("~", "~0").replace("/", "~1"));
+
+        match properties.and_then(|properties| properties.get(property)) {
+            Some(schema) => validate(value, schema, property_path.as_ref(), errors),
+            None => match keywords.get("additionalProperties") {
+                Some(schema) => validate(value, schema, property_path.as_ref(), errors),
+                None => ()
+            }
+        }
+    }
+
+    validate_bounds(object.len() as f64, keywords, "minProperties", "maxProperties", "number of properties", path, errors);
+}
+
+/// Validate the keywords applying to a JSON array.
+fn validate_array(items: &Vec<Value>, keywords: &::serde_json::Map<String, Value>, path: &str, errors: &mut Vec<(String, String)>) {
+    match keywords.get("items") {
+        Some(&Value::Array(ref schemas)) => for (index, (item, schema)) in items.iter().zip(schemas.iter()).enumerate() {
+            validate(item, schema, format!("{}/{}", path, index).as_ref(), errors);
+        },
+        Some(schema) => for (index, item) in items.iter().enumerate() {
+            validate(item, schema, format!("{}/{}", path, index).as_ref(), errors);
+        },
+        None => ()
+    }
+
+    if keywords.get("uniqueItems").and_then(|unique| unique.as_bool()).unwrap_or(false) {
+        for (index, item) in items.iter().enumerate() {
+            if items[..index].contains(item) {
+                errors.push((format!("{}/{}", path, index), String::from("Array items should be unique")));
+            }
+        }
+    }
+
+    validate_bounds(items.len() as f64, keywords, "minItems", "maxItems", "number of items", path, errors);
+}
+
+/// Validate the keywords applying to a JSON number.
+fn validate_number(number: f64, keywords: &::serde_json::Map<String, Value>, path: &str, errors: &mut Vec<(String, String)>) {
+    validate_bounds(number, keywords, "minimum", "maximum", "value", path, errors);
+
+    // Draft 4 uses boolean exclusive bounds, modifying the minimum and maximum keywords
+    let exclusive_bound = |keyword: &str, bound: &str| match keywords.get(keyword) {
+        Some(&Value::Bool(true)) => keywords.get(bound).and_then(|bound| bound.as_f64()),
+        Some(bound) => bound.as_f64(),
+        None => None
+    };
+
+    if let Some(minimum) = exclusive_bound("exclusiveMinimum", "minimum") {
+        if number <= minimum {
+            errors.push((String::from(path), format!("Expected a value greater than {}", minimum)));
+        }
+    }
+
+    if let Some(maximum) = exclusive_bound("exclusiveMaximum", "maximum") {
+        if number >= maximum {
+            errors.push((String::from(path), format!("Expected a value lower than {}", maximum)));
+        }
+    }
+
+    if let Some(divisor) = keywords.get("multipleOf").and_then(|divisor| divisor.as_f64()) {
+        if divisor > 0.0 && (number / divisor).fract() != 0.0 {
+            errors.push((String::from(path), format!("Expected a multiple of {}", divisor)));
+        }
+    }
+}
+
+/// Validate the inclusive `minimum` and `maximum` bounds of a `measure` of the value.
+fn validate_bounds(measure: f64, keywords: &::serde_json::Map<String, Value>, minimum: &str, maximum: &str, name: &str, path: &str, errors: &mut Vec<(String, String)>) {
+    if let Some(minimum) = keywords.get(minimum).and_then(|minimum| minimum.as_f64()) {
+        if measure < minimum {
+            errors.push((String::from(path), format!("Expected a {} of at least {}", name, minimum)));
+        }
+    }
+
+    if let Some(maximum) = keywords.get(maximum).and_then(|maximum| maximum.as_f64()) {
+        if measure > maximum {
+            errors.push((String::from(path), format!("Expected a {} of at most {}", name, maximum)));
+        }
+    }
+}
+
+/// Returns `true` if the `value` matches the `type` keyword, being either a type name or an array
+/// of type names.
+fn matches_type(value: &Value, types: &Value) -> bool {
+    match *types {
+        Value::String(ref name) => is_of_type(value, name.as_str()),
+        Value::Array(ref names) => names.iter().any(|name| name.as_str().map(|name| is_of_type(value, name)).unwrap_or(false)),
+        _ => true
+    }
+}
+
+/// Returns `true` if the `value` is of the JSON Schema type `name`.
+fn is_of_type(value: &Value, name: &str) -> bool {
+    match (name, value) {
+        ("null", &Value::Null) => true,
+        ("boolean", &Value::Bool(_)) => true,
+        ("object", &Value::Object(_)) => true,
+        ("array", &Value::Array(_)) => true,
+        ("string", &Value::String(_)) => true,
+        ("number", &Value::Number(_)) => true,
+        ("integer", &Value::Number(ref number)) => number.is_i64() || number.is_u64() || number.as_f64().map(|number| number.fract() == 0.0).unwrap_or(false),
+        _ => false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn schemas() -> BTreeMap<String, Value> {
+        let mut schemas = BTreeMap::new();
+        schemas.insert(String::from("invoice"), ::serde_json::from_str(r#"{
+            "type": "object",
+            "required": ["number", "amount"],
+            "properties": {
+                "number": {"type": "string", "minLength": 1},
+                "amount": {"type": "number", "minimum": 0}
+            },
+            "additionalProperties": false
+        }"#).unwrap());
+        schemas.insert(String::from("report"), ::serde_json::from_str(r#"{
+            "type": "object",
+            "required": ["tests"],
+            "properties": {"tests": {"type": "array", "items": {"type": "integer"}, "minItems": 1}}
+        }"#).unwrap());
+
+        schemas
+    }
+
+    #[test]
+    fn document_matching_one_of_the_schemas_should_be_accepted() {
+        assert!(schema_violations(r#"{"number": "F-1", "amount": 12.5}"#, &schemas()).is_empty());
+        assert!(schema_violations(r#"{"tests": [1, 2, 3]}"#, &schemas()).is_empty());
+        assert!(schema_violations("not even json", &BTreeMap::new()).is_empty());
+    }
+
+    #[test]
+    fn document_matching_no_schema_should_list_the_violations() {
+        let violations = schema_violations(r#"{"number": "F-1", "amount": -1, "extra": true}"#, &schemas());
+
+        assert!(violations.contains(&SchemaViolation {
+            schema: String::from("invoice"),
+            path: String::from("/amount"),
+            message: String::from("Expected a value of at least 0")
+        }));
+        assert!(violations.iter().any(|violation| violation.schema == "invoice" && violation.path == "/extra"));
+        assert!(violations.iter().any(|violation| violation.schema == "report" && violation.message == "Missing required property tests"));
+    }
+
+    #[test]
+    fn unsupported_keywords_should_be_rejected() {
+        assert!(check_schema("invoice", &schemas()["invoice"]).is_ok());
+        assert!(check_schema("reference", &::serde_json::from_str(r##"{"properties": {"a": {"$ref": "#/definitions/a"}}}"##).unwrap()).is_err());
+        assert!(check_schema("number", &::serde_json::from_str("42").unwrap()).is_err());
+    }
+
+    #[test]
+    fn only_supported_and_annotation_keywords_should_be_accepted() {
+        assert!(check_schema("annotated", &::serde_json::from_str(r#"{"title": "Invoice", "description": "An invoice", "type": "object"}"#).unwrap()).is_ok());
+        assert!(check_schema("format", &::serde_json::from_str(r#"{"type": "string", "format": "date"}"#).unwrap()).is_err());
+        assert!(check_schema("misspelled", &::serde_json::from_str(r#"{"properties": {"amount": {"minimun": 0}}}"#).unwrap()).is_err());
+    }
+}
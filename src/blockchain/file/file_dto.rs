@@ -0,0 +1,158 @@
+//! File record data transfer objects.
+
+use error::*;
+
+use sec::sha::sha512;
+
+use blockchain::block::{Block, BlockRepository};
+
+/// The Proof of Work penalty of a file increases by one bit each time its size doubles, starting
+/// from this size (in bytes).
+const PENALTY_UNIT: u64 = 1024;
+
+/// Record of a certified file, stored in the block in place of the file itself:
+///
+/// ```json
+/// {
+///     "file": {
+///         "hash": "{sha512 checksum of the file}",
+///         "size": {size in bytes},
+///         "media_type": "{media type}",
+///         "name": "{optional file name}"
+///     }
+/// }
+/// ```
+#[derive(
+    Debug, Clone,
+    Serialize, Deserialize
+)]
+#[serde(deny_unknown_fields)]
+pub struct FileDto {
+    pub hash: String,
+    pub size: u64,
+    pub media_type: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>
+}
+
+/// Document stored in the block for a certified file.
+#[derive(
+    Debug, Clone,
+    Serialize, Deserialize
+)]
+#[serde(deny_unknown_fields)]
+struct FileRecordDto {
+    file: FileDto
+}
+
+impl FileDto {
+
+    /// Instantiate the record of the given file `bytes`. The `media_type` parameters (e.g. the
+    /// charset) are dropped, and the `name` is stripped from its directories.
+    pub fn new(bytes: &[u8], media_type: &str, name: Option<&str>) -> LocksidianResult<Self> {
+        let media_type = media_type.split(';').next().unwrap_or("").trim().to_lowercase();
+        if !is_media_type(media_type.as_ref()) {
+            return Err(LocksidianError::new(format!("Invalid media type: {}", media_type)));
+        }
+
+        let name = name
+            .and_then(|name| name.rsplit(|c| c == '/' || c == '\\').next())
+            .map(|name| name.chars().filter(|c| !c.is_control() && *c != '"').collect::<String>())
+            .and_then(|name| match name.trim().is_empty() {
+                true => None,
+                false => Some(String::from(name.trim()))
+            });
+
+        Ok(FileDto {
+            hash: sha512(bytes),
+            size: bytes.len() as u64,
+            media_type: media_type,
+            name: name
+        })
+    }
+
+    /// Parse the record of a file stored in a block. Returns `None` if the document is not one.
+    pub fn parse(document: &str) -> Option<Self> {
+        match ::serde_json::from_str::<FileRecordDto>(document) {
+            Ok(record) => Some(record.file),
+            Err(_) => None
+        }
+    }
+
+    /// Find the record of the file certified by the document identified by the given `data_hash`.
+    pub fn find(data_hash: &str, repository: &BlockRepository) -> LocksidianResult<Option<Self>> {
+        match repository.locate_document(data_hash) {
+            Some((entity, position)) => {
+                let documents = Block::from_entity(entity)?.documents()?;
+
+                Ok(documents.get(position).and_then(|document| FileDto::parse(document.as_ref())))
+            },
+            None => Ok(None)
+        }
+    }
+
+    /// Serialize the record to be stored in the block.
+    pub fn to_json(&self) -> LocksidianResult<String> {
+        let record = FileRecordDto {
+            file: self.clone()
+        };
+
+        match ::serde_json::to_string(&record) {
+            Ok(json) => Ok(json),
+            Err(err) => Err(LocksidianError::from_err(err))
+        }
+    }
+
+    /// Proof of Work penalty of the file, in bits, so that the expected mining work of a block is
+    /// proportional to the size of the file it certifies.
+    pub fn penalty(&self) -> usize {
+        (64 - (self.size / PENALTY_UNIT).leading_zeros()) as usize
+    }
+}
+
+/// Returns `true` if `media_type` is a `type/subtype` couple of valid tokens.
+fn is_media_type(media_type: &str) -> bool {
+    let parts: Vec<&str> = media_type.split('/').collect();
+
+    parts.len() == 2 && parts.iter().all(|part| {
+        !part.is_empty() && part.chars().all(|c| c.is_digit(36) || "!#$&^_.+-".contains(c))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn file_record_should_be_parsed_back() {
+        let file = FileDto::new(b"%PDF-1.4", "application/PDF; charset=binary", Some("C:\\contracts\\lease.pdf")).unwrap();
+        assert_eq!(file.media_type, "application/pdf");
+        assert_eq!(file.name, Some(String::from("lease.pdf")));
+        assert_eq!(file.size, 8);
+
+        let parsed = FileDto::parse(file.to_json().unwrap().as_ref()).unwrap();
+        assert_eq!(parsed.hash, sha512(b"%PDF-1.4"));
+
+        assert!(FileDto::parse(r#"{"Hello": "World!"}"#).is_none());
+        assert!(FileDto::new(b"", "application/pdf\r\nX-Injected: true", None).is_err());
+    }
+
+    #[test]
+    fn penalty_should_increase_each_time_the_size_doubles() {
+        let mut file = FileDto::new(b"", "application/octet-stream", None).unwrap();
+        assert_eq!(file.penalty(), 0);
+
+        file.size = 1023;
+        assert_eq!(file.penalty(), 0);
+
+        file.size = 1024;
+        assert_eq!(file.penalty(), 1);
+
+        file.size = 1024 * 1024;
+        assert_eq!(file.penalty(), 11);
+
+        file.size = 2 * 1024 * 1024;
+        assert_eq!(file.penalty(), 12);
+    }
+}
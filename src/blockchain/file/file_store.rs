@@ -0,0 +1,144 @@
+//! Content-addressed storage of the certified files.
+//!
+//! Each file is stored once, in a file named after its SHA512 checksum, inside of the `files`
+//! directory located next to the node's database.
+
+use error::*;
+
+use sec::sha::sha512;
+
+use std::fs;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// Maximum size of an uploaded file: 64 MiB.
+pub const MAX_FILE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Content-addressed file store.
+pub struct FileStore {
+    directory: PathBuf
+}
+
+impl FileStore {
+
+    /// Instantiate a store of the files located in the given `directory`.
+    pub fn new(directory: PathBuf) -> Self {
+        FileStore {
+            directory: directory
+        }
+    }
+
+    /// Instantiate the store of the node, located next to its database.
+    pub fn default() -> Self {
+        let database_path = ::persistence::database_path();
+
+        let directory = match Path::new(database_path.as_str()).parent() {
+            Some(parent) => parent.join("files"),
+            None => PathBuf::from("files")
+        };
+
+        FileStore::new(directory)
+    }
+
+    /// Store the file `bytes`, unless they already are, and return their checksum.
+    pub fn store(&self, bytes: &[u8]) -> LocksidianResult<String> {
+        let hash = sha512(bytes);
+        let path = self.path(hash.as_ref())?;
+
+        if path.exists() {
+            return Ok(hash);
+        }
+
+        // Write a temporary file first, so that a partially written file is never served
+        let partial = self.directory.join(format!("{}.part", hash));
+        let written = fs::create_dir_all(&self.directory)
+            .and_then(|_| File::create(&partial))
+            .and_then(|mut file| file.write_all(bytes))
+            .and_then(|_| fs::rename(&partial, &path));
+
+        match written {
+            Ok(_) => Ok(hash),
+            Err(err) => Err(LocksidianError::from_err(err))
+        }
+    }
+
+    /// Returns `true` if the file identified by the given `hash` is stored.
+    pub fn contains(&self, hash: &str) -> LocksidianResult<bool> {
+        Ok(self.path(hash)?.exists())
+    }
+
+    /// Remove the stored file identified by the given `hash`, if any.
+    pub fn remove(&self, hash: &str) -> LocksidianResult<()> {
+        let path = self.path(hash)?;
+
+        match path.exists() {
+            true => match fs::remove_file(path) {
+                Ok(_) => Ok(()),
+                Err(err) => Err(LocksidianError::from_err(err))
+            },
+            false => Ok(())
+        }
+    }
+
+    /// Open the stored file identified by the given `hash`, or return `None` if it is not stored.
+    pub fn open(&self, hash: &str) -> LocksidianResult<Option<File>> {
+        let path = self.path(hash)?;
+
+        match path.exists() {
+            true => match File::open(path) {
+                Ok(file) => Ok(Some(file)),
+                Err(err) => Err(LocksidianError::from_err(err))
+            },
+            false => Ok(None)
+        }
+    }
+
+    /// Path of the file identified by the given `hash`, which must be a SHA512 checksum.
+    fn path(&self, hash: &str) -> LocksidianResult<PathBuf> {
+        match hash.len() == 128 && hash.chars().all(|c| c.is_digit(16) && !c.is_uppercase()) {
+            true => Ok(self.directory.join(hash)),
+            false => Err(LocksidianError::new(format!("{} is not a lowercase hexadecimal SHA512 checksum", hash)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::io::prelude::*;
+
+    use sec::hex::ToHex;
+    use openssl::rand::rand_bytes;
+
+    /// Store located in a new temporary directory, removed by the test.
+    fn temporary_store() -> (FileStore, PathBuf) {
+        let mut suffix = [0; 8];
+        rand_bytes(&mut suffix).unwrap();
+
+        let directory = env::temp_dir().join(format!("locksidian-files-{}", suffix.to_hex()));
+        (FileStore::new(directory.clone()), directory)
+    }
+
+    #[test]
+    fn stored_file_should_be_addressed_by_its_checksum() {
+        let (store, directory) = temporary_store();
+        let hash = store.store(b"%PDF-1.4").unwrap();
+        assert_eq!(hash, sha512(b"%PDF-1.4"));
+        assert_eq!(store.store(b"%PDF-1.4").unwrap(), hash);
+
+        let mut content = Vec::new();
+        store.open(hash.as_ref()).unwrap().unwrap().read_to_end(&mut content).unwrap();
+        assert_eq!(content.as_slice(), b"%PDF-1.4");
+
+        assert!(store.open(sha512(b"unknown").as_ref()).unwrap().is_none());
+        assert!(store.open("../locksidian.db").is_err());
+
+        assert!(store.contains(hash.as_ref()).unwrap());
+        store.remove(hash.as_ref()).unwrap();
+        assert!(!store.contains(hash.as_ref()).unwrap());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
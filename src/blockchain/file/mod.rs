@@ -0,0 +1,12 @@
+//! Binary files certified in the blockchain.
+//!
+//! The bytes of an uploaded file are kept in the content-addressed store of the node, under their
+//! SHA512 checksum, while the block stores a JSON record of the file (checksum, size, media type
+//! and name). The record is certified like any other document, so that the file itself can later
+//! be verified against the checksum it holds.
+
+mod file_dto;
+mod file_store;
+
+pub use self::file_dto::FileDto;
+pub use self::file_store::{FileStore, MAX_FILE_SIZE};
@@ -0,0 +1,40 @@
+//! Author Key Repository.
+//!
+//! Keep the public keys of the block authors that are neither local identities nor peers, once
+//! they have been verified against a replicated or synced block. They are needed later on to serve
+//! the blocks, verify the chain and issue receipts, even after the author's node left the network.
+
+use persistence::prelude::*;
+
+table! {
+	author_keys(identity) {
+		identity -> VarChar,
+		key -> VarChar,
+	}
+}
+
+#[derive(
+	Debug, Clone,
+	Queryable, Insertable, AsChangeset
+)]
+#[table_name = "author_keys"]
+pub struct AuthorKeyEntity {
+	pub identity: String,
+	pub key: String
+}
+
+pub struct AuthorKeyRepository<'pool> {
+	connection: &'pool SqliteConnection
+}
+
+impl<'pool> AuthorKeyRepository<'pool> {
+	
+	/// Instantiate a new `AuthorKeyRepository` whose lifetime is bound to its pooled connection.
+	pub fn new(connection: &SqliteConnection) -> AuthorKeyRepository {
+		AuthorKeyRepository {
+			connection: connection
+		}
+	}
+}
+
+crud_repository!(author_keys, AuthorKeyEntity, String, identity, AuthorKeyRepository<'pool>);
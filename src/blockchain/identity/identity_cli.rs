@@ -9,6 +9,7 @@ use sec::ripemd::ripemd160;
 
 use persistence::prelude::*;
 use blockchain::identity::*;
+use blockchain::peer::PeerRepository;
 
 /// Return the currently active `Identity`
 pub fn get_active_identity(connection: &SqliteConnection) -> LocksidianResult<Identity> {
@@ -109,4 +110,62 @@ pub fn compute_key_hash(key: &Rsa) -> LocksidianResult<String> {
 	let hash = ripemd160(sha_hash.as_bytes());
 	
 	Ok(hash)
+}
+
+/// Resolve the public key of the `Identity` identified by the provided `hash`.
+///
+/// The key is taken from the optional hexadecimal PEM-encoded `key` when it is provided, otherwise
+/// it is searched in the local peer and identity registries. In any case, the resolved key hash
+/// must match the requested `hash`, or an error is thrown.
+pub fn get_identity_key(hash: &str, key: Option<&String>, connection: &SqliteConnection) -> LocksidianResult<Rsa> {
+	let key_hex = match key {
+		Some(key) => key.clone(),
+		None => find_registered_key(hash, &connection)?
+	};
+	
+	match key_hex.from_hex() {
+		Ok(pem) => {
+			let rsa = Rsa::from_public_key(pem.as_slice())?;
+			
+			match compute_key_hash(&rsa)? == hash {
+				true => Ok(rsa),
+				false => Err(LocksidianError::new(format!("The provided public key does not belong to identity {}", hash)))
+			}
+		},
+		Err(err) => Err(LocksidianError::from_err(err))
+	}
+}
+
+/// Search the hexadecimal PEM-encoded public key of the specified identity `hash` in the peer,
+/// identity and author key registries.
+fn find_registered_key(hash: &str, connection: &SqliteConnection) -> LocksidianResult<String> {
+	let peer_repository = PeerRepository::new(&connection);
+	let identity_repository = IdentityRepository::new(&connection);
+	let author_key_repository = AuthorKeyRepository::new(&connection);
+	
+	match peer_repository.get(&String::from(hash)) {
+		Some(entity) => Ok(entity.key),
+		None => match identity_repository.get(&String::from(hash)) {
+			Some(entity) => entity.to_identity()?.public_key_to_hex(),
+			None => match author_key_repository.get(&String::from(hash)) {
+				Some(entity) => Ok(entity.key),
+				None => Err(LocksidianError::new(format!("Unknown identity: {}", hash)))
+			}
+		}
+	}
+}
+
+/// Persist the verified public `key` of a block author, unless it is already known, so that the
+/// blocks it signed can still be verified once its node is no longer a peer.
+pub fn remember_author_key(key: &Rsa, connection: &SqliteConnection) -> LocksidianResult<()> {
+	let hash = compute_key_hash(&key)?;
+	let repository = AuthorKeyRepository::new(&connection);
+	
+	match repository.get(&hash) {
+		Some(_) => Ok(()),
+		None => repository.save(&AuthorKeyEntity {
+			identity: hash,
+			key: key.export_public_key()?.to_hex()
+		}).map(|_| ())
+	}
 }
\ No newline at end of file
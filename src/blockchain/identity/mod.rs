@@ -6,8 +6,10 @@
 mod identity_domain;
 mod identity_dto;
 mod identity_repository;
+mod author_key_repository;
 pub mod identity_cli;
 
 pub use self::identity_domain::Identity;
 pub use self::identity_dto::IdentityDto;
-pub use self::identity_repository::{IdentityEntity, IdentityRepository};
\ No newline at end of file
+pub use self::identity_repository::{IdentityEntity, IdentityRepository};
+pub use self::author_key_repository::{AuthorKeyEntity, AuthorKeyRepository};
\ No newline at end of file
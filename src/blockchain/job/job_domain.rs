@@ -0,0 +1,258 @@
+//! Job Domain module.
+
+use error::*;
+use sec::hex::ToHex;
+use openssl::rand::rand_bytes;
+
+use blockchain::get_current_timestamp;
+use blockchain::job::JobEntity;
+
+/// Size, in bytes, of the random job identifiers.
+const JOB_ID_SIZE: usize = 16;
+
+/// Processing status of a submitted document.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobStatus {
+
+    /// The document is waiting for its block to be mined.
+    Queued,
+
+    /// The block of the document is being mined.
+    Mining,
+
+    /// The block of the document has been stored in the registry.
+    Stored,
+
+    /// The block of the document could not be created.
+    Failed
+}
+
+impl JobStatus {
+
+    /// Persisted representation of the status.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            JobStatus::Queued => "queued",
+            JobStatus::Mining => "mining",
+            JobStatus::Stored => "stored",
+            JobStatus::Failed => "failed"
+        }
+    }
+
+    /// Parse a persisted status.
+    pub fn from_str(status: &str) -> LocksidianResult<Self> {
+        match status {
+            "queued" => Ok(JobStatus::Queued),
+            "mining" => Ok(JobStatus::Mining),
+            "stored" => Ok(JobStatus::Stored),
+            "failed" => Ok(JobStatus::Failed),
+            _ => Err(LocksidianError::new(format!("Unknown job status: {}", status)))
+        }
+    }
+}
+
+pub struct Job {
+    id: String,
+    data: String,
+    status: JobStatus,
+    block: Option<String>,
+    error: Option<String>,
+
+    /// Checksum of the anchored document, the `data` then being its metadata.
+    anchor: Option<String>,
+
+    /// Checksum of the document superseded by the `data` of the job.
+    supersedes: Option<String>,
+
+    /// Cipher of the encrypted document envelope stored in the `data` of the job.
+    encryption: Option<String>,
+
+    created_at: u64,
+    updated_at: u64
+}
+
+impl Job {
+
+    /// Instantiate a new queued `Job` for the given document, identified by a random identifier.
+    pub fn new(data: String) -> LocksidianResult<Self> {
+        Job::queue(data, None, None, None)
+    }
+
+    /// Instantiate a new queued `Job` for a revision of the document identified by `supersedes`.
+    pub fn supersede_document(data: String, supersedes: String) -> LocksidianResult<Self> {
+        Job::queue(data, None, Some(supersedes), None)
+    }
+
+    /// Instantiate a new queued `Job` for the `envelope` of a document encrypted using `cipher`,
+    /// optionally superseding the document identified by `supersedes`.
+    pub fn encrypt_document(envelope: String, cipher: String, supersedes: Option<String>) -> LocksidianResult<Self> {
+        Job::queue(envelope, None, supersedes, Some(cipher))
+    }
+
+    /// Instantiate a new queued `Job` anchoring the document identified by its `data_hash`, along
+    /// with its optional `metadata`.
+    pub fn anchor_document(data_hash: String, metadata: String) -> LocksidianResult<Self> {
+        Job::queue(metadata, Some(data_hash), None, None)
+    }
+
+    /// Instantiate a new queued `Job`, identified by a random identifier.
+    fn queue(data: String, anchor: Option<String>, supersedes: Option<String>, encryption: Option<String>) -> LocksidianResult<Self> {
+        let mut id = [0; JOB_ID_SIZE];
+
+        match rand_bytes(&mut id) {
+            Ok(_) => Ok(Job {
+                id: id.to_hex(),
+                data: data,
+                status: JobStatus::Queued,
+                block: None,
+                error: None,
+                anchor: anchor,
+                supersedes: supersedes,
+                encryption: encryption,
+
+                created_at: get_current_timestamp(),
+                updated_at: get_current_timestamp()
+            }),
+            Err(err) => Err(LocksidianError::from_err(err))
+        }
+    }
+
+    /// Instantiate a new `Job` from the given `JobEntity`, consuming the entity instance.
+    pub fn from_entity(entity: JobEntity) -> LocksidianResult<Self> {
+        Ok(Job {
+            id: entity.id,
+            data: entity.data,
+            status: JobStatus::from_str(entity.status.as_ref())?,
+            block: match entity.block.is_empty() {
+                true => None,
+                false => Some(entity.block)
+            },
+            error: match entity.error.is_empty() {
+                true => None,
+                false => Some(entity.error)
+            },
+            anchor: match entity.anchor.is_empty() {
+                true => None,
+                false => Some(entity.anchor)
+            },
+            supersedes: match entity.supersedes.is_empty() {
+                true => None,
+                false => Some(entity.supersedes)
+            },
+            encryption: match entity.encryption.is_empty() {
+                true => None,
+                false => Some(entity.encryption)
+            },
+
+            created_at: entity.created_at as u64,
+            updated_at: entity.updated_at as u64
+        })
+    }
+
+    /// Flag the job as being mined.
+    pub fn start(&mut self) {
+        self.status = JobStatus::Mining;
+        self.updated_at = get_current_timestamp();
+    }
+
+    /// Flag the job as stored in the block identified by `hash`.
+    pub fn store(&mut self, hash: String) {
+        self.status = JobStatus::Stored;
+        self.block = Some(hash);
+        self.updated_at = get_current_timestamp();
+    }
+
+    /// Flag the job as failed because of the given `error`.
+    pub fn fail(&mut self, error: String) {
+        self.status = JobStatus::Failed;
+        self.error = Some(error);
+        self.updated_at = get_current_timestamp();
+    }
+
+    /// `id` getter.
+    pub fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    /// `data` getter.
+    pub fn data(&self) -> String {
+        self.data.clone()
+    }
+
+    /// `status` getter.
+    pub fn status(&self) -> JobStatus {
+        self.status
+    }
+
+    /// `block` getter.
+    pub fn block(&self) -> Option<String> {
+        self.block.clone()
+    }
+
+    /// `error` getter.
+    pub fn error(&self) -> Option<String> {
+        self.error.clone()
+    }
+
+    /// `anchor` getter.
+    pub fn anchor(&self) -> Option<String> {
+        self.anchor.clone()
+    }
+
+    /// `supersedes` getter.
+    pub fn supersedes(&self) -> Option<String> {
+        self.supersedes.clone()
+    }
+
+    /// `encryption` getter.
+    pub fn encryption(&self) -> Option<String> {
+        self.encryption.clone()
+    }
+
+    /// `created_at` getter.
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    /// `updated_at` getter.
+    pub fn updated_at(&self) -> u64 {
+        self.updated_at
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn job_status_should_be_parsed_from_its_persisted_representation() {
+        for status in vec![JobStatus::Queued, JobStatus::Mining, JobStatus::Stored, JobStatus::Failed] {
+            assert_eq!(JobStatus::from_str(status.as_str()).unwrap(), status);
+        }
+
+        assert!(JobStatus::from_str("unknown").is_err());
+    }
+
+    #[test]
+    fn new_jobs_should_be_queued_with_a_random_identifier() {
+        let first = Job::new(String::from(r#"{"Hello": "World!"}"#)).unwrap();
+        let second = Job::new(String::from(r#"{"Hello": "World!"}"#)).unwrap();
+
+        assert_eq!(first.status(), JobStatus::Queued);
+        assert_eq!(first.id().len(), 2 * JOB_ID_SIZE);
+        assert!(first.id() != second.id());
+    }
+
+    #[test]
+    fn job_should_keep_the_block_hash_once_stored() {
+        let mut job = Job::new(String::from(r#"{"Hello": "World!"}"#)).unwrap();
+
+        job.start();
+        assert_eq!(job.status(), JobStatus::Mining);
+
+        job.store(String::from("hash"));
+        assert_eq!(job.status(), JobStatus::Stored);
+        assert_eq!(job.block(), Some(String::from("hash")));
+        assert_eq!(job.error(), None);
+    }
+}
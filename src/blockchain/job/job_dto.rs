@@ -0,0 +1,39 @@
+//! Job Data Transfer Object module.
+
+use blockchain::job::Job;
+
+/// Processing status of a submitted document, as returned by the `GET /jobs/:id` endpoint.
+#[derive(
+    Debug, Clone,
+    Serialize, Deserialize
+)]
+pub struct JobDto {
+    pub id: String,
+    pub status: String,
+
+    /// Hash of the block storing the document, once stored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block: Option<String>,
+
+    /// Reason why the block of the document could not be created.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    pub created_at: u64,
+    pub updated_at: u64
+}
+
+impl JobDto {
+
+    /// Instantiate a new `JobDto` based on the given `Job`.
+    pub fn new(job: &Job) -> Self {
+        JobDto {
+            id: job.id(),
+            status: String::from(job.status().as_str()),
+            block: job.block(),
+            error: job.error(),
+            created_at: job.created_at(),
+            updated_at: job.updated_at()
+        }
+    }
+}
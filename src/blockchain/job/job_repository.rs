@@ -0,0 +1,96 @@
+//! Job Repository module.
+
+use persistence::prelude::*;
+use blockchain::job::{Job, JobStatus};
+
+table! {
+    jobs(id) {
+        id -> VarChar,
+        data -> VarChar,
+        status -> VarChar,
+        block -> VarChar,
+        error -> VarChar,
+        anchor -> VarChar,
+        supersedes -> VarChar,
+        encryption -> VarChar,
+        created_at -> Integer,
+        updated_at -> Integer,
+    }
+}
+
+#[derive(
+    Debug, Clone,
+    Queryable, Insertable, AsChangeset
+)]
+#[table_name = "jobs"]
+pub struct JobEntity {
+    pub id: String,
+    pub data: String,
+    pub status: String,
+    pub block: String,
+    pub error: String,
+    pub anchor: String,
+    pub supersedes: String,
+    pub encryption: String,
+
+    pub created_at: i32,
+    pub updated_at: i32
+}
+
+impl JobEntity {
+
+    /// Instantiate a new `JobEntity` based on the provided `Job`.
+    pub fn new(job: &Job) -> Self {
+        JobEntity {
+            id: job.id(),
+            data: job.data(),
+            status: String::from(job.status().as_str()),
+            block: job.block().unwrap_or(String::new()),
+            error: job.error().unwrap_or(String::new()),
+            anchor: job.anchor().unwrap_or(String::new()),
+            supersedes: job.supersedes().unwrap_or(String::new()),
+            encryption: job.encryption().unwrap_or(String::new()),
+
+            created_at: job.created_at() as i32,
+            updated_at: job.updated_at() as i32
+        }
+    }
+}
+
+pub struct JobRepository<'pool> {
+    connection: &'pool SqliteConnection
+}
+
+impl<'pool> JobRepository<'pool> {
+
+    /// Instantiate a new `JobRepository` whose lifetime is bound to its pooled connection.
+    pub fn new(connection: &SqliteConnection) -> JobRepository {
+        JobRepository {
+            connection: connection
+        }
+    }
+
+    /// Return at most `limit` queued jobs, from the oldest to the newest one.
+    pub fn get_queued(&self, limit: i64) -> Option<Vec<JobEntity>> {
+        match jobs::table.filter(jobs::status.eq(JobStatus::Queued.as_str()))
+            .order((jobs::created_at.asc(), jobs::id.asc()))
+            .limit(limit)
+            .load(self.connection) {
+            Ok(entities) => Some(entities),
+            Err(_) => None
+        }
+    }
+
+    /// Queue again the jobs whose mining was interrupted by a shutdown of the node, returning the
+    /// number of updated jobs.
+    pub fn requeue_interrupted(&self) -> LocksidianResult<usize> {
+        match ::diesel::update(jobs::table.filter(jobs::status.eq(JobStatus::Mining.as_str())))
+            .set(jobs::status.eq(JobStatus::Queued.as_str()))
+            .execute(self.connection) {
+            Ok(updated_rows) => Ok(updated_rows),
+            Err(err) => Err(LocksidianError::from_err(err))
+        }
+    }
+}
+
+crud_repository!(jobs, JobEntity, String, id, JobRepository<'pool>);
@@ -0,0 +1,246 @@
+//! Background worker mining the blocks of the queued jobs.
+//!
+//! The oldest queued jobs are stored together in a single batch block, holding at most
+//! `max_batch_size` documents (see the network configuration). Each anchor, each revision of a
+//! document and each encrypted document is stored in its own block.
+
+use persistence::prelude::*;
+use sec::sha::sha512;
+
+use std::collections::HashSet;
+use std::thread;
+use std::time::Duration;
+
+use blockchain::get_current_timestamp;
+use blockchain::block::{Block, BlockEntity, BlockRepository};
+use blockchain::identity::Identity;
+use blockchain::identity::identity_cli::get_active_identity;
+use blockchain::job::{Job, JobEntity, JobRepository, JobStatus};
+use blockchain::network::{Client, HttpClient, NetworkConfig};
+use blockchain::peer::PeerRepository;
+use blockchain::peer::peer_cli;
+
+/// Delay, in milliseconds, between two lookups of the queue when no job is waiting.
+const POLL_INTERVAL: u64 = 1000;
+
+/// Queue again the jobs interrupted by a previous shutdown of the node, then start the worker
+/// thread processing the queued jobs in their submission order.
+pub fn start(network: NetworkConfig) -> LocksidianResult<()> {
+    let connection = get_connection(database_path())?;
+    let requeued = JobRepository::new(&connection).requeue_interrupted()?;
+
+    if requeued > 0 {
+        info!("{} interrupted job(s) queued again", requeued);
+    }
+
+    match thread::Builder::new().name(String::from("job-worker")).spawn(move || work(network)) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(LocksidianError::from_err(err))
+    }
+}
+
+/// Worker thread loop.
+fn work(network: NetworkConfig) {
+    let connection = match get_connection(database_path()) {
+        Ok(connection) => connection,
+        Err(err) => {
+            error!("Unable to start the job worker: {}", err.description());
+            return;
+        }
+    };
+
+    loop {
+        match JobRepository::new(&connection).get_queued(network.max_batch_size as i64) {
+            Some(ref entities) if !entities.is_empty() => match process(entities.clone(), &network, &connection) {
+                Ok(_) => (),
+                Err(err) => {
+                    error!("Unable to process the queued jobs: {}", err.description());
+                    thread::sleep(Duration::from_millis(POLL_INTERVAL));
+                }
+            },
+            _ => thread::sleep(Duration::from_millis(POLL_INTERVAL))
+        }
+    }
+}
+
+/// Mine and store a single block for the documents of the jobs, and a block for each of their
+/// anchors, revisions and encrypted documents, keeping track of their status.
+///
+/// The jobs whose document is already stored in the registry (or submitted twice in the batch), or
+/// cannot be canonicalised, fail right away with their own error, so that they do not prevent the
+/// other documents from being stored.
+fn process(entities: Vec<JobEntity>, network: &NetworkConfig, connection: &SqliteConnection) -> LocksidianResult<()> {
+    let repository = JobRepository::new(connection);
+    let block_repository = BlockRepository::new(connection);
+
+    let mut jobs: Vec<Job> = Vec::new();
+    let mut data_hashes: HashSet<String> = HashSet::new();
+
+    for entity in entities {
+        let mut job = match Job::from_entity(entity.clone()) {
+            Ok(job) => job,
+            Err(err) => {
+                fail_entity(entity, err.description().to_string(), &repository)?;
+                continue;
+            }
+        };
+        let data_hash = job.anchor().unwrap_or(sha512(job.data().as_bytes()));
+
+        let admitted = match Block::assert_document_uniqueness(data_hash.as_ref(), &block_repository) {
+            Ok(_) => match data_hashes.insert(data_hash) {
+                true => check_document(&job, network),
+                false => Err(String::from("The same document has been submitted by another queued job"))
+            },
+            Err(err) => Err(err.description().to_string())
+        };
+
+        match admitted {
+            Ok(_) => job.start(),
+            Err(reason) => job.fail(reason)
+        };
+
+        repository.update(&JobEntity::new(&job))?;
+        jobs.push(job);
+    }
+
+    let documents: Vec<String> = jobs.iter()
+        .filter(|job| job.status() == JobStatus::Mining && is_batched(job))
+        .map(|job| job.data())
+        .collect();
+
+    if !documents.is_empty() {
+        info!("Mining a block storing {} document(s)", documents.len());
+        let result = store_documents(documents, network, connection);
+
+        for job in jobs.iter_mut().filter(|job| job.status() == JobStatus::Mining && is_batched(job)) {
+            finish(job, &result, &repository)?;
+        }
+    }
+
+    for job in jobs.iter_mut().filter(|job| job.status() == JobStatus::Mining) {
+        let result = match (job.anchor(), job.supersedes(), job.encryption()) {
+            (Some(data_hash), _, _) => {
+                info!("Mining a block anchoring document {}", data_hash);
+                store_anchor(data_hash, job.data(), network, connection)
+            },
+            (None, supersedes, Some(_)) => {
+                info!("Mining a block storing an encrypted document");
+                store_encrypted(job.data(), supersedes.unwrap_or(String::new()), network, connection)
+            },
+            (None, Some(supersedes), None) => {
+                info!("Mining a block superseding document {}", supersedes);
+                store_revision(job.data(), supersedes, network, connection)
+            },
+            (None, None, None) => Err(LocksidianError::new(String::from("The document should have been stored in a batch block")))
+        };
+
+        finish(job, &result, &repository)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the error preventing the document of a batched job from being stored, if any.
+fn check_document(job: &Job, network: &NetworkConfig) -> Result<(), String> {
+    if !is_batched(job) {
+        return Ok(());
+    }
+
+    match network.canonicalization.apply(job.data().as_ref()) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(err.description().to_string())
+    }
+}
+
+/// Is the document of the job stored along with the other documents, in a batch block?
+fn is_batched(job: &Job) -> bool {
+    job.anchor().is_none() && job.supersedes().is_none() && job.encryption().is_none()
+}
+
+/// Flag the job as stored in the block whose hash is returned by `result`, or as failed.
+fn finish(job: &mut Job, result: &LocksidianResult<String>, repository: &JobRepository) -> LocksidianResult<()> {
+    match *result {
+        Ok(ref hash) => job.store(hash.clone()),
+        Err(ref err) => {
+            warn!("Job {} failed: {}", job.id(), err.description());
+            job.fail(err.description().to_string());
+        }
+    };
+
+    repository.update(&JobEntity::new(&job))?;
+    Ok(())
+}
+
+/// Flag as failed a job that cannot be loaded from its `JobEntity`, so that it is not selected again.
+fn fail_entity(mut entity: JobEntity, reason: String, repository: &JobRepository) -> LocksidianResult<()> {
+    warn!("Job {} failed: {}", entity.id, reason);
+
+    entity.status = String::from(JobStatus::Failed.as_str());
+    entity.error = reason;
+    entity.updated_at = get_current_timestamp() as i32;
+
+    repository.update(&entity)?;
+    Ok(())
+}
+
+/// Store the documents in a new `Block`, propagate it to the peers and return its hash.
+fn store_documents(documents: Vec<String>, network: &NetworkConfig, connection: &SqliteConnection) -> LocksidianResult<String> {
+    let identity = get_active_identity(connection)?;
+    let repository = BlockRepository::new(connection);
+
+    let block = Block::new_batch(documents, &identity, network, &repository)?;
+    store(&block, &identity, network, &repository, connection)
+}
+
+/// Store the anchor of a document in a new `Block`, propagate it to the peers and return its hash.
+fn store_anchor(data_hash: String, metadata: String, network: &NetworkConfig, connection: &SqliteConnection) -> LocksidianResult<String> {
+    let identity = get_active_identity(connection)?;
+    let repository = BlockRepository::new(connection);
+
+    let block = Block::anchor(data_hash, metadata, &identity, network, &repository)?;
+    store(&block, &identity, network, &repository, connection)
+}
+
+/// Store the revision of a document in a new `Block`, propagate it to the peers and return its hash.
+fn store_revision(data: String, supersedes: String, network: &NetworkConfig, connection: &SqliteConnection) -> LocksidianResult<String> {
+    let identity = get_active_identity(connection)?;
+    let repository = BlockRepository::new(connection);
+
+    let block = Block::supersede(data, supersedes, &identity, network, &repository)?;
+    store(&block, &identity, network, &repository, connection)
+}
+
+/// Store an encrypted document in a new `Block`, propagate it to the peers and return its hash.
+fn store_encrypted(envelope: String, supersedes: String, network: &NetworkConfig, connection: &SqliteConnection) -> LocksidianResult<String> {
+    let identity = get_active_identity(connection)?;
+    let repository = BlockRepository::new(connection);
+
+    let block = Block::encrypted(envelope, supersedes, &identity, network, &repository)?;
+    store(&block, &identity, network, &repository, connection)
+}
+
+/// Save the newly mined `Block` in the registry, propagate it to the peers and return its hash.
+fn store(block: &Block, identity: &Identity, network: &NetworkConfig, repository: &BlockRepository, connection: &SqliteConnection) -> LocksidianResult<String> {
+    let mut entity = BlockEntity::new(&block);
+
+    match repository.save_block(&mut entity, network)? {
+        1 => {
+            propagate(&block, &identity, network, connection);
+            Ok(block.hash())
+        },
+        _ => Err(LocksidianError::new(String::from("An unexpected number of rows were inserted in the registry")))
+    }
+}
+
+/// Gossip the newly stored `Block` to the active peers.
+fn propagate(block: &Block, identity: &Identity, network: &NetworkConfig, connection: &SqliteConnection) {
+    let peers = peer_cli::active_peers(&PeerRepository::new(connection));
+
+    let result = identity.public_key_to_hex()
+        .and_then(|author_key| HttpClient::propagate(&block, &identity, author_key.as_ref(), peers, network.gossip_fanout as usize, connection));
+
+    match result {
+        Ok(_) => (),
+        Err(err) => warn!("Unable to propagate block {}: {}", block.hash(), err.description())
+    }
+}
@@ -0,0 +1,13 @@
+//! Asynchronous processing of the documents submitted to the node.
+//!
+//! A submitted document is persisted as a queued `Job`, whose block is then mined and stored by a
+//! background worker. As the jobs are persisted next to the blocks, the queue survives a restart.
+
+mod job_domain;
+mod job_dto;
+mod job_repository;
+pub mod job_worker;
+
+pub use self::job_domain::{Job, JobStatus};
+pub use self::job_dto::JobDto;
+pub use self::job_repository::{JobEntity, JobRepository};
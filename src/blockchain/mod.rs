@@ -1,6 +1,6 @@
 //! Blockchain root module.
 
-mod algorithm;
+pub mod algorithm;
 
 pub mod version;
 pub mod network;
@@ -8,6 +8,12 @@ pub mod identity;
 pub mod block;
 pub mod peer;
 pub mod metric;
+pub mod chain;
+pub mod consensus;
+pub mod job;
+pub mod document;
+pub mod file;
+pub mod receipt;
 
 /// Return the current timestamp as an `u64`.
 pub fn get_current_timestamp() -> u64 {
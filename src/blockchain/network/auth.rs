@@ -0,0 +1,273 @@
+//! Authentication of the node-to-node requests.
+//!
+//! A node signs each of the requests it sends to its peers using the key of its active identity.
+//! The signature covers the method, the path (without the query), the SHA-512 checksum of the body,
+//! the timestamp and a random nonce of the request, one per line:
+//!
+//! ```text
+//! PUT
+//! /blocks
+//! {sha512 body checksum}
+//! {timestamp}
+//! {nonce}
+//! ```
+//!
+//! The signer identity, the timestamp, the nonce and the hexadecimal signature are sent along with
+//! the request in the `X-LS-IDENTITY`, `X-LS-TIMESTAMP`, `X-LS-NONCE` and `X-LS-NODE-SIGNATURE`
+//! headers. A request whose timestamp is more than `MAX_CLOCK_SKEW` seconds away from the receiving
+//! node's clock is rejected, as is a request whose nonce was already used by its signer within
+//! this window.
+//!
+//! Before registering a peer, a node challenges it to prove that it holds the private key of the
+//! identity it announces: a random nonce is sent to the announced address, along with the identity
+//! of the registrar, and must be returned signed. The signed message is `CHALLENGE` followed by the
+//! nonce, the address of the challenged node and the registrar identity, one per line:
+//!
+//! ```text
+//! CHALLENGE
+//! {nonce}
+//! {address}
+//! {registrar identity}
+//! ```
+//!
+//! A challenge response can never be replayed as the signature of a request. The challenged node
+//! signs its own address, whatever the address the registrar knows it by: a node announcing the
+//! identity of another one cannot relay the challenge to it, as the proof would not be valid for
+//! its own address (nor for another registrar).
+
+use error::*;
+
+use sec::rsa::Rsa;
+use sec::sha::sha512;
+use sec::hex::{FromHex, ToHex};
+
+use openssl::rand::rand_bytes;
+
+use blockchain::identity::identity_cli::compute_key_hash;
+
+/// Header carrying the hash of the signer identity.
+pub const IDENTITY_HEADER: &'static str = "X-LS-IDENTITY";
+
+/// Header carrying the timestamp of the request.
+pub const TIMESTAMP_HEADER: &'static str = "X-LS-TIMESTAMP";
+
+/// Header carrying the hexadecimal nonce of the request.
+pub const NONCE_HEADER: &'static str = "X-LS-NONCE";
+
+/// Header carrying the hexadecimal signature of the request.
+pub const SIGNATURE_HEADER: &'static str = "X-LS-NODE-SIGNATURE";
+
+/// Maximum difference, in seconds, between the timestamp of a request and the receiving node's clock.
+pub const MAX_CLOCK_SKEW: u64 = 300;
+
+/// Length of an identity hash: a lowercase hexadecimal RIPEMD-160 checksum.
+const IDENTITY_HASH_SIZE: usize = 40;
+
+/// Size, in bytes, of the nonces of the requests and of the challenges of the registering peers.
+pub const NONCE_SIZE: usize = 32;
+
+/// Hexadecimal nonce sent to a registering peer by the `registrar` identity.
+#[derive(
+    Debug, Clone,
+    Serialize, Deserialize
+)]
+pub struct ChallengeDto {
+    pub nonce: String,
+    pub registrar: String
+}
+
+/// Hexadecimal signature of a challenge nonce, proving the possession of an identity key.
+#[derive(
+    Debug, Clone,
+    Serialize, Deserialize
+)]
+pub struct ProofDto {
+    pub signature: String
+}
+
+/// Signature of a node-to-node request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestSignature {
+    pub identity: String,
+    pub timestamp: u64,
+    pub nonce: String,
+    pub signature: String
+}
+
+impl RequestSignature {
+
+    /// Sign the request as the `identity` hash owning the private `key`, using a fresh nonce.
+    pub fn sign(identity: String, key: &Rsa, method: &str, path: &str, body: &[u8], timestamp: u64) -> LocksidianResult<Self> {
+        let nonce = generate_nonce()?;
+        let message = request_message(method, path, body, timestamp, nonce.as_ref())?;
+
+        Ok(RequestSignature {
+            identity: identity,
+            timestamp: timestamp,
+            nonce: nonce,
+            signature: key.sign(message.as_bytes())?.to_hex()
+        })
+    }
+
+    /// Check that the request was signed, no more than `MAX_CLOCK_SKEW` seconds away from `now`,
+    /// by the identity owning the public `key`.
+    pub fn verify(&self, key: &Rsa, method: &str, path: &str, body: &[u8], now: u64) -> LocksidianResult<()> {
+        let skew = match now > self.timestamp {
+            true => now - self.timestamp,
+            false => self.timestamp - now
+        };
+
+        if skew > MAX_CLOCK_SKEW {
+            return Err(LocksidianError::new(format!("The request timestamp {} is out of the accepted window", self.timestamp)));
+        }
+
+        if compute_key_hash(&key)? != self.identity {
+            return Err(LocksidianError::new(format!("The key does not belong to identity {}", self.identity)));
+        }
+
+        let signature = match self.signature.from_hex() {
+            Ok(signature) => signature,
+            Err(err) => return Err(LocksidianError::from_err(err))
+        };
+
+        let message = request_message(method, path, body, self.timestamp, self.nonce.as_ref())?;
+        match key.verify_signature(message.as_bytes(), signature.as_slice())? {
+            true => Ok(()),
+            false => Err(LocksidianError::new(format!("Invalid request signature for identity {}", self.identity)))
+        }
+    }
+}
+
+/// Returns the signed message of a request. Only the nonces of `NONCE_SIZE` hexadecimal bytes are
+/// accepted.
+pub fn request_message(method: &str, path: &str, body: &[u8], timestamp: u64, nonce: &str) -> LocksidianResult<String> {
+    check_nonce(nonce)?;
+
+    Ok(format!("{}\n{}\n{}\n{}\n{}", method.to_uppercase(), path, sha512(body), timestamp, nonce.to_lowercase()))
+}
+
+/// Generate a random hexadecimal nonce of `NONCE_SIZE` bytes.
+pub fn generate_nonce() -> LocksidianResult<String> {
+    let mut nonce = vec![0u8; NONCE_SIZE];
+
+    match rand_bytes(&mut nonce) {
+        Ok(_) => Ok(nonce.to_hex()),
+        Err(err) => Err(LocksidianError::from_err(err))
+    }
+}
+
+/// Returns the signed message of a challenge sent by the `registrar` identity to the node at
+/// `address`. Only the nonces of `NONCE_SIZE` hexadecimal bytes are accepted.
+pub fn challenge_message(nonce: &str, address: &str, registrar: &str) -> LocksidianResult<String> {
+    check_nonce(nonce)?;
+
+    if address.is_empty() {
+        return Err(LocksidianError::new(String::from("A challenge must be bound to an address")));
+    }
+
+    // A well-formed identity hash, so that the node cannot be made to sign an arbitrary string
+    if registrar.len() != IDENTITY_HASH_SIZE || !registrar.chars().all(|c| c.is_digit(16) && !c.is_uppercase()) {
+        return Err(LocksidianError::new(format!("The registrar {} is not a valid identity hash", registrar)));
+    }
+
+    Ok(format!("CHALLENGE\n{}\n{}\n{}", nonce.to_lowercase(), address, registrar))
+}
+
+/// Check that the `nonce` is made of `NONCE_SIZE` hexadecimal bytes.
+fn check_nonce(nonce: &str) -> LocksidianResult<()> {
+    match nonce.from_hex() {
+        Ok(ref bytes) if bytes.len() == NONCE_SIZE => Ok(()),
+        _ => Err(LocksidianError::new(format!("Invalid nonce, expected {} hexadecimal bytes", NONCE_SIZE)))
+    }
+}
+
+/// Answer the challenge `nonce` of the `registrar` using the private `key` of the identity of the
+/// node at `address`.
+pub fn prove_possession(nonce: &str, address: &str, registrar: &str, key: &Rsa) -> LocksidianResult<ProofDto> {
+    let message = challenge_message(nonce, address, registrar)?;
+
+    Ok(ProofDto {
+        signature: key.sign(message.as_bytes())?.to_hex()
+    })
+}
+
+/// Check that the `proof` answering the challenge `nonce` of the `registrar` was signed by the owner
+/// of the public `key`, for the node at `address`.
+pub fn verify_possession(nonce: &str, address: &str, registrar: &str, proof: &ProofDto, key: &Rsa) -> LocksidianResult<()> {
+    let message = challenge_message(nonce, address, registrar)?;
+
+    let signature = match proof.signature.from_hex() {
+        Ok(signature) => signature,
+        Err(err) => return Err(LocksidianError::from_err(err))
+    };
+
+    match key.verify_signature(message.as_bytes(), signature.as_slice())? {
+        true => Ok(()),
+        false => Err(LocksidianError::new(String::from("The challenge was not signed using the announced key")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const REGISTRAR: &'static str = "0123456789abcdef0123456789abcdef01234567";
+    const RELAY: &'static str = "76543210fedcba9876543210fedcba9876543210";
+
+    #[test]
+    fn signed_requests_should_only_verify_unaltered() {
+        let key = Rsa::generate(2048).unwrap();
+        let identity = compute_key_hash(&key).unwrap();
+        let signature = RequestSignature::sign(identity, &key, "PUT", "/blocks", b"{}", 1000).unwrap();
+
+        assert!(signature.verify(&key, "PUT", "/blocks", b"{}", 1000 + MAX_CLOCK_SKEW).is_ok());
+        assert!(signature.verify(&key, "PUT", "/blocks", b"{}", 1000 + MAX_CLOCK_SKEW + 1).is_err());
+        assert!(signature.verify(&key, "POST", "/blocks", b"{}", 1000).is_err());
+        assert!(signature.verify(&key, "PUT", "/peers/register", b"{}", 1000).is_err());
+        assert!(signature.verify(&key, "PUT", "/blocks", b"[]", 1000).is_err());
+
+        let mut forged = signature.clone();
+        forged.identity = String::from("someone else");
+        assert!(forged.verify(&key, "PUT", "/blocks", b"{}", 1000).is_err());
+
+        let mut forged = signature.clone();
+        forged.nonce = generate_nonce().unwrap();
+        assert!(forged.verify(&key, "PUT", "/blocks", b"{}", 1000).is_err());
+
+        forged.nonce = String::from("00");
+        assert!(forged.verify(&key, "PUT", "/blocks", b"{}", 1000).is_err());
+
+        let other = RequestSignature::sign(signature.identity.clone(), &key, "PUT", "/blocks", b"{}", 1000).unwrap();
+        assert!(other.nonce != signature.nonce);
+    }
+
+    #[test]
+    fn challenges_should_prove_the_possession_of_the_key() {
+        let key = Rsa::generate(2048).unwrap();
+        let other = Rsa::generate(2048).unwrap();
+        let nonce = generate_nonce().unwrap();
+
+        let proof = prove_possession(nonce.as_ref(), "10.0.0.1:8080", REGISTRAR, &key).unwrap();
+        assert!(verify_possession(nonce.as_ref(), "10.0.0.1:8080", REGISTRAR, &proof, &key).is_ok());
+        assert!(verify_possession(nonce.as_ref(), "10.0.0.1:8080", REGISTRAR, &proof, &other).is_err());
+        assert!(verify_possession(generate_nonce().unwrap().as_ref(), "10.0.0.1:8080", REGISTRAR, &proof, &key).is_err());
+
+        assert!(prove_possession("PUT", "10.0.0.1:8080", REGISTRAR, &key).is_err());
+        assert!(prove_possession("00", "10.0.0.1:8080", REGISTRAR, &key).is_err());
+        assert!(prove_possession(nonce.as_ref(), "", REGISTRAR, &key).is_err());
+        assert!(prove_possession(nonce.as_ref(), "10.0.0.1:8080", "", &key).is_err());
+        assert!(prove_possession(nonce.as_ref(), "10.0.0.1:8080", "registrar", &key).is_err());
+        assert!(prove_possession(nonce.as_ref(), "10.0.0.1:8080", REGISTRAR.to_uppercase().as_ref(), &key).is_err());
+    }
+
+    #[test]
+    fn challenges_should_not_be_relayed() {
+        let key = Rsa::generate(2048).unwrap();
+        let nonce = generate_nonce().unwrap();
+        let proof = prove_possession(nonce.as_ref(), "10.0.0.1:8080", REGISTRAR, &key).unwrap();
+
+        // Announced at another address, or challenged by another registrar
+        assert!(verify_possession(nonce.as_ref(), "10.0.0.2:8080", REGISTRAR, &proof, &key).is_err());
+        assert!(verify_possession(nonce.as_ref(), "10.0.0.1:8080", RELAY, &proof, &key).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+//! Network configuration.
+//!
+//! Rules shared by all the nodes of a `Locksidian` network, loaded at startup from the JSON file
+//! specified using the `--network-config` command line argument. Every missing field falls back to
+//! its default value, so that an empty JSON object (`{}`) describes the default network.
+
+use error::*;
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use blockchain::consensus::{ConsensusConfig, ConsensusEngine, build_engine};
+use blockchain::document::{Canonicalization, check_schema};
+
+/// Default maximum number of blocks a replicated fork block can lie behind the `HEAD` block.
+const DEFAULT_MAX_REPLICATION_DEPTH: u64 = 5;
+
+/// Default number of blocks between two difficulty retargetings.
+const DEFAULT_RETARGET_WINDOW: u64 = 10;
+
+/// Default maximum number of documents stored in a single block.
+const DEFAULT_MAX_BATCH_SIZE: u64 = 64;
+
+/// Default number of peers a new block is announced to.
+const DEFAULT_GOSSIP_FANOUT: u64 = 8;
+
+/// Configuration of the `Locksidian` network joined by the node.
+#[derive(
+	Debug, Clone,
+	Serialize, Deserialize
+)]
+pub struct NetworkConfig {
+	
+	/// A replicated block whose `previous` block already has a `next` block is rejected if it lies
+	/// more than `max_replication_depth` blocks behind the `HEAD` block.
+	#[serde(default = "default_max_replication_depth")]
+	pub max_replication_depth: u64,
+	
+	/// Consensus engine used to seal and validate the blocks of the network.
+	#[serde(default)]
+	pub consensus: ConsensusConfig,
+	
+	/// Expected time, in seconds, between two blocks. The Proof of Work difficulty is retargeted
+	/// towards this interval, unless it is `0` (default).
+	#[serde(default)]
+	pub target_block_interval: u64,
+	
+	/// Number of blocks between two difficulty retargetings.
+	#[serde(default = "default_retarget_window")]
+	pub retarget_window: u64,
+	
+	/// Maximum number of documents stored in a single block.
+	#[serde(default = "default_max_batch_size")]
+	pub max_batch_size: u64,
+	
+	/// Number of randomly selected peers a new block is announced to.
+	#[serde(default = "default_gossip_fanout")]
+	pub gossip_fanout: u64,
+	
+	/// Canonicalisation mode applied to the submitted documents before they are hashed: `raw`
+	/// (default) or `jcs`.
+	#[serde(default)]
+	pub canonicalization: Canonicalization,
+	
+	/// Named JSON Schemas of the documents accepted by the network: a document must match at least
+	/// one of them. Any document is accepted when no schema is configured (default).
+	#[serde(default)]
+	pub schemas: BTreeMap<String, Value>,
+	
+	/// Height up to which the blocks created by older nodes, whose signature covers their data
+	/// instead of their checksum, are accepted. Such signatures are never accepted when it is `0`
+	/// (default): set it to the height of the `HEAD` block when upgrading a network created by
+	/// older nodes.
+	#[serde(default)]
+	pub legacy_signature_height: u64
+}
+
+fn default_max_replication_depth() -> u64 {
+	DEFAULT_MAX_REPLICATION_DEPTH
+}
+
+fn default_retarget_window() -> u64 {
+	DEFAULT_RETARGET_WINDOW
+}
+
+fn default_max_batch_size() -> u64 {
+	DEFAULT_MAX_BATCH_SIZE
+}
+
+fn default_gossip_fanout() -> u64 {
+	DEFAULT_GOSSIP_FANOUT
+}
+
+impl Default for NetworkConfig {
+	fn default() -> Self {
+		NetworkConfig {
+			max_replication_depth: default_max_replication_depth(),
+			consensus: ConsensusConfig::default(),
+			target_block_interval: 0,
+			retarget_window: default_retarget_window(),
+			max_batch_size: default_max_batch_size(),
+			gossip_fanout: default_gossip_fanout(),
+			canonicalization: Canonicalization::default(),
+			schemas: BTreeMap::new(),
+			legacy_signature_height: 0
+		}
+	}
+}
+
+impl NetworkConfig {
+	
+	/// Load the network configuration from the specified JSON file. If no file is specified, the
+	/// default configuration is used.
+	pub fn load(path: Option<String>) -> LocksidianResult<Self> {
+		match path {
+			Some(path) => match File::open(path.as_str()) {
+				Ok(mut file) => {
+					let mut json = String::new();
+					
+					match file.read_to_string(&mut json) {
+						Ok(_) => NetworkConfig::from_json(json.as_ref()),
+						Err(err) => Err(LocksidianError::from_err(err))
+					}
+				},
+				Err(err) => Err(LocksidianError::new(format!("Unable to open the network configuration file {}: {}", path, err.description())))
+			},
+			None => Ok(NetworkConfig::default())
+		}
+	}
+	
+	/// Instantiate the consensus engine of the network.
+	pub fn consensus_engine(&self) -> LocksidianResult<Box<ConsensusEngine>> {
+		build_engine(&self.consensus)
+	}
+	
+	/// Parse a JSON network configuration, and make sure that its consensus engine can be instantiated.
+	fn from_json(json: &str) -> LocksidianResult<Self> {
+		let config: NetworkConfig = match ::serde_json::from_str(json) {
+			Ok(config) => config,
+			Err(err) => return Err(LocksidianError::new(format!("Invalid network configuration: {}", err)))
+		};
+		
+		if config.target_block_interval > 0 && config.retarget_window < 2 {
+			return Err(LocksidianError::new(String::from("Invalid network configuration: retarget_window should be at least 2")));
+		}
+		
+		if config.max_batch_size == 0 {
+			return Err(LocksidianError::new(String::from("Invalid network configuration: max_batch_size should be at least 1")));
+		}
+		
+		if config.gossip_fanout == 0 {
+			return Err(LocksidianError::new(String::from("Invalid network configuration: gossip_fanout should be at least 1")));
+		}
+		
+		for (name, schema) in config.schemas.iter() {
+			match check_schema(name.as_ref(), schema) {
+				Ok(_) => (),
+				Err(err) => return Err(LocksidianError::new(format!("Invalid network configuration: {}", err.description())))
+			}
+		}
+		
+		match config.consensus_engine() {
+			Ok(_) => Ok(config),
+			Err(err) => Err(LocksidianError::new(format!("Invalid consensus configuration: {}", err.description())))
+		}
+	}
+	
+	/// Is the Proof of Work difficulty retargeted on this network?
+	pub fn is_retargeting(&self) -> bool {
+		self.target_block_interval > 0
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	
+	#[test]
+	fn missing_fields_should_use_default_values() {
+		let config = NetworkConfig::from_json("{}").unwrap();
+		assert_eq!(DEFAULT_MAX_REPLICATION_DEPTH, config.max_replication_depth);
+	}
+	
+	#[test]
+	fn fields_should_be_overridden() {
+		let config = NetworkConfig::from_json(r#"{"max_replication_depth": 10}"#).unwrap();
+		assert_eq!(10, config.max_replication_depth);
+	}
+	
+	#[test]
+	fn unsigned_proof_of_authority_should_be_rejected() {
+		assert!(NetworkConfig::from_json(r#"{"consensus": {"engine": "proof-of-authority", "authorities": ["alice"]}}"#).is_err());
+	}
+	
+	#[test]
+	fn retargeting_should_require_a_window_of_at_least_two_blocks() {
+		assert!(!NetworkConfig::from_json("{}").unwrap().is_retargeting());
+		assert!(NetworkConfig::from_json(r#"{"target_block_interval": 60}"#).unwrap().is_retargeting());
+		assert!(NetworkConfig::from_json(r#"{"target_block_interval": 60, "retarget_window": 1}"#).is_err());
+	}
+	
+	#[test]
+	fn invalid_configuration_should_be_rejected() {
+		assert!(NetworkConfig::from_json(r#"{"max_replication_depth": "ten"}"#).is_err());
+		assert!(NetworkConfig::from_json(r#"{"max_batch_size": 0}"#).is_err());
+		assert!(NetworkConfig::from_json(r#"{"gossip_fanout": 0}"#).is_err());
+		assert!(NetworkConfig::from_json(r#"{"canonicalization": "c14n"}"#).is_err());
+		assert!(NetworkConfig::from_json(r#"{"schemas": {"invoice": {"type": "object", "pattern": "^F-"}}}"#).is_err());
+	}
+	
+	#[test]
+	fn documents_should_be_canonicalised_on_demand() {
+		assert_eq!(Canonicalization::Raw, NetworkConfig::from_json("{}").unwrap().canonicalization);
+		assert_eq!(Canonicalization::Jcs, NetworkConfig::from_json(r#"{"canonicalization": "jcs"}"#).unwrap().canonicalization);
+	}
+}
@@ -0,0 +1,80 @@
+//! Inventory-based gossip.
+//!
+//! Instead of pushing a new block to every peer, a node announces its hash to a random subset of
+//! its peers (the *fanout*, see the network configuration). Each of them answers with the hashes it
+//! lacks, and only these blocks are replicated to it. A node receiving a new block forwards it the
+//! same way, once, when it stores it: a block already in the registry is neither requested nor
+//! forwarded again.
+
+use error::*;
+
+use openssl::rand::rand_bytes;
+
+/// Maximum number of block hashes announced in a single inventory.
+pub const MAX_INVENTORY_SIZE: usize = 500;
+
+/// Hashes of the blocks announced by the `sender` identity. The receiving node ignores the
+/// `sender` field, and uses the verified signer of the request instead.
+#[derive(
+    Debug, Clone,
+    Serialize, Deserialize
+)]
+pub struct InventoryDto {
+    pub sender: String,
+    pub hashes: Vec<String>
+}
+
+/// Hashes of the announced blocks that the receiving node lacks, and wants to be replicated to it.
+#[derive(
+    Debug, Clone,
+    Serialize, Deserialize
+)]
+pub struct WantedDto {
+    pub wanted: Vec<String>
+}
+
+/// Randomly select at most `fanout` of the given `items`.
+pub fn select_fanout<T>(items: Vec<T>, fanout: usize) -> LocksidianResult<Vec<T>> {
+    let mut random = vec![0u8; items.len() * 4];
+
+    match rand_bytes(&mut random) {
+        Ok(_) => Ok(pick(items, fanout, &random)),
+        Err(err) => Err(LocksidianError::from_err(err))
+    }
+}
+
+/// Select at most `fanout` of the given `items` using a partial Fisher-Yates shuffle, drawing a
+/// 32-bit number from the `random` bytes for each selected item.
+fn pick<T>(mut items: Vec<T>, fanout: usize, random: &[u8]) -> Vec<T> {
+    let count = ::std::cmp::min(fanout, items.len());
+
+    for i in 0..count {
+        let bytes = &random[i * 4..i * 4 + 4];
+        let draw = (bytes[0] as usize) << 24 | (bytes[1] as usize) << 16 | (bytes[2] as usize) << 8 | bytes[3] as usize;
+
+        let j = i + draw % (items.len() - i);
+        items.swap(i, j);
+    }
+
+    items.truncate(count);
+    items
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn fanout_should_bound_the_number_of_selected_peers() {
+        let peers: Vec<u32> = (0..10).collect();
+
+        let selected = select_fanout(peers.clone(), 3).unwrap();
+        let distinct: HashSet<&u32> = selected.iter().collect();
+        assert_eq!(selected.len(), 3);
+        assert_eq!(distinct.len(), 3);
+
+        assert_eq!(select_fanout(peers.clone(), 20).unwrap().len(), 10);
+        assert_eq!(pick(peers, 2, &[0; 40]), vec![0, 1]);
+    }
+}
@@ -1,8 +1,13 @@
 //! Blockchain networking client.
 
 use error::*;
+use std::io;
 use std::io::Read;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
 use hyper::Client;
+use hyper::net::{HttpsConnector, HttpsStream, HttpStream, NetworkConnector, SslClient};
+use hyper_openssl::OpensslClient;
 
 use persistence::prelude::*;
 
@@ -11,11 +16,23 @@ use hyper::header::{Headers, ContentType};
 use iron::mime::{Mime, TopLevel, SubLevel, Attr, Value};
 
 use blockchain::network::p2p;
-use blockchain::peer::{Peer, PeerDto};
+use blockchain::network::tls;
+use blockchain::network::NetworkConfig;
+use blockchain::network::{InventoryDto, WantedDto, select_fanout};
+use blockchain::network::{RequestSignature, IDENTITY_HEADER, TIMESTAMP_HEADER, NONCE_HEADER, SIGNATURE_HEADER};
+use blockchain::network::{ChallengeDto, ProofDto};
+use blockchain::peer::{Peer, PeerDto, PeerRepository, InventoryRepository};
+use blockchain::peer::peer_cli;
 use blockchain::block::*;
+use blockchain::get_current_timestamp;
 use blockchain::identity::Identity;
+use blockchain::identity::identity_cli::{get_identity_key, remember_author_key};
 use blockchain::version::Version;
 
+/// Error of a signed request rejected by the peer, usually because it does not know the identity of
+/// this node (e.g. it evicted this node during a network partition).
+pub const REJECTED_SIGNATURE_ERROR: &'static str = "The peer rejected the signature of this node";
+
 pub struct HttpClient {
     client: Client,
     address: String,
@@ -24,24 +41,62 @@ pub struct HttpClient {
 
 impl HttpClient {
 
-    pub fn new(client: Client, address: String, identity: Option<String>) -> Self {
+    pub fn new(client: Client, scheme: &str, address: String, identity: Option<String>) -> Self {
         HttpClient {
             client: client,
-            address: format!("http://{}", address),
+            address: format!("{}://{}", scheme, address),
 	        identity: identity
         }
     }
 
-    pub fn from_address(address: String) -> Self {
-        HttpClient::new(HttpClient::default_client(), address, None)
+    /// Instantiate a client reaching the node at the given `address`, prefixed by its scheme when
+    /// it is not `http`. Only the certificates issued for the pinned `identity` are accepted when it
+    /// is provided, any certificate otherwise (trust on first use, see `tls`).
+    pub fn from_address(address: String, identity: Option<String>) -> Self {
+        let (scheme, host) = tls::split_scheme(address.as_ref());
+        HttpClient::new(HttpClient::default_client(identity.clone()), scheme, String::from(host), identity)
     }
 	
+    /// Instantiate a client reaching the `Peer`, only accepting the certificates issued for its
+    /// identity key when it uses HTTPS.
     pub fn from_peer(peer: &Peer) -> Self {
-        HttpClient::new(HttpClient::default_client(), peer.address(), Some(peer.identity()))
+        HttpClient::new(HttpClient::default_client(Some(peer.identity())), peer.scheme().as_ref(), peer.address(), Some(peer.identity()))
+    }
+
+    /// Instantiate a client giving up on the requests to the `Peer` which does not accept the
+    /// connection, whose response is not read, or whose body is not written, within the `timeout`.
+    pub fn with_timeout(peer: &Peer, timeout: Duration) -> Self {
+        let connector = TimeoutConnector {
+            ssl: tls::client(Some(peer.identity())).ok(),
+            timeout: timeout
+        };
+
+        let mut client = Client::with_connector(connector);
+        client.set_read_timeout(Some(timeout));
+        client.set_write_timeout(Some(timeout));
+
+        HttpClient::new(client, peer.scheme().as_ref(), peer.address(), Some(peer.identity()))
+    }
+
+    /// Client checking the certificates against the `identity` of the node. The TLS client is
+    /// checked at startup (see `check_tls`): only a plain HTTP client can be returned afterwards.
+    fn default_client(identity: Option<String>) -> Client {
+        match tls::client(identity) {
+            Ok(ssl) => Client::with_connector(HttpsConnector::new(ssl)),
+            Err(err) => {
+                error!("Unable to configure TLS, HTTPS peers are unreachable: {}", err.description());
+                Client::new()
+            }
+        }
     }
 
-    fn default_client() -> Client {
-        Client::new()
+    /// Check that the TLS client can be configured, so that the node does not start unable to reach
+    /// its HTTPS peers.
+    pub fn check_tls() -> LocksidianResult<()> {
+        match tls::client(None) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(LocksidianError::new(format!("Unable to configure TLS, HTTPS peers would be unreachable: {}", err.description())))
+        }
     }
 	
 	fn headers(&self) -> Headers {
@@ -54,6 +109,20 @@ impl HttpClient {
 		headers
 	}
 	
+	/// Headers of a request whose `body` is sent to `path` using `method`, signed by the `identity`
+	/// of the node.
+	fn signed_headers(&self, method: &str, path: &str, body: &str, identity: &Identity) -> LocksidianResult<Headers> {
+		let signature = RequestSignature::sign(identity.hash(), identity.key(), method, path, body.as_bytes(), get_current_timestamp())?;
+		
+		let mut headers = self.headers();
+		headers.set_raw(IDENTITY_HEADER, vec![signature.identity.into_bytes()]);
+		headers.set_raw(TIMESTAMP_HEADER, vec![signature.timestamp.to_string().into_bytes()]);
+		headers.set_raw(NONCE_HEADER, vec![signature.nonce.into_bytes()]);
+		headers.set_raw(SIGNATURE_HEADER, vec![signature.signature.into_bytes()]);
+		
+		Ok(headers)
+	}
+	
 	fn to_json<T: ?Sized>(&self, value: &T) -> LocksidianResult<String> where T: ::serde::Serialize {
 		match ::serde_json::to_string(value) {
 			Ok(json) => Ok(json),
@@ -62,7 +131,8 @@ impl HttpClient {
 	}
 	
 	fn get_head(&self) -> LocksidianResult<String> {
-		let url = format!("{}/blocks", self.address.clone());
+		// Only the hash of the head is needed, not the page of blocks listed along with it
+		let url = format!("{}/blocks?limit=1", self.address.clone());
 		
 		match self.client.get(&url).send() {
 			Ok(mut res) => match client_body!(res) {
@@ -73,15 +143,12 @@ impl HttpClient {
 		}
 	}
 	
-	fn get_block(&self, hash: String) -> LocksidianResult<Block> {
+	fn get_block(&self, hash: String) -> LocksidianResult<BlockDto> {
 		let url = format!("{}/blocks/{}", self.address.clone(), hash);
 		
 		match self.client.get(&url).send() {
 			Ok(mut res) => match client_body!(res, BlockDto) {
-				Ok(dto) => match Block::from_dto(dto, self.identity.as_ref()) {
-					Ok(block) => Ok(block),
-					Err(err) => Err(LocksidianError::from_err(err))
-				},
+				Ok(dto) => Ok(dto),
 				Err(err) => Err(LocksidianError::from_err(err))
 			},
 			Err(err) => Err(LocksidianError::from_err(err))
@@ -89,6 +156,35 @@ impl HttpClient {
 	}
 }
 
+/// Connector giving up on the nodes which do not accept the connection within the `timeout`: the
+/// HTTP connector of hyper waits for the operating system to give up instead. HTTPS nodes cannot
+/// be reached when TLS could not be configured.
+struct TimeoutConnector {
+    ssl: Option<OpensslClient>,
+    timeout: Duration
+}
+
+impl NetworkConnector for TimeoutConnector {
+    type Stream = HttpsStream<<OpensslClient as SslClient>::Stream>;
+
+    fn connect(&self, host: &str, port: u16, scheme: &str) -> ::hyper::Result<Self::Stream> {
+        let mut last = io::Error::new(io::ErrorKind::NotFound, "Unable to resolve the address of the node");
+
+        for address in (host, port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, self.timeout) {
+                Ok(stream) => return match (scheme, self.ssl.as_ref()) {
+                    ("https", Some(ssl)) => ssl.wrap_client(HttpStream(stream), host).map(HttpsStream::Https),
+                    ("https", None) => Err(::hyper::Error::Io(io::Error::new(io::ErrorKind::Other, "TLS is not configured"))),
+                    _ => Ok(HttpsStream::Http(HttpStream(stream)))
+                },
+                Err(err) => last = err
+            }
+        }
+
+        Err(::hyper::Error::Io(last))
+    }
+}
+
 impl p2p::Client for HttpClient {
 	
 	fn check_version(&self) -> LocksidianResult<bool> {
@@ -110,12 +206,13 @@ impl p2p::Client for HttpClient {
 		}
 	}
     
-    fn register(&self, peer: &Peer) -> LocksidianResult<Peer> {
+    fn register(&self, peer: &Peer, identity: &Identity) -> LocksidianResult<Peer> {
         let url = format!("{}/peers/register", self.address.clone());
 		let dto = PeerDto::new(&peer)?;
 		let json = self.to_json(&dto)?;
+		let headers = self.signed_headers("POST", "/peers/register", json.as_ref(), &identity)?;
 		
-		match self.client.post(&url).headers(self.headers()).body(&json).send() {
+		match self.client.post(&url).headers(headers).body(&json).send() {
 			Ok(mut res) => match res.status {
 				StatusCode::Ok => match client_body!(res, PeerDto) {
 					Ok(dto) => dto.to_peer(),
@@ -127,6 +224,26 @@ impl p2p::Client for HttpClient {
 		}
     }
 
+    fn challenge(&self, nonce: &str, registrar: &str) -> LocksidianResult<ProofDto> {
+		let url = format!("{}/peers/challenge", self.address.clone());
+		let dto = ChallengeDto {
+			nonce: String::from(nonce),
+			registrar: String::from(registrar)
+		};
+		let json = self.to_json(&dto)?;
+		
+		match self.client.post(&url).headers(self.headers()).body(&json).send() {
+			Ok(mut res) => match res.status {
+				StatusCode::Ok => match client_body!(res, ProofDto) {
+					Ok(dto) => Ok(dto),
+					Err(err) => Err(LocksidianError::from_err(err))
+				},
+				_ => Err(LocksidianError::new(format!("Status code is: {}; expected 200 OK", res.status)))
+			},
+			Err(err) => Err(LocksidianError::from_err(err))
+		}
+	}
+
     fn get_peers(&self) -> LocksidianResult<Vec<Peer>> {
         let url = format!("{}/peers", self.address.clone());
 		
@@ -147,53 +264,102 @@ impl p2p::Client for HttpClient {
         }
     }
 	
-	fn replicate(&self, block: &Block, identity: &Identity) -> LocksidianResult<()> {
+	fn replicate(&self, block: &Block, identity: &Identity, author_key: &str) -> LocksidianResult<()> {
 		let url = format!("{}/blocks", self.address.clone());
-		let dto = BlockReplicationDto::new(&block, &identity);
+		let dto = BlockReplicationDto::new(&block, &identity, String::from(author_key));
 		let json = self.to_json(&dto)?;
+		let headers = self.signed_headers("PUT", "/blocks", json.as_ref(), &identity)?;
 		
-		match self.client.put(&url).headers(self.headers()).body(&json).send() {
-			Ok(_) => Ok(()),
+		match self.client.put(&url).headers(headers).body(&json).send() {
+			Ok(res) => match res.status {
+				StatusCode::Ok => Ok(()),
+				StatusCode::Forbidden => Err(LocksidianError::new(String::from(REJECTED_SIGNATURE_ERROR))),
+				_ => Err(LocksidianError::new(format!("Status code is: {}; expected 200 OK", res.status)))
+			},
 			Err(err) => Err(LocksidianError::from_err(err))
 		}
 	}
 	
-	fn propagate(block: &Block, identity: &Identity, peers: Vec<Peer>) -> LocksidianResult<()> {
-		for peer in peers.iter() {
+	fn announce(&self, hashes: &[String], identity: &Identity) -> LocksidianResult<Vec<String>> {
+		let url = format!("{}/blocks/inventory", self.address.clone());
+		let dto = InventoryDto {
+			sender: identity.hash(),
+			hashes: hashes.to_vec()
+		};
+		let json = self.to_json(&dto)?;
+		let headers = self.signed_headers("POST", "/blocks/inventory", json.as_ref(), &identity)?;
+		
+		match self.client.post(&url).headers(headers).body(&json).send() {
+			Ok(mut res) => match res.status {
+				StatusCode::Ok => match client_body!(res, WantedDto) {
+					Ok(dto) => Ok(dto.wanted),
+					Err(err) => Err(LocksidianError::from_err(err))
+				},
+				StatusCode::Forbidden => Err(LocksidianError::new(String::from(REJECTED_SIGNATURE_ERROR))),
+				_ => Err(LocksidianError::new(format!("Status code is: {}; expected 200 OK", res.status)))
+			},
+			Err(err) => Err(LocksidianError::from_err(err))
+		}
+	}
+	
+	fn propagate(block: &Block, identity: &Identity, author_key: &str, peers: Vec<Peer>, fanout: usize, connection: &SqliteConnection) -> LocksidianResult<()> {
+		let peer_repository = PeerRepository::new(&connection);
+		let inventory = InventoryRepository::new(&connection);
+		let hash = block.hash();
+		
+		let candidates: Vec<Peer> = peers.into_iter()
+			.filter(|peer| !inventory.knows(hash.as_ref(), peer.identity().as_ref()))
+			.collect();
+		
+		for peer in select_fanout(candidates, fanout)? {
 			let client = HttpClient::from_peer(&peer);
-			client.replicate(&block, &identity).unwrap_or(());
+			
+			let result = client.announce(&[hash.clone()], &identity).and_then(|wanted| match wanted.contains(&hash) {
+				true => client.replicate(&block, &identity, author_key),
+				false => Ok(())
+			});
+			
+			match result {
+				Ok(_) => {
+					peer_cli::record_exchange(peer.identity().as_ref(), &peer_repository).unwrap_or(());
+					// The block is only announced again to the peer if its inventory is not updated
+					match inventory.record(hash.as_ref(), peer.identity().as_ref()) {
+						Ok(_) => (),
+						Err(err) => warn!("Unable to record that peer {} knows block {}: {}", peer.identity(), hash, err.description())
+					}
+				},
+				Err(err) => debug!("Unable to gossip block {} to peer {}: {}", hash, peer.identity(), err.description())
+			}
 		}
 		
 		Ok(())
 	}
 	
-	fn sync(&self, hash: Option<String>, repository: &BlockRepository) -> LocksidianResult<()> {
+	fn sync(&self, hash: Option<String>, config: &NetworkConfig, connection: &SqliteConnection) -> LocksidianResult<()> {
+		let repository = BlockRepository::new(&connection);
+		
 		match hash {
 			Some(hash) => {
-				let block = self.get_block(hash)?;
-				block.integrity_check(&repository)?;
+				let dto = self.get_block(hash)?;
+				let author_key = get_identity_key(dto.author.as_ref(), dto.author_key.as_ref(), &connection)?;
+				
+				let block = Block::sync_from(dto, self.identity.as_ref(), &author_key, &config, &repository)?;
 				
 				let mut entity = BlockEntity::new(&block);
-				match repository.get(&block.previous()) {
-					Some(mut previous) => {
-                        info!("Adding block {}", entity.hash);
-						repository.save_next(&mut entity, &mut previous)?;
-						Ok(())
-					},
-					None => {
-                        info!("Adding block {}", entity.hash);
-						repository.save(&entity)?;
-						
-						match block.previous().is_empty() {
-							true => Ok(()),
-							false => self.sync(Some(block.previous()), &repository)
-						}
-					}
+				let previous_known = repository.get(&block.previous()).is_some();
+				
+				info!("Adding block {}", entity.hash);
+				repository.save_block(&mut entity, &config)?;
+				remember_author_key(&author_key, &connection)?;
+				
+				match previous_known || block.previous().is_empty() {
+					true => Ok(()),
+					false => self.sync(Some(block.previous()), &config, &connection)
 				}
 			},
 			None => {
 				let head = self.get_head()?;
-				self.sync(Some(head), &repository)
+				self.sync(Some(head), &config, &connection)
 			}
 		}
 	}
@@ -205,7 +371,11 @@ mod test {
 
     #[test]
     fn should_append_http_protocol() {
-        let client = HttpClient::from_address(String::from("127.0.0.1"));
+        let client = HttpClient::from_address(String::from("127.0.0.1"), None);
         assert_eq!("http://127.0.0.1", client.address);
+
+        let client = HttpClient::from_address(String::from("https://127.0.0.1"), Some(String::from("identity")));
+        assert_eq!("https://127.0.0.1", client.address);
+        assert_eq!(Some(String::from("identity")), client.identity);
     }
 }
\ No newline at end of file
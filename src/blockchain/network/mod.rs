@@ -6,9 +6,18 @@ mod macros;
 mod public;
 mod p2p;
 mod http;
+mod config;
+mod gossip;
+mod auth;
+
+pub mod tls;
 
 pub use self::public::*;
 pub use self::p2p::Client;
-pub use self::http::HttpClient;
+pub use self::gossip::{InventoryDto, WantedDto, MAX_INVENTORY_SIZE, select_fanout};
+pub use self::auth::{RequestSignature, IDENTITY_HEADER, TIMESTAMP_HEADER, NONCE_HEADER, SIGNATURE_HEADER, MAX_CLOCK_SKEW};
+pub use self::auth::{ChallengeDto, ProofDto, NONCE_SIZE, generate_nonce, prove_possession, verify_possession};
+pub use self::http::{HttpClient, REJECTED_SIGNATURE_ERROR};
+pub use self::config::NetworkConfig;
 
 mod segregation;
\ No newline at end of file
@@ -1,10 +1,12 @@
 //! Peer-to-Peer networking trait.
 
 use error::*;
+use persistence::prelude::*;
 
 use blockchain::peer::Peer;
-use blockchain::block::{Block, BlockRepository};
+use blockchain::block::Block;
 use blockchain::identity::Identity;
+use blockchain::network::{NetworkConfig, ProofDto};
 
 /// Peer-to-Peer client trait definition.
 pub trait Client {
@@ -15,19 +17,31 @@ pub trait Client {
     /// Returns the `Peer`'s version.
     fn get_peer_version(&self) -> Option<String>;
 
-    /// Register the specified `Identity` on this Peer-to-Peer client.
-    fn register(&self, peer: &Peer) -> LocksidianResult<Peer>;
+    /// Register the specified `Peer` on this Peer-to-Peer client. The request is signed by the
+    /// `Identity` of the peer.
+    fn register(&self, peer: &Peer, identity: &Identity) -> LocksidianResult<Peer>;
+
+    /// Challenge this Peer-to-Peer client to sign the `nonce` of the `registrar` identity, along with
+    /// its own address, using the private key of its identity.
+    fn challenge(&self, nonce: &str, registrar: &str) -> LocksidianResult<ProofDto>;
 
     /// Get the list of all `Peer`s registered on this Peer-to-Peer client.
     fn get_peers(&self) -> LocksidianResult<Vec<Peer>>;
     
-    /// Replicate the specified `Block` to this Peer-to-Peer client.
-    fn replicate(&self, block: &Block, identity: &Identity) -> LocksidianResult<()>;
+    /// Replicate the specified `Block` to this Peer-to-Peer client, along with the hexadecimal
+    /// PEM-encoded public key of its author.
+    fn replicate(&self, block: &Block, identity: &Identity, author_key: &str) -> LocksidianResult<()>;
+    
+    /// Announce the hashes of some blocks to this Peer-to-Peer client, and return the hashes of the
+    /// blocks it lacks.
+    fn announce(&self, hashes: &[String], identity: &Identity) -> LocksidianResult<Vec<String>>;
     
-    /// Propagate the `Block` through a list of `Peer`s.
-    fn propagate(block: &Block, identity: &Identity, peers: Vec<Peer>) -> LocksidianResult<()>;
+    /// Gossip the `Block` to at most `fanout` randomly selected `Peer`s that are not known to have
+    /// it: its hash is announced to them, and the block is replicated to the ones lacking it. The
+    /// successful exchanges are recorded in the peers registry.
+    fn propagate(block: &Block, identity: &Identity, author_key: &str, peers: Vec<Peer>, fanout: usize, connection: &SqliteConnection) -> LocksidianResult<()>;
     
     /// Sync down the blockchain from the provided `Block` hash.
     /// If `None` is specified, sync the blockchain from its `HEAD`.
-    fn sync(&self, hash: Option<String>, repository: &BlockRepository) -> LocksidianResult<()>;
+    fn sync(&self, hash: Option<String>, config: &NetworkConfig, connection: &SqliteConnection) -> LocksidianResult<()>;
 }
\ No newline at end of file
@@ -0,0 +1,233 @@
+//! TLS support of the API server and of the peer client.
+//!
+//! The certificate of a node is tied to its identity: it is issued for the public key of the
+//! node's active identity. A self-signed certificate is generated at first start, and regenerated
+//! whenever the active identity changes. A certificate issued by any other authority can be
+//! provided instead, as long as it is issued for the identity key.
+//!
+//! The peer client does not rely on certificate authorities: the certificate presented by an HTTPS
+//! peer is accepted if, and only if, its public key hash matches the identity of the peer.
+//!
+//! The identity of the network entrypoint is not known before registering onto it, unless it is
+//! pinned at startup (`--entrypoint-identity`). Otherwise, the entrypoint is trusted on first use:
+//! any certificate is accepted from it, and the identity it answers the registration with is proven
+//! by the registration challenge, then stored in the peers registry. An attacker intercepting this
+//! first connection could therefore introduce its own identity as the entrypoint, and the node
+//! would join its network instead: pin the entrypoint identity when its certificate cannot be
+//! trusted on first use.
+
+use error::*;
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+use openssl::ssl::{SslMethod, SslAcceptorBuilder, SslConnectorBuilder, SSL_VERIFY_PEER};
+use openssl::x509::{X509, X509Ref, X509Builder, X509NameBuilder, X509StoreContextRef};
+use openssl::pkey::PKey;
+use openssl::asn1::Asn1Time;
+use openssl::bn::BigNum;
+use openssl::hash::MessageDigest;
+use openssl::error::ErrorStack;
+use hyper_openssl::{OpensslServer, OpensslClient};
+
+use sec::rsa::Rsa;
+use persistence::database_path;
+use blockchain::get_current_timestamp;
+use blockchain::identity::Identity;
+use blockchain::identity::identity_cli::compute_key_hash;
+
+/// Validity, in days, of the self-signed certificates.
+const CERTIFICATE_VALIDITY: u32 = 3650;
+
+/// Returns the scheme matching the TLS mode of a node.
+pub fn scheme(tls: bool) -> &'static str {
+    match tls {
+        true => "https",
+        false => "http"
+    }
+}
+
+/// Split the optional `http://` or `https://` scheme from a node `address`. The `http` scheme is
+/// assumed when it is omitted.
+pub fn split_scheme(address: &str) -> (&'static str, &str) {
+    if address.starts_with("https://") {
+        ("https", &address[8..])
+    } else if address.starts_with("http://") {
+        ("http", &address[7..])
+    } else {
+        ("http", address)
+    }
+}
+
+/// Configure the TLS listener of the API server using the PEM-encoded `cert` and `key` files when
+/// provided, or the self-signed certificate of the node `identity` otherwise.
+pub fn server(identity: &Identity, cert: Option<String>, key: Option<String>) -> LocksidianResult<OpensslServer> {
+    let (certificate, pkey) = match (cert, key) {
+        (Some(cert), Some(key)) => {
+            let certificate = match X509::from_pem(read_file(&cert)?.as_slice()) {
+                Ok(certificate) => certificate,
+                Err(err) => return Err(LocksidianError::from_err(err))
+            };
+
+            if certificate_identity(&certificate)? != identity.hash() {
+                warn!("The certificate {} is not issued for the key of identity {}: the peers will reject it", cert, identity.hash());
+            }
+
+            match PKey::private_key_from_pem(read_file(&key)?.as_slice()) {
+                Ok(pkey) => (certificate, pkey),
+                Err(err) => return Err(LocksidianError::from_err(err))
+            }
+        },
+        (None, None) => {
+            let pkey = match PKey::private_key_from_pem(identity.key().export_private_key()?.as_slice()) {
+                Ok(pkey) => pkey,
+                Err(err) => return Err(LocksidianError::from_err(err))
+            };
+
+            (self_signed_certificate(identity, &pkey)?, pkey)
+        },
+        _ => return Err(LocksidianError::new(String::from("Both the TLS certificate and key files should be provided")))
+    };
+
+    match SslAcceptorBuilder::mozilla_intermediate(SslMethod::tls(), &pkey, &certificate, Vec::<X509>::new()) {
+        Ok(acceptor) => Ok(OpensslServer::from(acceptor.build())),
+        Err(err) => Err(LocksidianError::from_err(err))
+    }
+}
+
+/// Configure the TLS connector of a peer client, only accepting the certificates issued for the
+/// key of the expected peer `identity`, if any.
+pub fn client(identity: Option<String>) -> LocksidianResult<OpensslClient> {
+    let mut connector = match SslConnectorBuilder::new(SslMethod::tls()) {
+        Ok(connector) => connector,
+        Err(err) => return Err(LocksidianError::from_err(err))
+    };
+
+    connector.builder_mut().set_verify_callback(SSL_VERIFY_PEER, move |_, context| verify_peer(identity.as_ref(), context));
+
+    // The peers are addressed by IP and present self-signed certificates: their identity is
+    // verified instead of their host name
+    let mut client = OpensslClient::from(connector.build());
+    client.danger_disable_hostname_verification(true);
+
+    Ok(client)
+}
+
+/// Verify a certificate of the chain presented by a peer: only the peer's own certificate is
+/// checked against its `identity`.
+fn verify_peer(identity: Option<&String>, context: &X509StoreContextRef) -> bool {
+    match (identity, context.error_depth(), context.current_cert()) {
+        (None, _, _) => true,
+        (Some(_), depth, _) if depth > 0 => true,
+        (Some(identity), _, Some(certificate)) => match certificate_identity(certificate) {
+            Ok(hash) => hash == *identity,
+            Err(_) => false
+        },
+        (Some(_), _, None) => false
+    }
+}
+
+/// Returns the hash of the identity whose key the `certificate` is issued for.
+fn certificate_identity(certificate: &X509Ref) -> LocksidianResult<String> {
+    let pem = match certificate.public_key() {
+        Ok(pkey) => match pkey.public_key_to_pem() {
+            Ok(pem) => pem,
+            Err(err) => return Err(LocksidianError::from_err(err))
+        },
+        Err(err) => return Err(LocksidianError::from_err(err))
+    };
+
+    compute_key_hash(&Rsa::from_public_key(pem.as_slice())?)
+}
+
+/// Load the self-signed certificate of the node, stored next to its database, or generate it if
+/// it does not exist yet or was issued for another identity.
+fn self_signed_certificate(identity: &Identity, pkey: &PKey) -> LocksidianResult<X509> {
+    let path = Path::new(&database_path()).with_file_name("locksidian.crt");
+
+    if path.exists() {
+        if let Ok(pem) = read_file(&path) {
+            if let Ok(certificate) = X509::from_pem(pem.as_slice()) {
+                if certificate_identity(&certificate).ok() == Some(identity.hash()) {
+                    return Ok(certificate);
+                }
+            }
+        }
+    }
+
+    info!("Generating the self-signed certificate of identity {}", identity.hash());
+    let certificate = generate_certificate(identity.hash().as_ref(), pkey)?;
+
+    let pem = match certificate.to_pem() {
+        Ok(pem) => pem,
+        Err(err) => return Err(LocksidianError::from_err(err))
+    };
+
+    match File::create(&path).and_then(|mut file| file.write_all(pem.as_slice())) {
+        Ok(_) => Ok(certificate),
+        Err(err) => Err(LocksidianError::from_err(err))
+    }
+}
+
+/// Generate a certificate for the given key, self-signed using the same key. Its common name is
+/// the identity `hash`.
+fn generate_certificate(hash: &str, pkey: &PKey) -> LocksidianResult<X509> {
+    match build_certificate(hash, pkey) {
+        Ok(certificate) => Ok(certificate),
+        Err(err) => Err(LocksidianError::from_err(err))
+    }
+}
+
+fn build_certificate(hash: &str, pkey: &PKey) -> Result<X509, ErrorStack> {
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_text("CN", hash)?;
+    let name = name.build();
+
+    let serial = BigNum::from_u32(get_current_timestamp() as u32)?.to_asn1_integer()?;
+    let not_before = Asn1Time::days_from_now(0)?;
+    let not_after = Asn1Time::days_from_now(CERTIFICATE_VALIDITY)?;
+
+    let mut builder = X509Builder::new()?;
+    builder.set_version(2)?;
+    builder.set_serial_number(&serial)?;
+    builder.set_subject_name(&name)?;
+    builder.set_issuer_name(&name)?;
+    builder.set_pubkey(pkey)?;
+    builder.set_not_before(&not_before)?;
+    builder.set_not_after(&not_after)?;
+    builder.sign(pkey, MessageDigest::sha256())?;
+
+    Ok(builder.build())
+}
+
+fn read_file<P: AsRef<Path>>(path: P) -> LocksidianResult<Vec<u8>> {
+    let mut content = Vec::new();
+
+    match File::open(path).and_then(|mut file| file.read_to_end(&mut content)) {
+        Ok(_) => Ok(content),
+        Err(err) => Err(LocksidianError::from_err(err))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn generated_certificates_should_be_tied_to_the_identity_key() {
+        let rsa = Rsa::generate(2048).unwrap();
+        let hash = compute_key_hash(&rsa).unwrap();
+        let pkey = PKey::private_key_from_pem(rsa.export_private_key().unwrap().as_slice()).unwrap();
+
+        let certificate = generate_certificate(hash.as_ref(), &pkey).unwrap();
+        assert_eq!(certificate_identity(&certificate).unwrap(), hash);
+    }
+
+    #[test]
+    fn addresses_should_default_to_http() {
+        assert_eq!(split_scheme("127.0.0.1:8080"), ("http", "127.0.0.1:8080"));
+        assert_eq!(split_scheme("http://127.0.0.1:8080"), ("http", "127.0.0.1:8080"));
+        assert_eq!(split_scheme("https://127.0.0.1:8080"), ("https", "127.0.0.1:8080"));
+    }
+}
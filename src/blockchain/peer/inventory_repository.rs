@@ -0,0 +1,83 @@
+//! Inventory Repository module.
+//!
+//! Keep track of the blocks known by each peer: the blocks it announced or replicated to the node,
+//! and the blocks the node announced or replicated to it. A block is never announced to a peer
+//! known to have it.
+
+use persistence::prelude::*;
+
+table! {
+    inventory(id) {
+        id -> VarChar,
+        hash -> VarChar,
+        peer -> VarChar,
+        seen_at -> Integer,
+    }
+}
+
+/// Block known by a peer.
+#[derive(
+    Debug, Clone,
+    Queryable, Insertable, AsChangeset
+)]
+#[table_name = "inventory"]
+pub struct InventoryEntity {
+    pub id: String,
+    pub hash: String,
+    pub peer: String,
+    pub seen_at: i32
+}
+
+impl InventoryEntity {
+
+    /// Instantiate a new `InventoryEntity` recording that the block identified by its `hash` is
+    /// known by the `peer` identity since `seen_at`.
+    pub fn new(hash: &str, peer: &str, seen_at: u64) -> Self {
+        InventoryEntity {
+            id: format!("{}:{}", peer, hash),
+            hash: String::from(hash),
+            peer: String::from(peer),
+            seen_at: seen_at as i32
+        }
+    }
+}
+
+pub struct InventoryRepository<'pool> {
+    connection: &'pool SqliteConnection
+}
+
+impl<'pool> InventoryRepository<'pool> {
+
+    /// Instantiate a new `InventoryRepository` whose lifetime is bound to its pooled connection.
+    pub fn new(connection: &SqliteConnection) -> InventoryRepository {
+        InventoryRepository {
+            connection: connection
+        }
+    }
+
+    /// Record that the block identified by its `hash` is known by the `peer` identity.
+    pub fn record(&self, hash: &str, peer: &str) -> LocksidianResult<()> {
+        let entity = InventoryEntity::new(hash, peer, ::blockchain::get_current_timestamp());
+
+        match self.get(&entity.id) {
+            Some(_) => Ok(()),
+            None => self.save(&entity).map(|_| ())
+        }
+    }
+
+    /// Is the block identified by its `hash` known by the `peer` identity?
+    pub fn knows(&self, hash: &str, peer: &str) -> bool {
+        self.get(&format!("{}:{}", peer, hash)).is_some()
+    }
+
+    /// Forget the blocks recorded before the `before` timestamp, and return the number of deleted
+    /// rows. By then, these blocks have been gossiped through the whole network.
+    pub fn prune(&self, before: u64) -> LocksidianResult<usize> {
+        match ::diesel::delete(inventory::table.filter(inventory::seen_at.lt(before as i32))).execute(self.connection) {
+            Ok(deleted_rows) => Ok(deleted_rows),
+            Err(err) => Err(LocksidianError::from_err(err))
+        }
+    }
+}
+
+crud_repository!(inventory, InventoryEntity, String, id, InventoryRepository<'pool>);
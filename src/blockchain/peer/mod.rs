@@ -3,8 +3,11 @@
 mod peer_dto;
 mod peer_domain;
 mod peer_repository;
+mod inventory_repository;
 pub mod peer_cli;
+pub mod peer_monitor;
 
 pub use self::peer_dto::PeerDto;
-pub use self::peer_domain::Peer;
-pub use self::peer_repository::{PeerEntity, PeerRepository};
\ No newline at end of file
+pub use self::peer_domain::{Peer, Liveness};
+pub use self::peer_repository::{PeerEntity, PeerRepository};
+pub use self::inventory_repository::{InventoryEntity, InventoryRepository};
\ No newline at end of file
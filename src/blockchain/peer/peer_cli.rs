@@ -8,10 +8,10 @@ use blockchain::network::*;
 use blockchain::peer::*;
 use blockchain::identity::identity_cli::get_active_identity;
 
-/// Register a batch of `Peer`s into the registry.
-pub fn register_batch(peers: &mut Vec<Peer>, repository: &PeerRepository, current_address: &str) -> LocksidianResult<()> {
+/// Register a batch of `Peer`s into the registry, as the `registrar` identity.
+pub fn register_batch(peers: &mut Vec<Peer>, repository: &PeerRepository, current_address: &str, registrar: &str) -> LocksidianResult<()> {
     for peer in peers.iter_mut() {
-		match register(peer, &repository, current_address) {
+		match register(peer, &repository, current_address, registrar) {
 			Ok(_) => (),
 			Err(_) => ()
 		}
@@ -20,19 +20,21 @@ pub fn register_batch(peers: &mut Vec<Peer>, repository: &PeerRepository, curren
 	Ok(())
 }
 
-/// Register a `Peer` into the registry.
-pub fn register(peer: &mut Peer, repository: &PeerRepository, current_address: &str) -> LocksidianResult<()> {
+/// Register a `Peer` into the registry, as the `registrar` identity.
+pub fn register(peer: &mut Peer, repository: &PeerRepository, current_address: &str, registrar: &str) -> LocksidianResult<()> {
 	info!("Trying to register peer {} ({})...", peer.identity(), peer.address());
     check_peer_version(&peer)?;
     
     match peer.address().eq(current_address) {
         true => Ok(()),
         false => {
+            check_key_possession(&peer, registrar)?;
+
             peer.set_last_recv(get_current_timestamp());
             peer.set_last_sent(get_current_timestamp());
 
             match repository.get(&peer.identity()) {
-                Some(mut entity) => update_existing_peer(&mut entity, &repository),
+                Some(mut entity) => update_existing_peer(&mut entity, &peer, &repository),
                 None => register_new_peer(&peer, &repository)
             }
         }
@@ -50,10 +52,25 @@ pub fn check_peer_version(peer: &Peer) -> LocksidianResult<()> {
     }
 }
 
-/// Update an existing `PeerEntity`.
-fn update_existing_peer(entity: &mut PeerEntity, repository: &PeerRepository) -> LocksidianResult<()> {
+/// Challenge the node at the peer address to prove that it holds the private key of the peer
+/// identity, by signing a random nonce sent by the `registrar` identity along with this address.
+pub fn check_key_possession(peer: &Peer, registrar: &str) -> LocksidianResult<()> {
+    let nonce = generate_nonce()?;
+    let proof = HttpClient::from_peer(&peer).challenge(nonce.as_ref(), registrar)?;
+
+    match verify_possession(nonce.as_ref(), peer.address().as_ref(), registrar, &proof, peer.key()) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(LocksidianError::new(format!("Peer {} at {} failed to prove its identity: {}", peer.identity(), peer.address(), err.description())))
+    }
+}
+
+/// Update an existing `PeerEntity`. The scheme of a peer changes when it is restarted with or
+/// without TLS.
+fn update_existing_peer(entity: &mut PeerEntity, peer: &Peer, repository: &PeerRepository) -> LocksidianResult<()> {
     entity.last_recv = get_current_timestamp() as i32;
     entity.last_sent = get_current_timestamp() as i32;
+    entity.active = true;
+    entity.scheme = peer.scheme();
 
     match repository.update(&entity) {
         Ok(1) => Ok(()),
@@ -62,6 +79,45 @@ fn update_existing_peer(entity: &mut PeerEntity, repository: &PeerRepository) ->
     }
 }
 
+/// Record a successful exchange initiated with the peer identified by its `identity`: data were
+/// sent to it, and its response was received. A demoted peer is promoted back.
+pub fn record_exchange(identity: &str, repository: &PeerRepository) -> LocksidianResult<()> {
+    record(identity, true, repository)
+}
+
+/// Record the reception of a request sent by the peer identified by its `identity`. A demoted peer
+/// is promoted back.
+pub fn record_received(identity: &str, repository: &PeerRepository) -> LocksidianResult<()> {
+    record(identity, false, repository)
+}
+
+/// Update the exchange timestamps of a registered peer. Nothing is done for an unknown peer.
+fn record(identity: &str, sent: bool, repository: &PeerRepository) -> LocksidianResult<()> {
+    match repository.get(&String::from(identity)) {
+        Some(mut entity) => {
+            let now = get_current_timestamp() as i32;
+
+            if sent {
+                entity.last_sent = now;
+            }
+            entity.last_recv = now;
+            entity.active = true;
+
+            repository.update(&entity).map(|_| ())
+        },
+        None => Ok(())
+    }
+}
+
+/// Load the peers that have not been demoted for being silent past the timeout.
+pub fn active_peers(repository: &PeerRepository) -> Vec<Peer> {
+    repository.get_active().unwrap_or(Vec::new()).iter()
+        .map(|entity| Peer::from_entity(entity))
+        .filter(|peer| peer.is_ok())
+        .map(|peer| peer.unwrap())
+        .collect()
+}
+
 /// Insert a new `Peer` into the registry.
 fn register_new_peer(peer: &Peer, repository: &PeerRepository) -> LocksidianResult<()> {
     match PeerEntity::new(&peer) {
@@ -74,12 +130,12 @@ fn register_new_peer(peer: &Peer, repository: &PeerRepository) -> LocksidianResu
     }
 }
 
-/// Create a `Peer` structure based on the current `Identity` and address.
-pub fn current_identity_as_peer(connection: &SqliteConnection, address: String) -> LocksidianResult<Peer> {
+/// Create a `Peer` structure based on the current `Identity`, address and scheme.
+pub fn current_identity_as_peer(connection: &SqliteConnection, address: String, scheme: &str) -> LocksidianResult<Peer> {
     match get_active_identity(&*connection) {
 		Ok(identity) => {
             let key = identity.public_key_to_hex()?;
-            Peer::new(key, address)
+            Peer::new(key, address)?.with_scheme(scheme)
         },
 		Err(err) => Err(LocksidianError::from_err(err))
 	}
@@ -7,13 +7,49 @@ use sec::hex::*;
 use blockchain::peer::PeerEntity;
 use blockchain::identity::identity_cli::compute_key_hash;
 
+/// Liveness of a `Peer`, assessed from the last time data were received from it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Liveness {
+
+    /// The peer was heard from recently.
+    Alive,
+
+    /// The peer has been silent past the timeout: it is demoted, and no block is propagated to it.
+    Down,
+
+    /// The peer has been silent past the eviction delay: it is removed from the registry.
+    Evicted
+}
+
+impl Liveness {
+
+    /// Assess the liveness of a peer last heard from at `last_recv`, given the `timeout` and the
+    /// `eviction` delays (in seconds).
+    pub fn assess(last_recv: u64, now: u64, timeout: u64, eviction: u64) -> Self {
+        let silence = match now > last_recv {
+            true => now - last_recv,
+            false => 0
+        };
+
+        if silence > eviction {
+            Liveness::Evicted
+        } else if silence > timeout {
+            Liveness::Down
+        } else {
+            Liveness::Alive
+        }
+    }
+}
+
 pub struct Peer {
     identity: String,
     key: Rsa,
     address: String,
+    scheme: String,
 
     last_sent: u64,
-    last_recv: u64
+    last_recv: u64,
+    active: bool
 }
 
 impl Peer {
@@ -28,8 +64,10 @@ impl Peer {
                     identity: compute_key_hash(&rsa)?,
                     key: rsa,
                     address: address,
+                    scheme: String::from("http"),
                     last_sent: 0,
-                    last_recv: 0
+                    last_recv: 0,
+                    active: true
                 })
             },
             Err(err) => Err(LocksidianError::from_err(err))
@@ -38,9 +76,10 @@ impl Peer {
 
     /// Instantiate a new `Peer` from the given `PeerEntity`, consuming the entity instance.
     pub fn from_entity(entity: &PeerEntity) -> LocksidianResult<Self> {
-        let mut peer = Peer::new(entity.key.clone(), entity.address.clone())?;
+        let mut peer = Peer::new(entity.key.clone(), entity.address.clone())?.with_scheme(entity.scheme.as_ref())?;
         peer.last_sent = entity.last_sent as u64;
         peer.last_recv = entity.last_recv as u64;
+        peer.active = entity.active;
 
         Ok(peer)
     }
@@ -67,6 +106,22 @@ impl Peer {
         self.address.clone()
     }
 
+    /// `scheme` getter: either `http` or `https`.
+    pub fn scheme(&self) -> String {
+        self.scheme.clone()
+    }
+
+    /// Set the scheme of the peer address, `http` by default.
+    pub fn with_scheme(mut self, scheme: &str) -> LocksidianResult<Self> {
+        match scheme {
+            "http" | "https" => {
+                self.scheme = String::from(scheme);
+                Ok(self)
+            },
+            _ => Err(LocksidianError::new(format!("Invalid scheme {}, expected http or https", scheme)))
+        }
+    }
+
     /// `last_sent` getter.
     pub fn last_sent(&self) -> u64 {
         self.last_sent
@@ -86,4 +141,27 @@ impl Peer {
     pub fn set_last_recv(&mut self, timestamp: u64) {
         self.last_recv = timestamp;
     }
-}
\ No newline at end of file
+
+    /// Returns `true` unless the peer has been demoted for being silent past the timeout.
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// `active` setter.
+    pub fn set_active(&mut self, active: bool) {
+        self.active = active;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn silent_peers_should_be_demoted_then_evicted() {
+        assert_eq!(Liveness::assess(1000, 1100, 300, 3600), Liveness::Alive);
+        assert_eq!(Liveness::assess(1000, 1400, 300, 3600), Liveness::Down);
+        assert_eq!(Liveness::assess(1000, 5000, 300, 3600), Liveness::Evicted);
+        assert_eq!(Liveness::assess(2000, 1000, 300, 3600), Liveness::Alive);
+    }
+}
@@ -9,7 +9,15 @@ use blockchain::peer::Peer;
 )]
 pub struct PeerDto {
     key: String,
-    address: String
+    address: String,
+
+    /// Scheme of the peer address, `http` when omitted by the nodes predating HTTPS support.
+    #[serde(default = "default_scheme")]
+    scheme: String
+}
+
+fn default_scheme() -> String {
+    String::from("http")
 }
 
 impl PeerDto {
@@ -18,12 +26,13 @@ impl PeerDto {
     pub fn new(peer: &Peer) -> LocksidianResult<Self> {
         Ok(PeerDto {
             key: peer.key_to_hex()?,
-            address: peer.address()
+            address: peer.address(),
+            scheme: peer.scheme()
         })
     }
 
     /// Instantiate a new `Peer` based on this DTO instance.
     pub fn to_peer(&self) -> LocksidianResult<Peer> {
-        Peer::new(self.key.clone(), self.address.clone())
+        Peer::new(self.key.clone(), self.address.clone())?.with_scheme(self.scheme.as_ref())
     }
 }
\ No newline at end of file
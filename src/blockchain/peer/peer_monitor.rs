@@ -0,0 +1,158 @@
+//! Background monitoring of the liveness of the peers.
+//!
+//! Every registered peer is pinged on a regular schedule, by announcing it an empty inventory: the
+//! ping is signed, so that a peer which evicted this node (e.g. during a network partition longer
+//! than its eviction delay) rejects it. This node then registers again onto the peer. A successful
+//! ping (as any other successful exchange with the peer) updates its `last_sent` and `last_recv`
+//! timestamps, while a peer silent past the timeout is demoted: the blocks are no longer propagated
+//! to it, and it is no longer advertised to the other nodes. A demoted peer is promoted back as soon
+//! as it responds again, and is evicted from the registry once it has been silent past the eviction
+//! delay.
+//!
+//! The monitor also forgets the blocks known by each peer once they have been gossiped.
+
+use persistence::prelude::*;
+
+use std::thread;
+use std::time::Duration;
+
+use blockchain::get_current_timestamp;
+use blockchain::network::{Client, HttpClient, REJECTED_SIGNATURE_ERROR};
+use blockchain::peer::{Peer, PeerEntity, PeerRepository, InventoryRepository, Liveness};
+use blockchain::peer::peer_cli;
+use blockchain::identity::Identity;
+use blockchain::identity::identity_cli::{get_active_identity, remember_author_key};
+
+/// Delay, in seconds, between two pings of the peers.
+pub const HEARTBEAT_INTERVAL: u64 = 30;
+
+/// Delay, in seconds, after which an unanswered ping is given up on.
+const PING_TIMEOUT: u64 = 5;
+
+/// Default delay, in seconds, after which a silent peer is demoted.
+pub const DEFAULT_PEER_TIMEOUT: u64 = 180;
+
+/// Default delay, in seconds, after which a silent peer is evicted from the registry.
+pub const DEFAULT_PEER_EVICTION: u64 = 86400;
+
+/// Delay, in seconds, during which the blocks known by each peer are remembered.
+const INVENTORY_RETENTION: u64 = 3600;
+
+/// Start the thread pinging the peers every `HEARTBEAT_INTERVAL` seconds. The peers silent for
+/// more than `timeout` seconds are demoted, and evicted after `eviction` seconds. This node is
+/// registered again, at its `address` and using its `scheme`, onto the peers which forgot it.
+pub fn start(timeout: u64, eviction: u64, address: String, scheme: &'static str) -> LocksidianResult<()> {
+    check_delays(timeout, eviction)?;
+
+    match thread::Builder::new().name(String::from("peer-monitor")).spawn(move || monitor(timeout, eviction, address, scheme)) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(LocksidianError::from_err(err))
+    }
+}
+
+/// A peer must be demoted before being evicted: the eviction delay cannot be shorter than the
+/// timeout.
+fn check_delays(timeout: u64, eviction: u64) -> LocksidianResult<()> {
+    match eviction >= timeout {
+        true => Ok(()),
+        false => Err(LocksidianError::new(format!("The peer eviction delay ({}s) cannot be shorter than the peer timeout ({}s)", eviction, timeout)))
+    }
+}
+
+/// Monitoring thread loop.
+fn monitor(timeout: u64, eviction: u64, address: String, scheme: &'static str) {
+    let connection = match get_connection(database_path()) {
+        Ok(connection) => connection,
+        Err(err) => {
+            error!("Unable to start the peer monitor: {}", err.description());
+            return;
+        }
+    };
+
+    loop {
+        thread::sleep(Duration::from_secs(HEARTBEAT_INTERVAL));
+
+        // The active identity is reloaded, as it may have been changed since the previous heartbeat
+        let (identity, current) = match get_active_identity(&connection).and_then(|identity| {
+            peer_cli::current_identity_as_peer(&connection, address.clone(), scheme).map(|current| (identity, current))
+        }) {
+            Ok(node) => node,
+            Err(err) => {
+                warn!("Unable to load the identity of the node, the peers are not pinged: {}", err.description());
+                continue;
+            }
+        };
+
+        let repository = PeerRepository::new(&connection);
+        for entity in repository.get_all().unwrap_or(Vec::new()) {
+            match heartbeat(entity, &identity, &current, timeout, eviction, &repository, &connection) {
+                Ok(_) => (),
+                Err(err) => warn!("Unable to update the liveness of a peer: {}", err.description())
+            }
+        }
+
+        let before = get_current_timestamp().saturating_sub(INVENTORY_RETENTION);
+        match InventoryRepository::new(&connection).prune(before) {
+            Ok(_) => (),
+            Err(err) => warn!("Unable to prune the inventory of the peers: {}", err.description())
+        }
+    }
+}
+
+/// Ping a single peer as the node `identity`, then record its response or demote (or evict) it if
+/// it has been silent for too long. The `current` node is registered again onto a peer rejecting
+/// the ping.
+fn heartbeat(entity: PeerEntity, identity: &Identity, current: &Peer, timeout: u64, eviction: u64, repository: &PeerRepository, connection: &SqliteConnection) -> LocksidianResult<()> {
+    let mut peer = Peer::from_entity(&entity)?;
+    let client = HttpClient::with_timeout(&peer, Duration::from_secs(PING_TIMEOUT));
+
+    let responded = match client.announce(&[], identity) {
+        Ok(_) => true,
+        Err(ref err) if err.description() == REJECTED_SIGNATURE_ERROR => {
+            info!("Peer {} ({}) rejected the ping, registering again onto it", peer.identity(), peer.address());
+
+            // The peer challenges this node during the registration: the ping timeout is too short
+            HttpClient::from_peer(&peer).register(current, identity)?;
+            true
+        },
+        Err(_) => false
+    };
+
+    if responded {
+        if !peer.is_active() {
+            info!("Peer {} ({}) is up again", peer.identity(), peer.address());
+        }
+
+        return peer_cli::record_exchange(peer.identity().as_ref(), &repository);
+    }
+
+    match Liveness::assess(peer.last_recv(), get_current_timestamp(), timeout, eviction) {
+        Liveness::Alive => Ok(()),
+        Liveness::Down if peer.is_active() => {
+            info!("Demoting peer {} ({}): no response since {}", peer.identity(), peer.address(), peer.last_recv());
+            peer.set_active(false);
+
+            repository.update(&PeerEntity::new(&peer)?).map(|_| ())
+        },
+        Liveness::Down => Ok(()),
+        Liveness::Evicted => {
+            info!("Evicting peer {} ({}): no response since {}", peer.identity(), peer.address(), peer.last_recv());
+
+            // The blocks authored by the peer must remain verifiable once it is gone
+            remember_author_key(peer.key(), &connection)?;
+            repository.delete(&entity).map(|_| ())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn eviction_should_not_precede_the_timeout() {
+        assert!(check_delays(DEFAULT_PEER_TIMEOUT, DEFAULT_PEER_EVICTION).is_ok());
+        assert!(check_delays(60, 60).is_ok());
+        assert!(check_delays(60, 30).is_err());
+    }
+}
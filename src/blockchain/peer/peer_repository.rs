@@ -10,6 +10,8 @@ table! {
         address -> VarChar,
         last_sent -> Integer,
         last_recv -> Integer,
+        active -> Bool,
+        scheme -> VarChar,
     }
 }
 
@@ -24,7 +26,10 @@ pub struct PeerEntity {
     pub address: String,
 
     pub last_sent: i32,
-    pub last_recv: i32
+    pub last_recv: i32,
+    pub active: bool,
+
+    pub scheme: String
 }
 
 impl PeerEntity {
@@ -37,7 +42,10 @@ impl PeerEntity {
             address: peer.address(),
             
             last_sent: peer.last_sent() as i32,
-            last_recv: peer.last_recv() as i32
+            last_recv: peer.last_recv() as i32,
+            active: peer.is_active(),
+
+            scheme: peer.scheme()
         })
     }
 }
@@ -54,6 +62,14 @@ impl<'pool> PeerRepository<'pool> {
             connection: connection
         }
     }
+
+    /// Select the peers that have not been demoted for being silent past the timeout.
+    pub fn get_active(&self) -> Option<Vec<PeerEntity>> {
+        match peers::table.filter(peers::active.eq(true)).load(self.connection) {
+            Ok(entities) => Some(entities),
+            Err(_) => None
+        }
+    }
 }
 
 crud_repository!(peers, PeerEntity, String, identity, PeerRepository<'pool>);
\ No newline at end of file
@@ -0,0 +1,14 @@
+//! Proof-of-existence receipts.
+//!
+//! A receipt is a self-contained JSON document proving that a document is stored in the main chain
+//! of a node: it holds the header of the block storing the document, the public key of its author,
+//! the inclusion proof of the document and the headers of the blocks built on top of it, all signed
+//! by the identity of the node issuing it. It can be verified offline, without any access to the
+//! network.
+
+mod receipt_domain;
+mod receipt_dto;
+pub mod receipt_cli;
+
+pub use self::receipt_domain::Receipt;
+pub use self::receipt_dto::{ReceiptDto, ReceiptContentDto, RECEIPT_VERSION};
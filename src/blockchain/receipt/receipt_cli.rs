@@ -0,0 +1,31 @@
+//! Receipt command line interface.
+
+use error::*;
+
+use std::fs::File;
+use std::io::Read;
+
+use blockchain::network::NetworkConfig;
+use blockchain::receipt::{Receipt, ReceiptDto};
+
+/// Verify offline the receipt stored in the JSON file located at `path` against the network `config`
+/// and the comma-separated `issuers` trusted by the verifier, and return a summary of what it proves.
+pub fn verify_receipt(path: String, config: NetworkConfig, issuers: Option<String>) -> LocksidianResult<String> {
+    let trusted_issuers: Vec<String> = issuers.unwrap_or(String::new()).split(',')
+        .map(|hash| String::from(hash.trim()))
+        .filter(|hash| !hash.is_empty())
+        .collect();
+
+    let mut json = String::new();
+    match File::open(&path).and_then(|mut file| file.read_to_string(&mut json)) {
+        Ok(_) => (),
+        Err(err) => return Err(LocksidianError::new(format!("Unable to read the receipt file {}: {}", path, err)))
+    };
+
+    let dto = match ::serde_json::from_str::<ReceiptDto>(json.as_ref()) {
+        Ok(dto) => dto,
+        Err(err) => return Err(LocksidianError::new(format!("Invalid receipt: {}", err)))
+    };
+
+    Receipt::from_dto(dto)?.verify(&config, &trusted_issuers)
+}
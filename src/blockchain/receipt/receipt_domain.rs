@@ -0,0 +1,362 @@
+//! Receipt domain structure.
+
+use error::*;
+
+use sec::sha::sha512;
+use sec::hex::*;
+use sec::rsa::Rsa;
+
+use persistence::prelude::*;
+
+use blockchain::get_current_timestamp;
+use blockchain::block::{Block, BlockEntity, BlockHeaderDto, BlockRepository};
+use blockchain::consensus::{ConsensusEngine, ConsensusKind};
+use blockchain::document::DocumentProofDto;
+use blockchain::identity::Identity;
+use blockchain::identity::identity_cli::{compute_key_hash, get_identity_key};
+use blockchain::network::NetworkConfig;
+use blockchain::receipt::{ReceiptDto, ReceiptContentDto, RECEIPT_VERSION};
+
+/// Proof-of-existence receipt of a block, signed by the node that issued it.
+pub struct Receipt {
+    content: ReceiptContentDto,
+    signature: Vec<u8>
+}
+
+impl Receipt {
+
+    /// Issue the receipt of the main chain block identified by `hash`, signed by the `issuer`
+    /// identity. Returns `None` if the block is unknown.
+    ///
+    /// The `document` checksum selects the document whose inclusion proof is part of the receipt.
+    /// It may be omitted for a single document block.
+    pub fn issue(hash: &str, document: Option<String>, issuer: &Identity, config: &NetworkConfig, connection: &SqliteConnection) -> LocksidianResult<Option<Self>> {
+        let repository = BlockRepository::new(&connection);
+
+        let entity = match repository.get(&String::from(hash)) {
+            Some(entity) => entity,
+            None => return Ok(None)
+        };
+
+        if entity.orphan {
+            return Err(LocksidianError::new(format!("Block {} is not part of the main chain", hash)));
+        }
+
+        let headers = Receipt::following_headers(&entity, &repository)?;
+        let block = Block::from_entity(entity)?;
+
+        let author_key = get_identity_key(block.author().as_ref(), None, &connection)?;
+        let data = match block.is_data_hash_signed(&author_key)? {
+            true => None,
+            false => Some(block.data())
+        };
+
+        let content = ReceiptContentDto {
+            version: RECEIPT_VERSION,
+            consensus: config.consensus.engine.clone(),
+            block: BlockHeaderDto::new(&block),
+            author_key: author_key.export_public_key()?.to_hex(),
+            data: data,
+            document: Receipt::document_proof(&block, document, &repository)?,
+            headers: headers,
+            issuer: issuer.hash(),
+            issuer_key: issuer.public_key_to_hex()?,
+            issued_at: get_current_timestamp()
+        };
+
+        let signature = issuer.key().sign(Receipt::message(&content)?.as_bytes())?;
+
+        Ok(Some(Receipt {
+            content: content,
+            signature: signature
+        }))
+    }
+
+    /// Adapt a `ReceiptDto` into a `Receipt` structure, consuming its instance.
+    pub fn from_dto(dto: ReceiptDto) -> LocksidianResult<Self> {
+        match dto.signature.from_hex() {
+            Ok(signature) => Ok(Receipt {
+                content: dto.receipt,
+                signature: signature
+            }),
+            Err(err) => Err(LocksidianError::from_err(err))
+        }
+    }
+
+    /// Convert the `Receipt` into a `ReceiptDto`.
+    pub fn to_dto(&self) -> ReceiptDto {
+        ReceiptDto {
+            receipt: self.content.clone(),
+            signature: self.signature.to_hex()
+        }
+    }
+
+    /// Verify the receipt offline and return a summary of what it proves, namely:
+    ///
+    /// - The receipt issuer against the `trusted_issuers` of the verifier;
+    /// - The receipt consensus against the network `config` of the verifier;
+    /// - The receipt signature against the public key of its issuer;
+    /// - The block signature against the public key of its author;
+    /// - The block hash and Proof of Work, or its author against the allow-list of the verifier;
+    /// - The inclusion proof of the document against the block header;
+    /// - The linkage, hash and consensus rules of the headers built on top of the block.
+    ///
+    /// Nothing in the receipt itself is trusted: as the Proof of Work of the headers is only checked
+    /// against the target of their base difficulty, the issuer of a receipt of a Proof of Work network
+    /// must be one of the `trusted_issuers`.
+    pub fn verify(&self, config: &NetworkConfig, trusted_issuers: &[String]) -> LocksidianResult<String> {
+        let content = &self.content;
+
+        Receipt::check_issuer(content.issuer.as_ref(), &config.consensus.engine, trusted_issuers)?;
+        if content.consensus != config.consensus.engine {
+            return Err(LocksidianError::new(String::from("The receipt consensus does not match the network configuration")));
+        }
+
+        let engine = config.consensus_engine()?;
+        let issuer_key = Receipt::identity_key(content.issuer.as_ref(), content.issuer_key.as_ref())?;
+        match issuer_key.verify_signature(Receipt::message(&content)?.as_bytes(), &self.signature)? {
+            true => (),
+            false => return Err(LocksidianError::new(format!("Receipt signature does not match the issuer {}", content.issuer)))
+        };
+
+        let data = content.data.clone().unwrap_or(String::new());
+        if content.data.is_some() && sha512(data.as_bytes()) != content.block.data_hash {
+            return Err(LocksidianError::new(String::from("Block data_hash does not match the recomputed data checksum")));
+        }
+
+        let block = Block::from_header(&content.block, data)?;
+        let author_key = Receipt::identity_key(block.author().as_ref(), content.author_key.as_ref())?;
+        block.check_signature(&author_key, &config)?;
+        block.check_header(&content.consensus)?;
+        Receipt::check_authority(&block, &content.consensus, &*engine)?;
+
+        let document = match content.document {
+            Some(ref proof) => Receipt::check_document_proof(&block, proof)?,
+            None => block.data_hash()
+        };
+
+        let mut previous = Block::from_header(&content.block, String::new())?;
+        for header in content.headers.iter() {
+            let next = Block::from_header(header, String::new())?;
+            if next.previous() != previous.hash() || next.height() != previous.height() + 1 {
+                return Err(LocksidianError::new(format!("Block {} is not built on top of block {}", next.hash(), previous.hash())));
+            }
+
+            next.check_header(&content.consensus)?;
+            Receipt::check_authority(&next, &content.consensus, &*engine)?;
+            previous = next;
+        }
+
+        Ok(format!(
+            "Document {} is stored in block {} at height {}, signed by {}, followed by {} block(s) up to block {}. Receipt issued by {} at {}.",
+            document, block.hash(), block.height(), block.author(), content.headers.len(), previous.hash(), content.issuer, content.issued_at
+        ))
+    }
+
+    /// Returns an error if the receipt `issuer` is not trusted by the verifier. Any issuer is accepted
+    /// on a Proof of Authority network when no issuer is specified, as the block authors are then
+    /// checked against the allow-list of the verifier.
+    fn check_issuer(issuer: &str, consensus: &ConsensusKind, trusted_issuers: &[String]) -> LocksidianResult<()> {
+        match (trusted_issuers.is_empty(), consensus) {
+            (true, &ConsensusKind::ProofOfAuthority) => Ok(()),
+            (true, &ConsensusKind::ProofOfWork) => Err(LocksidianError::new(String::from(
+                "The trusted issuers of a proof-of-work receipt should be specified"
+            ))),
+            (false, _) => match trusted_issuers.iter().any(|trusted| trusted == issuer) {
+                true => Ok(()),
+                false => Err(LocksidianError::new(format!("The receipt issuer {} is not trusted", issuer)))
+            }
+        }
+    }
+
+    /// Returns an error if the author of the block header is not allowed by the Proof of Authority
+    /// `engine` of the verifier.
+    fn check_authority(block: &Block, consensus: &ConsensusKind, engine: &ConsensusEngine) -> LocksidianResult<()> {
+        match *consensus {
+            ConsensusKind::ProofOfAuthority => engine.verify(&block),
+            ConsensusKind::ProofOfWork => Ok(())
+        }
+    }
+
+    /// Returns the headers of the main chain blocks built on top of the given block, up to the
+    /// `HEAD` block.
+    fn following_headers(entity: &BlockEntity, repository: &BlockRepository) -> LocksidianResult<Vec<BlockHeaderDto>> {
+        let mut headers = Vec::new();
+        let mut next = entity.next.clone();
+
+        while !next.is_empty() {
+            match repository.get(&next) {
+                Some(entity) => {
+                    next = entity.next.clone();
+                    headers.push(BlockHeaderDto::new(&Block::from_entity(entity)?));
+                },
+                None => return Err(LocksidianError::new(format!("Block {} is missing from the registry", next)))
+            }
+        }
+
+        Ok(headers)
+    }
+
+    /// Build the inclusion proof of the `document` stored in the given `Block`. The proof of a single
+    /// document block is built even if no `document` is requested.
+    fn document_proof(block: &Block, document: Option<String>, repository: &BlockRepository) -> LocksidianResult<Option<DocumentProofDto>> {
+        let data_hash = match document {
+            Some(data_hash) => data_hash,
+            None => match block.merkle_root().is_empty() {
+                true => return Ok(Some(DocumentProofDto::new(&block, 0)?)),
+                false => return Ok(None)
+            }
+        };
+
+        match repository.locate_document(data_hash.as_ref()) {
+            Some((ref entity, position)) if entity.hash == block.hash() => Ok(Some(DocumentProofDto::new(&block, position)?)),
+            _ => Err(LocksidianError::new(format!("Document {} is not stored in block {}", data_hash, block.hash())))
+        }
+    }
+
+    /// Returns an error if the inclusion `proof` does not lead to the documents commitment of the
+    /// block header, otherwise the checksum of the proven document.
+    fn check_document_proof(block: &Block, proof: &DocumentProofDto) -> LocksidianResult<String> {
+        match proof.is_committed_in(&block) {
+            true => Ok(proof.data_hash.clone()),
+            false => Err(LocksidianError::new(format!("Document {} inclusion proof does not match block {}", proof.data_hash, block.hash())))
+        }
+    }
+
+    /// Parse the hexadecimal PEM-encoded public `key` of the identity `hash`.
+    fn identity_key(hash: &str, key: &str) -> LocksidianResult<Rsa> {
+        let rsa = match key.from_hex() {
+            Ok(pem) => Rsa::from_public_key(pem.as_slice())?,
+            Err(err) => return Err(LocksidianError::from_err(err))
+        };
+
+        match compute_key_hash(&rsa)? == hash {
+            true => Ok(rsa),
+            false => Err(LocksidianError::new(format!("The provided public key does not belong to identity {}", hash)))
+        }
+    }
+
+    /// Returns the signed message of the receipt: its JSON serialized content.
+    fn message(content: &ReceiptContentDto) -> LocksidianResult<String> {
+        match ::serde_json::to_string(content) {
+            Ok(json) => Ok(json),
+            Err(err) => Err(LocksidianError::from_err(err))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use blockchain::consensus::{ConsensusConfig, ProofOfAuthorityEngine};
+
+    fn header(author: &Identity, data: &str, previous: &str, height: u64) -> BlockHeaderDto {
+        let data_hash = sha512(data.as_bytes());
+        let mut header = BlockHeaderDto {
+            data_hash: data_hash.clone(),
+            signature: author.key().sign(Block::signed_message(data_hash.as_ref(), "").as_bytes()).unwrap().to_hex(),
+            timestamp: height,
+            nonce: 0,
+            previous: String::from(previous),
+            base_difficulty: 512,
+            merkle_root: String::new(),
+            canonicalization: String::new(),
+            metadata_hash: String::new(),
+            supersedes: String::new(),
+            encryption: String::new(),
+            hash: String::new(),
+            height: height,
+            author: author.hash()
+        };
+
+        header.hash = Block::from_header(&header, String::new()).unwrap().calculate_hash();
+        header
+    }
+
+    fn receipt(issuer: &Identity, author: &Identity) -> Receipt {
+        let block = header(&author, r#"{"Hello": "World!"}"#, "", 1);
+        let next = header(&author, r#"{"Hello": "Again!"}"#, block.hash.as_ref(), 2);
+        let proof = DocumentProofDto {
+            data_hash: block.data_hash.clone(),
+            block: block.hash.clone(),
+            position: 0,
+            merkle_root: block.data_hash.clone(),
+            path: vec![]
+        };
+
+        let content = ReceiptContentDto {
+            version: RECEIPT_VERSION,
+            consensus: ConsensusKind::ProofOfAuthority,
+            block: block,
+            author_key: author.public_key_to_hex().unwrap(),
+            data: None,
+            document: Some(proof),
+            headers: vec![next],
+            issuer: issuer.hash(),
+            issuer_key: issuer.public_key_to_hex().unwrap(),
+            issued_at: 0
+        };
+
+        let signature = issuer.key().sign(Receipt::message(&content).unwrap().as_bytes()).unwrap();
+        Receipt {
+            content: content,
+            signature: signature
+        }
+    }
+
+    fn authority_config(authority: &Identity, authorities: Vec<String>) -> NetworkConfig {
+        let signature = authority.key().sign(ProofOfAuthorityEngine::message("private", &authorities).as_bytes()).unwrap();
+
+        let mut config = NetworkConfig::default();
+        config.consensus = ConsensusConfig {
+            engine: ConsensusKind::ProofOfAuthority,
+            network_id: Some(String::from("private")),
+            authority_key: Some(authority.public_key_to_hex().unwrap()),
+            authorities: authorities,
+            signature: Some(signature.to_hex())
+        };
+
+        config
+    }
+
+    #[test]
+    fn receipt_should_be_verified_offline() {
+        let issuer = Identity::generate(2048).unwrap();
+        let author = Identity::generate(2048).unwrap();
+        let config = authority_config(&author, vec![author.hash()]);
+
+        assert!(receipt(&issuer, &author).verify(&config, &[]).is_ok());
+        assert!(receipt(&issuer, &author).verify(&config, &[issuer.hash()]).is_ok());
+    }
+
+    #[test]
+    fn receipt_should_only_be_trusted_by_the_verifier_rules() {
+        let issuer = Identity::generate(2048).unwrap();
+        let author = Identity::generate(2048).unwrap();
+        let config = authority_config(&author, vec![author.hash()]);
+
+        assert!(receipt(&issuer, &author).verify(&config, &[author.hash()]).is_err());
+        assert!(receipt(&issuer, &author).verify(&NetworkConfig::default(), &[]).is_err());
+        assert!(receipt(&issuer, &author).verify(&NetworkConfig::default(), &[issuer.hash()]).is_err());
+
+        let other = Identity::generate(2048).unwrap();
+        assert!(receipt(&issuer, &author).verify(&authority_config(&other, vec![other.hash()]), &[]).is_err());
+    }
+
+    #[test]
+    fn tampered_receipt_should_not_be_verified() {
+        let issuer = Identity::generate(2048).unwrap();
+        let author = Identity::generate(2048).unwrap();
+
+        let config = authority_config(&author, vec![author.hash()]);
+
+        let mut tampered = receipt(&issuer, &author);
+        tampered.content.issued_at = 1;
+        assert!(tampered.verify(&config, &[]).is_err());
+
+        let mut unlinked = receipt(&issuer, &author);
+        unlinked.content.headers[0].height = 3;
+        unlinked.signature = issuer.key().sign(Receipt::message(&unlinked.content).unwrap().as_bytes()).unwrap();
+        assert!(unlinked.verify(&config, &[]).is_err());
+    }
+}
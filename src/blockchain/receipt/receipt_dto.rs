@@ -0,0 +1,61 @@
+//! Receipt data transfer objects.
+
+use blockchain::block::BlockHeaderDto;
+use blockchain::consensus::ConsensusKind;
+use blockchain::document::DocumentProofDto;
+
+/// Version of the receipt format.
+pub const RECEIPT_VERSION: u32 = 1;
+
+/// Content of a proof-of-existence receipt, signed by the node issuing it.
+#[derive(
+    Debug, Clone,
+    Serialize, Deserialize
+)]
+pub struct ReceiptContentDto {
+    pub version: u32,
+
+    /// Consensus engine of the network, telling whether the Proof of Work of the headers is checked.
+    pub consensus: ConsensusKind,
+
+    /// Header of the block storing the document.
+    pub block: BlockHeaderDto,
+
+    /// Hexadecimal PEM-encoded public key of the block author.
+    pub author_key: String,
+
+    /// Data of a block created by an older node, whose signature covers its data instead of its
+    /// checksum.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+
+    /// Inclusion proof of the document in the block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document: Option<DocumentProofDto>,
+
+    /// Headers of the main chain blocks built on top of the block, up to the `HEAD` block.
+    pub headers: Vec<BlockHeaderDto>,
+
+    pub issuer: String,
+
+    /// Hexadecimal PEM-encoded public key of the issuing node identity.
+    pub issuer_key: String,
+    pub issued_at: u64
+}
+
+/// Signed proof-of-existence receipt:
+///
+/// ```json
+/// {
+///     "receipt": {receipt content},
+///     "signature": "{hexadecimal signature of the JSON receipt content by the issuer}"
+/// }
+/// ```
+#[derive(
+    Debug, Clone,
+    Serialize, Deserialize
+)]
+pub struct ReceiptDto {
+    pub receipt: ReceiptContentDto,
+    pub signature: String
+}
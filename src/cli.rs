@@ -12,6 +12,11 @@ use opts;
 
 use api;
 use blockchain::identity::identity_cli;
+use blockchain::chain::chain_cli;
+use blockchain::consensus::consensus_cli;
+use blockchain::receipt::receipt_cli;
+use blockchain::network::NetworkConfig;
+use blockchain::peer::peer_monitor;
 
 pub fn handle(matches: Matches) -> LocksidianResult<String> {
 
@@ -42,7 +47,15 @@ pub fn handle(matches: Matches) -> LocksidianResult<String> {
                 api::ServerConfig {
                     local_only: matches.opt_present("local"),
                     protected: matches.opt_present("protected"),
-                    entrypoint: matches.opt_str("entrypoint")
+                    entrypoint: matches.opt_str("entrypoint"),
+                    entrypoint_identity: matches.opt_str("entrypoint-identity"),
+                    network: NetworkConfig::load(matches.opt_str("network-config"))?,
+                    mining_threads: mining_threads(&matches)?,
+                    peer_timeout: seconds(&matches, "peer-timeout", peer_monitor::DEFAULT_PEER_TIMEOUT)?,
+                    peer_eviction: seconds(&matches, "peer-eviction", peer_monitor::DEFAULT_PEER_EVICTION)?,
+                    tls: matches.opt_present("tls") || matches.opt_present("tls-cert") || matches.opt_present("tls-key"),
+                    tls_cert: matches.opt_str("tls-cert"),
+                    tls_key: matches.opt_str("tls-key")
                 }
             ),
             None => Err(LocksidianError::new(opts::usage()))
@@ -55,7 +68,15 @@ pub fn handle(matches: Matches) -> LocksidianResult<String> {
                 api::ServerConfig {
                     local_only: false,
                     protected: matches.opt_present("protected"),
-                    entrypoint: matches.opt_str("entrypoint")
+                    entrypoint: matches.opt_str("entrypoint"),
+                    entrypoint_identity: matches.opt_str("entrypoint-identity"),
+                    network: NetworkConfig::load(matches.opt_str("network-config"))?,
+                    mining_threads: mining_threads(&matches)?,
+                    peer_timeout: seconds(&matches, "peer-timeout", peer_monitor::DEFAULT_PEER_TIMEOUT)?,
+                    peer_eviction: seconds(&matches, "peer-eviction", peer_monitor::DEFAULT_PEER_EVICTION)?,
+                    tls: matches.opt_present("tls") || matches.opt_present("tls-cert") || matches.opt_present("tls-key"),
+                    tls_cert: matches.opt_str("tls-cert"),
+                    tls_key: matches.opt_str("tls-key")
                 }
             ),
             None => Err(LocksidianError::new(opts::usage()))
@@ -86,8 +107,55 @@ pub fn handle(matches: Matches) -> LocksidianResult<String> {
             None => Err(LocksidianError::new(opts::usage()))
         }
     }
+	// Chain
+	else if matches.opt_present("verify-chain") {
+		chain_cli::verify_chain(
+			matches.opt_present("relink"),
+			NetworkConfig::load(matches.opt_str("network-config"))?
+		)
+	}
+	// Consensus
+	else if matches.opt_present("sign-authorities") {
+		match (matches.opt_str("sign-authorities"), matches.opt_str("network-id")) {
+			(Some(identities), Some(network_id)) => consensus_cli::sign_authorities(identities, network_id),
+			_ => Err(LocksidianError::new(opts::usage()))
+		}
+	}
+	// Receipt
+	else if matches.opt_present("verify-receipt") {
+		match matches.opt_str("verify-receipt") {
+			Some(path) => receipt_cli::verify_receipt(
+				path,
+				NetworkConfig::load(matches.opt_str("network-config"))?,
+				matches.opt_str("receipt-issuers")
+			),
+			None => Err(LocksidianError::new(opts::usage()))
+		}
+	}
 	// Unknown option
     else {
         Err(LocksidianError::new(opts::usage()))
     }
+}
+
+/// Parse the number of threads used to mine new blocks, defaulting to a single thread.
+fn mining_threads(matches: &Matches) -> LocksidianResult<usize> {
+	match matches.opt_str("mining-threads") {
+		Some(threads) => match threads.parse::<usize>() {
+			Ok(count) if count > 0 => Ok(count),
+			_ => Err(LocksidianError::new(format!("Invalid number of mining threads: {}", threads)))
+		},
+		None => Ok(1)
+	}
+}
+
+/// Parse a delay expressed in seconds, defaulting to `default`.
+fn seconds(matches: &Matches, name: &str, default: u64) -> LocksidianResult<u64> {
+	match matches.opt_str(name) {
+		Some(seconds) => match seconds.parse::<u64>() {
+			Ok(seconds) if seconds > 0 => Ok(seconds),
+			_ => Err(LocksidianError::new(format!("Invalid --{} delay: {}", name, seconds)))
+		},
+		None => Ok(default)
+	}
 }
\ No newline at end of file
@@ -125,10 +125,12 @@
 //! struct Peer {
 //!     identity: String,   // Unique identifier for this peer
 //!     key: PKey,          // RSA public key
-//!     address: String,    // HTTP(S) URL with port number
+//!     address: String,    // Host name or IP address with port number
+//!     scheme: String,     // `http` or `https`
 //!
 //!     last_sent: u64,     // Timestamp of the last time data were sent to this peer
-//!     last_recv: u64      // Timestamp of the last time data were received from this peer
+//!     last_recv: u64,     // Timestamp of the last time data were received from this peer
+//!     active: bool        // Is this peer responding (see below)?
 //! }
 //! ```
 //!
@@ -144,12 +146,28 @@
 //! network.
 //!
 //! The entrypoint will initialize a new `Peer` structure using the provided information, and check
-//! back the node's version of the Locksidian daemon. If the version matches, it sends back its own
-//! information.
+//! back the node's version of the Locksidian daemon. It then challenges the node to prove that it
+//! holds the private key behind the announced public key, by sending a random nonce to the
+//! `POST /peers/challenge` endpoint of the announced address:
+//!
+//! ```json
+//! {
+//!     "nonce": "{32 random bytes, hex-encoded}",
+//!     "registrar": "{identity hash of the entrypoint}"
+//! }
+//! ```
+//!
+//! The node answers with the signature of `CHALLENGE\n{nonce}\n{address}\n{registrar}` using its
+//! identity key, where `address` is the public address the node announces itself:
+//! `{"signature": "{hexadecimal signature}"}`. The challenge can therefore not be relayed to another
+//! node by a node announcing its identity. If the version matches and the signature is verified
+//! using the announced key, the node is registered and the entrypoint sends back its own information.
+//! The node registers its entrypoint, and every other peer, following the same steps.
 //!
 //! Finally, the node will gather all of its entrypoint's peers by sending a `GET /peers` request
 //! in order to create its own list of peers. For each of them, it will check their daemon version
-//! before registering them. This way, a single peer address is needed to join the peer-to-peer network.
+//! before registering them, and register itself onto them the same way it registered onto its
+//! entrypoint. This way, a single peer address is needed to join the peer-to-peer network.
 //!
 //! Once the registration process is completed, the node will check the current `HEAD` reference of
 //! its entrypoint, and sync down its blockchain if this block hash is unknown. This way, a node
@@ -161,6 +179,100 @@
 //! but will instead be the first `entrypoint` of a new `Locksidian` network! This way, you can
 //! easily create at will your own private network, hence your own private `Locksidian` blockchain.
 //!
+//! While the daemon is running, every peer is pinged every 30 seconds. A successful ping, as any
+//! other successful exchange with a peer (a block replicated to it or received from it), updates its
+//! `last_sent` and `last_recv` timestamps. A peer silent for more than `--peer-timeout` seconds
+//! (180 by default) is demoted: blocks are no longer propagated to it and it is no longer listed by
+//! `GET /peers`, until it responds again. A peer silent for more than `--peer-eviction` seconds (one
+//! day by default) is removed from the registry: the daemon refuses to start if this delay is shorter
+//! than the timeout. A ping is given up on if the peer does not accept the connection, or does not
+//! respond, within 5 seconds. The ping is a signed announcement of an empty inventory: a peer which
+//! evicted the node (e.g. after a network partition) rejects it, and the node then registers again
+//! onto this peer.
+//!
+//! ### Serving over HTTPS
+//!
+//! Start the daemon with the `--tls` flag in order to serve the REST API over HTTPS. At first start,
+//! a self-signed certificate is issued for the key of the active identity and stored next to the
+//! node's database, in the `locksidian.crt` file. It is regenerated whenever the active identity
+//! changes. A certificate issued by any other authority can be used instead, using the
+//! `--tls-cert={path}` and `--tls-key={path}` options, as long as it is issued for the key of the
+//! active identity.
+//!
+//! The scheme of every peer is stored in the peers registry, and advertised along with its address
+//! and key: `{"key": "{hex key}", "address": "{host}:{port}", "scheme": "https"}`. The certificates
+//! presented by the HTTPS peers are not checked against any certificate authority: a certificate is
+//! accepted if, and only if, it is issued for the key of the peer identity. Prefix the entrypoint
+//! address with its scheme when it serves HTTPS: `--entrypoint=https://{addr}`.
+//!
+//! As the identity of the entrypoint is not known before registering onto it, it is trusted on
+//! first use: any certificate is accepted from it until it proved the identity it answers the
+//! registration with, using the registration challenge. Whoever intercepts this first connection
+//! can thus pose as the entrypoint. Pin the identity of the entrypoint in order to only accept its
+//! own certificate, and its own registration: `--entrypoint-identity={identity hash}`. The daemon
+//! refuses to start if the TLS client cannot be configured.
+//!
+//! ### Network configuration
+//!
+//! The rules shared by all the nodes of a network can be specified at startup in a JSON file, using
+//! `locksidian --daemon={listen_addr} --network-config={path}`. Every missing field falls back to
+//! its default value:
+//!
+//! ```json
+//! {
+//!     "max_replication_depth": 5,     // Maximum number of blocks a fork block can lie behind HEAD
+//!     "consensus": {
+//!         "engine": "proof-of-work"   // Consensus engine of the network
+//!     },
+//!     "target_block_interval": 0,     // Expected seconds between two blocks (0: no retargeting)
+//!     "retarget_window": 10,          // Number of blocks between two difficulty retargetings
+//!     "max_batch_size": 64,           // Maximum number of documents stored in a single block
+//!     "gossip_fanout": 8,             // Number of peers a new block is announced to
+//!     "canonicalization": "raw",      // Canonicalisation of the documents: "raw" or "jcs"
+//!     "schemas": {},                  // Named JSON Schemas of the accepted documents
+//!     "legacy_signature_height": 0    // Height up to which the data signatures of older nodes are accepted
+//! }
+//! ```
+//!
+//! All the nodes of a network should be started using the same configuration file.
+//!
+//! ### Consensus engines
+//!
+//! By default, a `Locksidian` network uses the **Proof of Work** consensus engine described below:
+//! anyone can append a block to the chain, as long as its Proof of Work is solved.
+//!
+//! Private networks can instead use the **Proof of Authority** consensus engine: only the identities
+//! listed in an allow-list can append blocks to the chain, and no Proof of Work is required
+//! (the `nonce` of every block is `0`). When a block is created or replicated, the node checks that
+//! its author is part of the allow-list instead of checking its hash against a target. As every
+//! block represents the same amount of work, the heaviest branch is the longest one.
+//!
+//! The allow-list is signed by the *network authority*, using its identity, for a given network
+//! identifier: `locksidian --identity={authority_hash} --sign-authorities={hash_1},{hash_2}
+//! --network-id={network_id}` outputs the `consensus` section to copy into the network configuration
+//! file:
+//!
+//! ```json
+//! {
+//!     "engine": "proof-of-authority",
+//!     "network_id": {Identifier of the private network},
+//!     "authority_key": {Hexadecimal PEM-encoded public key of the network authority},
+//!     "authorities": [{Allowed identity hashes}],
+//!     "signature": {Signature of the network identifier followed by the allow-list, one per line}
+//! }
+//! ```
+//!
+//! The signature of the allow-list is verified at startup, and the node refuses to start if it does
+//! not match the `authority_key`. As the network identifier is signed along with the allow-list, an
+//! authority running several networks (e.g. staging and production) cannot have the allow-list of one
+//! network replayed on another one: each network must use its own identifier.
+//!
+//! The authority key is pinned in the chain itself: the `ORIGIN` block (the first block of the chain)
+//! must be authored by the network authority, which thus stores the first document of the network.
+//! A node configured with another `authority_key` rejects the `ORIGIN` block of the network, and
+//! refuses to start once this block is stored in its registry: editing the network configuration of
+//! a node does not allow it to accept another allow-list.
+//!
 //! ### Store a JSON document inside the blockchain
 //!
 //! The `Block` structure is defined as follows:
@@ -174,6 +286,10 @@
 //!     timestamp: u64,         // Creation timestamp of the block          |
 //!     nonce: u32,             // Proof of Work solution                   |
 //!     previous: String,       // Hash of the previous block in the chain  |
+//!     base_difficulty: u32,   // Retargeted PoW base difficulty (512)     |
+//!     merkle_root: String,    // Merkle root of the documents of a batch  |
+//!     canonicalization: String, // Canonicalisation mode of the documents |
+//!     metadata_hash: String,  // SHA512 metadata checksum of an anchor    |
 //!
 //!     hash: String,           // SHA512 Block Header checksum                                         | Block metadata
 //!     height: u64,            // Block index relative to the main chain                               |
@@ -190,18 +306,110 @@
 //! By default, the origin of the requests `POST`ed on this endpoints are not checked: anyone can
 //! submit a document to the blockchain!
 //!
+//! By default, a document is certified byte-for-byte: `{"a":1,"b":2}` and `{"b":2, "a":1}` are two
+//! different documents. When the `canonicalization` of the network is set to `jcs`, the submitted
+//! body must be a valid JSON document (or a `400 Bad Request` is returned) and it is canonicalised
+//! using the JSON Canonicalization Scheme (RFC 8785) before being hashed and stored: members sorted
+//! by key, no insignificant whitespace and numbers serialized as ECMAScript does. Duplicates are
+//! then detected on the canonical content. The mode is recorded in the `canonicalization` field of
+//! the Block Header (`jcs`, or empty for raw documents), so that anyone can reproduce the checksum
+//! of a document, and the nodes reject the blocks whose documents are not in canonical form, as well
+//! as the replicated blocks recording another mode than the one of the network (except for the
+//! anchors, whose metadata is never canonicalised).
+//!
+//! A private network may only accept certain document shapes, using the `schemas` of the network
+//! configuration: a JSON object mapping a name to a JSON Schema (e.g. `{"invoice": {...}}`). A
+//! document is then accepted if it matches at least one of the schemas, otherwise it is rejected
+//! with a `422 Unprocessable Entity` status listing the violations of every schema. The replicated
+//! blocks are checked against the same schemas, so that a misconfigured peer cannot slip other
+//! documents in. The supported JSON Schema keywords are listed in the `blockchain::document::schema`
+//! module, and a schema using an unsupported keyword (e.g. `$ref` or `pattern`) is rejected at
+//! startup.
+//!
+//! As mining a block may take a while, the document is not stored right away: it is persisted as a
+//! queued *job* and the node immediately answers with a `202 Accepted` status and the job identifier:
+//!
+//! ```json
+//! {
+//!     "job": "{id}"
+//! }
+//! ```
+//!
+//! A background worker then mines the blocks of the queued jobs. The progress of a job can be
+//! followed on the `GET /jobs/{id}` endpoint, whose `status` field is either `queued`, `mining`,
+//! `stored` (the `block` field then contains the hash of the generated block) or `failed` (the
+//! `error` field then contains the reason of the failure). The jobs are persisted next to the
+//! blocks, so that the queue survives a restart of the node: the jobs interrupted while mining are
+//! queued again.
+//!
+//! In order to increase the throughput of the node, the oldest queued jobs (up to the
+//! `max_batch_size` of the network configuration) are stored together in a single *batch block*:
+//! its `data` is the JSON array of the documents, which are committed in the Block Header through
+//! the `merkle_root` of their SHA512 checksums. The PoW size penalty of a batch block only depends
+//! on the size of its largest document.
+//!
+//! `GET /documents/{data_hash}/proof` returns the Merkle inclusion proof of a document, allowing
+//! anyone to prove that the document is committed in a block header without downloading the other
+//! documents of the block:
+//!
+//! ```json
+//! {
+//!     "data_hash": "{data_hash}",
+//!     "block": "{hash}",
+//!     "position": {position of the document in the block},
+//!     "merkle_root": "{merkle_root}",
+//!     "path": [{"hash": "{sibling checksum}", "side": "left|right"}]
+//! }
+//! ```
+//!
+//! Starting from the leaf node `sha512(0x00 + data_hash)`, each step of the `path` is combined with
+//! the current checksum (`sha512(0x01 + sibling + current)` for a `left` sibling,
+//! `sha512(0x01 + current + sibling)` otherwise) up to the `merkle_root`. The `0x00` and `0x01`
+//! prefixes are single bytes, followed by the hexadecimal checksums: they prevent an inner node from
+//! being passed off as a document checksum. For a block storing a single document, the `path` is
+//! empty and the `merkle_root` is the `data_hash` itself.
+//!
+//! `GET /documents/{data_hash}` returns the block storing a document, along with the position of
+//! the document in the block and the position of the block in the chain:
+//!
+//! ```json
+//! {
+//!     "data_hash": "{data_hash}",
+//!     "block": "{hash}",
+//!     "position": {position of the document in the block},
+//!     "height": {height of the block},
+//!     "timestamp": {timestamp of the block},
+//!     "author": "{identity hash of the block author}",
+//!     "main_chain": {false for an orphan block},
+//!     "confirmations": {number of main chain blocks from the block up to the HEAD block},
+//!     "supersedes": "{checksum of the superseded document, for a revision only}"
+//! }
+//! ```
+//!
+//! Clients that did not keep the checksum of a document can `POST /documents/verify` the raw
+//! document: it is hashed using SHA512 and the response tells whether, and where, it was certified:
+//!
+//! ```json
+//! {
+//!     "data_hash": "{data_hash}",
+//!     "certified": true,
+//!     "location": {location of the document, as returned by GET /documents/{data_hash}}
+//! }
+//! ```
+//!
 //! A new `Block` structure is initialized with the current `timestamp`, the JSON document as its
 //! `data` field and the document's SHA512 checksum as its `data_hash` field.
 //!
 //! The node will then browse the blockchain, searching for a block of the exact same checksum.
 //! If a block *does* exists with the exact same `data_hash` anywhere in the chain, the node will throw
-//! a `409 Conflict` and send the existing block `hash` in the HTTP response.
+//! a `409 Conflict` and send the existing block `hash` in the HTTP response (when the document is
+//! submitted, or later on, in the `error` field of its failed job).
 //!
 //! If there is no block with the same `data_hash` checksum in the chain, the following fields of the `Block`
 //! structure are initialized (with `HEAD` the Block representing the current head of the blockchain):
 //!
 //! ```text
-//! block.signature = {block.data_hash signed using the node's private key}
+//! block.signature = {"BLOCK\n" + block.data_hash + block.supersedes signed using the node's private key}
 //! block.previous = HEAD.hash
 //! block.next = (empty string)
 //!
@@ -236,6 +444,30 @@
 //!    the current `nonce` value is stored in the structure. If the PoW is not satisfied, the `nonce`
 //!    is incremented and the payload checksum is recomputed; loop until the PoW is solved.
 //!
+//!  - When the network configuration specifies a `target_block_interval`, the base `512` bits are
+//!    *retargeted* every `retarget_window` blocks, depending on the time it took to mine the
+//!    previous window (computed from the timestamps of its blocks): each time the blocks were mined
+//!    twice as fast (resp. slow) as expected, one bit is removed from (resp. added to) the base,
+//!    up to 4 bits per retargeting. The retargeted base is stored in the Block Header as its
+//!    `base_difficulty` field, which is only part of the SHA512 checksum once different from `512`.
+//!    As it only depends on the chain, any node recomputes the expected base difficulty of the
+//!    blocks it replicates and rejects the blocks that do not match it, or whose base is out of
+//!    the `[256, 512]` bounds. A block received before its ancestors (e.g. while syncing from the
+//!    `HEAD` block) is checked once they are received, and discarded if it does not match. As the
+//!    timestamps drive the retargeting, a replicated block stamped more than 10 minutes ahead of the
+//!    node's clock, or before its parent, is rejected as well. A node whose registry lacks the
+//!    ancestors needed to compute the base difficulty does not mine: the job fails instead.
+//!
+//!  - The nonce space can be split between several mining threads using the `--mining-threads`
+//!    option of the daemon: each thread tries its own share of the nonces, and the smallest valid
+//!    nonce is always kept, so that the mined block does not depend on the number of threads. If
+//!    all the `2^32` nonces were tried without solving the PoW, the block `timestamp` is increased
+//!    by one second and the nonce search starts over.
+//!
+//!  - Mining is cancelled as soon as a new HEAD block is stored in the node's registry (e.g.
+//!    received from a peer): the block being mined would be stale, so it is built again on top of
+//!    the new HEAD block before being mined again.
+//!
 //! Once the PoW is solved, the nonce is stored in the Block Header whose SHA512 checksum is computed
 //! and stored into the Block Metadata. Finally, the `next` field of the current HEAD block is updated:
 //!
@@ -265,6 +497,63 @@
 //! If there is no signature provided or if the signature does not match, a `403 Unauthorized` HTTP
 //! status will be returned to the client.
 //!
+//! The node administration endpoints (`POST /chain/verify` and `DELETE /blocks`) are *always* protected, whether
+//! the node runs in protected mode or not.
+//!
+//! The signed message is made of the request method, the request path and query string (e.g.
+//! `/blocks?dry_run=true`), the SHA512 checksum of the request body (of an empty body for the
+//! requests without body) and the UNIX timestamp of the request, one per line. The timestamp is sent
+//! in the `X-LS-TIMESTAMP` HTTP header:
+//!
+//! ```text
+//! {METHOD}
+//! {path?query}
+//! {sha512 body checksum}
+//! {timestamp}
+//! ```
+//!
+//! This is a breaking change for the clients of the protected endpoints written for the previous
+//! versions, which only signed the body checksum (or the path of a bodyless request): they must now
+//! sign the message above and send its timestamp in the `X-LS-TIMESTAMP` header, otherwise their
+//! requests are rejected. Signing the method and query string prevents an intercepted request from
+//! being altered (e.g. its `supersedes` or `recipients` query parameters) or sent to another endpoint.
+//!
+//! A request whose timestamp is more than 5 minutes away from the node's clock is rejected, as well as
+//! a request whose signature was already accepted: a signed request cannot be replayed.
+//!
+//! ### Node-to-node authentication
+//!
+//! The requests the nodes send to each other in order to register (`POST /peers/register`),
+//! announce blocks (`POST /blocks/inventory`) and replicate them (`PUT /blocks`) are signed using
+//! the key of the sender's `Identity`. The signature covers the method, the path, the SHA512
+//! checksum of the body, the timestamp and a random nonce of 32 hexadecimal bytes, one per line:
+//!
+//! ```text
+//! PUT
+//! /blocks
+//! {sha512 body checksum}
+//! {timestamp}
+//! {nonce}
+//! ```
+//!
+//! It is sent along with the request in the following HTTP headers:
+//!
+//! ```text
+//! X-LS-IDENTITY: {sender's identity hash}
+//! X-LS-TIMESTAMP: {timestamp}
+//! X-LS-NONCE: {nonce}
+//! X-LS-NODE-SIGNATURE: {hexadecimal signature}
+//! ```
+//!
+//! The receiving node verifies the signature using the key of the sender, as found in its peers
+//! registry. A registering node is not in the registry yet: its request must be signed using the
+//! key it registers. A request that is unsigned, signed by an unknown peer, or whose timestamp is
+//! more than 5 minutes away from the receiving node's clock is rejected with a `403 Forbidden`, as
+//! is a request reusing a nonce already used by the same sender within these 5 minutes.
+//!
+//! The identity a block or an inventory is recorded as received from is always the verified
+//! signer of the request, never a value of its payload.
+//!
 //! ### Block replication 101
 //!
 //! In order to replicate a block, the following fields of the `Block` structure are sent to the
@@ -283,6 +572,7 @@
 //!     "hash": {Block Header checksum}             | Block Metadata
 //!     "height": {Block height},                   |
 //!     "author": {Identity of the block's author}  |
+//!     "author_key": {Author's public key}         |
 //! }
 //! ```
 //!
@@ -290,6 +580,15 @@
 //! node's registry. If the recalculated size or hash does not match the provided values, a `400 Bad request`
 //! status will be thrown. If a match is found in the node's registry: `409 Conflict`.
 //!
+//! The block signature is then verified using the public key of its author. This key is either
+//! taken from the `author_key` field of the replication payload, or searched in the node's peer and
+//! identity registries. In both cases, its identity hash must match the block's `author`, otherwise
+//! the block is rejected with a `400 Bad request`. Once the block is stored, the verified key is
+//! kept in the node's author key registry, so that the block can still be served with its author
+//! key, verified and receipted after its author's node left the network. The blocks created by older
+//! nodes, whose signature covers the raw `data`, are only accepted up to the
+//! `legacy_signature_height` of the network configuration (`0`, i.e. never, by default).
+//!
 //! Then the **Proof of Work** is verified. Using the block's hash, the required number of zeros is
 //! recalculated. The provided `nonce` is appended to the data and their checksum is computed. If the
 //! resulting hash effectively starts with the correct number of zeros, the PoW is validated. Else,
@@ -301,7 +600,7 @@
 //! ```text
 //! block.next = (empty string)
 //! block.received_at = (current timestamp)
-//! block.received_from = (requester's identity hash)
+//! block.received_from = (identity hash of the request signer)
 //! ```
 //!
 //! The block referenced by the `previous` field will then be searched in the registry. If it is not
@@ -314,15 +613,182 @@
 //!
 //! If the `previous` block is found but its `next` field is **not** empty, then the `height` of the
 //! block is compared to the `height` of the current `HEAD`. If the new block is too far behind the
-//! `HEAD` (i.e. more than **5 blocks behind** the current `HEAD`), it is purely rejected and the
+//! `HEAD` (i.e. more than **5 blocks behind** the current `HEAD` by default, see the
+//! `max_replication_depth` network setting), it is purely rejected with a `400 Bad request` and the
 //! registry is *not* updated. Otherwise the new block is nevertheless stored in the registry but
-//! the `previous.next` hash is **not** updated, making it an *orphan block*. This approach is used
+//! the `previous.next` hash is **not** updated, and the block is flagged as an *orphan block*
+//! (as well as all the blocks that will later be built on top of it). This approach is used
 //! because if we consider that the new block has a chance to become part of the future main chain,
-//! it will automatically be linked to its previous block when a *prune* of the registry will happen
-//! in the future.
+//! it will automatically be promoted if its branch becomes the heaviest one (see the "Forks and
+//! chain reorganisation" section), or discarded when a *prune* of the registry will happen.
+//!
+//! Once the replication process is successful, the node gossips the block to its peers, to ensure
+//! that it reaches all of the network nodes without flooding them:
+//!
+//! - the hash of the block is announced to at most `gossip_fanout` (8 by default, see the network
+//!   configuration) randomly selected peers, using the `POST /blocks/inventory` endpoint:
+//!   `{"sender": "{identity}", "hashes": ["{hash}"]}`;
+//! - each of them answers with the hashes of the blocks missing from its registry:
+//!   `{"wanted": ["{hash}"]}`, and only these blocks are replicated to it using `PUT /blocks`;
+//! - a block is never announced to a peer known to have it, i.e. a peer that sent it or announced
+//!   it, or that it was already announced to;
+//! - a node forwards a block only once, when it stores it: a block already in its registry is
+//!   neither requested nor forwarded again.
+//!
+//! Newly mined blocks are gossiped the same way.
+//!
+//! ### Anchoring confidential documents
 //!
-//! Once the replication process is successful, the node will broadcast it to all of its peers, to
-//! ensure that it reaches all of the network nodes.
+//! Some documents must never leave the premises of their author. Such a document can be *anchored*
+//! by `POST`ing only its SHA512 checksum, along with optional JSON metadata (at most 1024 bytes), to
+//! the `/documents/anchor` endpoint:
+//!
+//! ```json
+//! {
+//!     "data_hash": "{sha512 checksum of the document}",
+//!     "metadata": {optional JSON metadata}
+//! }
+//! ```
+//!
+//! As for a regular document, a job is queued and its identifier is returned with a `202 Accepted`
+//! status. When the node runs in protected mode, the request must be signed by the node identity
+//! just like a `POST /blocks` request (see the "Protecting your node" section). The anchor is stored
+//! in its own block, whose `data_hash` is the provided checksum and whose `data` is the metadata (or
+//! an empty string). The metadata is committed in the Block Header through its `metadata_hash`
+//! checksum, and the PoW size penalty of an anchor is computed from a fixed size of 1024 bytes.
+//!
+//! Anchors are treated just like full documents by the verification endpoints: `GET
+//! /documents/{data_hash}` (whose `anchor` field is then `true`), `POST /documents/verify` with the
+//! confidential document as body, the Merkle proofs and the receipts.
+//!
+//! The checksum of an anchored document is computed by its author on the document as it is, even on
+//! a network canonicalising the documents: `POST /documents/verify` matches the anchors using the
+//! checksum of the raw body, before falling back to the checksum of its canonical form.
+//!
+//! ### Document revisions
+//!
+//! A revised document is submitted as a revision of its previous version using `POST
+//! /blocks?supersedes={data_hash}`. The superseded document must be stored in the registry, must
+//! have been stored by the identity of the node, and must not have been superseded yet: a document
+//! has at most one revision, so that its versions form a chain. Otherwise, a `400 Bad Request`
+//! status is returned.
+//!
+//! A revision is always stored in its own block, whose header records the checksum of the superseded
+//! document in a `supersedes` field. This field is covered by the block hash, and by the signature
+//! of the author (which then signs `data_hash + supersedes`). When replicating such a block, the
+//! nodes check that the superseded document was stored by the author of the block and has no other
+//! revision in the main chain. When the block is received before its ancestors (e.g. while syncing),
+//! the superseded document may be stored in one of them: the block is then discarded if the
+//! document is still unknown once its ancestors are linked to the chain.
+//!
+//! `GET /documents/{data_hash}/history` walks the revision chain of a document both ways, and
+//! returns the location of each version, from the original one to the latest one:
+//!
+//! ```json
+//! {
+//!     "data_hash": "{data_hash}",
+//!     "versions": [{location of a version, as returned by GET /documents/{data_hash}}]
+//! }
+//! ```
+//!
+//! Only the versions stored in the main chain by the author of the requested document are part of
+//! its history: the revisions stored in orphan blocks are ignored, and do not prevent a document from
+//! being superseded again.
+//!
+//! ### Querying documents
+//!
+//! The fields of the stored JSON documents are indexed when their block is saved, so that the
+//! documents can be searched by the value of their fields using `GET /documents?path={path}`. The
+//! path of a field uses a subset of the JSONPath syntax: `$.customer.id`, `$.items[0].sku` or
+//! `$["first name"]`. Without any other parameter, every document having the field is returned.
+//!
+//! | Parameter                             | Selected documents                                   |
+//! |---------------------------------------|------------------------------------------------------|
+//! | `eq={value}`                          | The field equals `value`                             |
+//! | `gt`, `gte`, `lt`, `lte` (`={value}`) | The field is greater / lower than `value`            |
+//! | `exists=false`                        | The documents lacking the field                      |
+//! | `author={identity}`                   | The documents stored by `identity`                   |
+//! | `since`, `until` (`={timestamp}`)     | The documents stored in a block created in between   |
+//! | `limit`, `offset`                     | Pagination (100 documents by default, 1000 at most)  |
+//!
+//! Numbers are compared numerically when the `value` is a number. Otherwise, only the string, boolean
+//! and null fields are compared, as text: objects and arrays never match a value. A document stored
+//! in several competing blocks is returned once, located through the first block storing it. For
+//! example, `GET /documents?path=$.customer.id&eq=42` returns:
+//!
+//! ```json
+//! {
+//!     "documents": [{location of a document, as returned by GET /documents/{data_hash}}]
+//! }
+//! ```
+//!
+//! Neither the anchored documents nor the encrypted documents are indexed, as their content cannot
+//! be read by the node. The documents stored before the upgrade of a node are indexed once, at the
+//! first startup of the upgraded node.
+//!
+//! ### Encrypting documents for recipients
+//!
+//! A sensitive document can be certified on a shared network while only its intended parties are
+//! able to read it. `POST /blocks?recipients={identity},{identity}` encrypts the document using a
+//! random AES-256-GCM key, which is then wrapped (RSA, OAEP padding) using the public key of
+//! each recipient. The public keys of the recipients must be known to the node, either as peers or
+//! as local identities. The block stores the resulting envelope in place of the document:
+//!
+//! ```json
+//! {
+//!     "encrypted": {
+//!         "cipher": "aes-256-gcm",
+//!         "iv": "{initialization vector}",
+//!         "tag": "{authentication tag}",
+//!         "ciphertext": "{encrypted document}",
+//!         "recipients": [{"identity": "{identity hash}", "key": "{wrapped key}"}]
+//!     }
+//! }
+//! ```
+//!
+//! The envelope is certified like any other document: its checksum is the block `data_hash`. An
+//! encrypted document is always stored in its own block, which records the cipher in the
+//! `encryption` field of its header. The network schemas are checked against the plaintext by the
+//! node the document is submitted to, but cannot be checked by the replicating nodes: they check
+//! instead that the document of a block flagged as encrypted is a well-formed envelope (supported
+//! cipher, hexadecimal values of the expected size, a single wrapped key per recipient). The other
+//! blocks are always checked against the schemas, even if their documents look like envelopes.
+//!
+//! A recipient retrieves the plaintext using the `GET /blocks/{hash}/decrypt` endpoint of a node
+//! whose active identity is one of the recipients. This endpoint is always protected: the request
+//! must be signed by the node identity in the `X-LS-SIGNATURE` and `X-LS-TIMESTAMP` headers.
+//! Whatever the reason a document cannot be decrypted, the same `409 Conflict` error is returned.
+//!
+//! ### Certifying binary files
+//!
+//! PDFs, images or signed archives are certified by `POST`ing them to the `/files` endpoint, either
+//! as an `application/octet-stream` body or as the file part of a `multipart/form-data` body (at
+//! most 64 MiB). The `name` and `media_type` query parameters can be used when the upload does not
+//! provide them.
+//!
+//! The bytes of the file are kept in the content-addressed store of the node (the `files` directory
+//! located next to its database, each file being named after its SHA512 checksum), while the block
+//! stores a record of the file:
+//!
+//! ```json
+//! {
+//!     "file": {
+//!         "hash": "{sha512 checksum of the file}",
+//!         "size": {size in bytes},
+//!         "media_type": "{media type}",
+//!         "name": "{optional file name}"
+//!     }
+//! }
+//! ```
+//!
+//! The job identifier is returned with a `202 Accepted` status, along with the record and its
+//! checksum. The record is certified like any other document, and its PoW penalty is increased by
+//! one bit each time the recorded file size doubles past 1 KiB, so that the expected mining work
+//! remains proportional to the size of the file. The recorded size is always the number of bytes
+//! received by the node: a file record `POST`ed to `/blocks` is rejected with a `400 Bad Request`.
+//!
+//! `GET /files/{data_hash}` streams the file certified by the record whose checksum is `data_hash`
+//! back, with its recorded media type and name. Only the node the file was uploaded to stores it.
 //!
 //! ### Retrieving a block
 //!
@@ -330,13 +796,76 @@
 //! on any of the available nodes (anyone can access the data of a block).
 //!
 //! A request on `GET /blocks/HEAD` will return the current head of the main chain. In order to do
-//! that, the block having the `MAX(height)` will be used. If more than one block are returned by
-//! this query, the real HEAD is the one which `previous` block's `next` field is itself
-//! (`HEAD.previous.next == HEAD`).
+//! that, the block having the greatest cumulative work will be used (see below). If more than one
+//! block match, the highest one is selected, and then the one having the smallest `hash`.
 //!
-//! ### Prune the blockchain!
+//! `GET /blocks/height/{n}` returns the main chain block located at the height `n`, while `GET
+//! /blocks` returns the hash of the current head along with a page of the main chain blocks,
+//! ordered by height:
+//!
+//! ```json
+//! {
+//!     "head": "{hash}",
+//!     "blocks": [{block, as returned by GET /blocks/{hash}}],
+//!     "next_height": {height of the first block of the next page, null on the last page}
+//! }
+//! ```
+//!
+//! The page starts at the `from_height` query parameter (`0` by default) and holds at most `limit`
+//! blocks (100 by default, 1000 at most). The `since` and `until` query parameters only select the
+//! blocks whose timestamp (a UNIX timestamp) is in this interval.
+//!
+//! ### Proof-of-existence receipts
+//!
+//! `GET /blocks/{hash}/receipt` returns a self-contained JSON receipt proving that a block is part
+//! of the main chain of the node. It holds the header of the block (without its data), the public
+//! key of its author, the inclusion proof of the document (selected using the `document={data_hash}`
+//! query parameter for a batch block) and the headers of all the blocks built on top of it, up to
+//! the current `HEAD`. The receipt is signed by the identity of the node.
+//!
+//! Such a receipt can be verified offline, without any access to the network, using
+//! `locksidian --verify-receipt {receipt.json} --network-config {network.json} --receipt-issuers {hash}`:
+//! the signatures of the node and of the block author, the inclusion proof, the linkage of the headers
+//! and their hash and Proof of Work are all checked. As the data of the block is not part of the
+//! receipt, the Proof of Work is checked against the target of the base difficulty.
 //!
-//! *Not Implemented Yet*
+//! The receipt is only trusted according to the rules of the verifier, never to the ones it carries:
+//! its consensus must match the one of the `--network-config` file, and its issuer must be one of
+//! the comma-separated `--receipt-issuers` identities. On a Proof of Authority network, the issuer
+//! may be omitted, as the authors of the block and of the following headers are then checked against
+//! the allow-list of the network configuration.
+//!
+//! ### Forks and chain reorganisation
+//!
+//! Two nodes mining at the same time will produce two blocks sharing the same `previous` block:
+//! the chain *forks* into two competing branches. Every node keeps track of all the branches it
+//! knows about, and the tip of each of them can be listed using `GET /chain/tips`.
+//!
+//! The **work** of a block is the expected number of hashes required to solve its Proof of Work,
+//! i.e. `2^512 / target`. The cumulative work of a block is the sum of its own work and of the work
+//! of all its ancestors, and is stored in the registry along with the block as soon as its
+//! `previous` block is known.
+//!
+//! The canonical `HEAD` is the block having the greatest cumulative work. Whenever a stored block
+//! makes another branch heavier than the current main chain, the node *reorganises* its registry:
+//! the `next` fields are relinked from the new `HEAD` back to the block where both branches fork.
+//! This way, all the nodes of the network eventually converge to the same main chain.
+//!
+//! ### Verifying the blockchain
+//!
+//! The whole registry of a node can be audited using `locksidian --verify-chain`, or by sending a
+//! `POST /chain/verify` request to a running node.
+//!
+//! The main chain is walked from its `ORIGIN` to its `HEAD` block: for each block, the `previous`
+//! and `next` links, the height continuity, the `data_hash` checksum, the block `hash`, the
+//! Proof of Work and the author signature are checked. A JSON report is then returned, containing
+//! the first broken block, every detected issue, the orphan blocks (i.e. not part of the main chain)
+//! and the dangling blocks (i.e. whose `previous` or `next` link points to an unknown block).
+//!
+//! The inconsistent `next` links of the main chain can be repaired by adding the `--relink` flag,
+//! or by sending the `{"relink": true}` JSON body to the `POST /chain/verify` endpoint.
+//!
+//! ### Prune the blockchain!
 //!
 //! In order to keep the blockchain consistent and to get rid of unused, useless or potentially
 //! altered, corrupted or falsified data, the blockchain (i.e. the entire registry of a node)
@@ -346,6 +875,25 @@
 //! discarded (i.e. removed from the node's registry). The **main chain** can be described as the
 //! succession of blocks respectively linked by their `previous` and `next` fields, that lie between
 //! the `HEAD` and `ORIGIN` blocks.
+//!
+//! The main chain is resolved by walking the `previous` links back from the `HEAD` block. Every
+//! other block (orphans and forks) is then removed, and the `next` links of the main chain are
+//! repaired, in a single transaction: if anything goes wrong, the registry is left untouched.
+//!
+//! Use `DELETE /blocks?dry_run=true` in order to get the list of the blocks that would be removed
+//! and relinked without altering the registry. The response has the following format:
+//!
+//! ```json
+//! {
+//!     "dry_run": true,
+//!     "kept": {Number of blocks in the main chain},
+//!     "removed": [{Hashes of the discarded blocks}],
+//!     "relinked": [{Hashes of the repaired main chain blocks}]
+//! }
+//! ```
+//!
+//! As this operation is destructive, the `DELETE /blocks` endpoint is always protected: the request
+//! must be signed by the node's identity (see the "Protecting your node" section).
 
 // Custom compiler lint checks
 #![forbid(
@@ -390,6 +938,7 @@ extern crate serde_json;
 extern crate serde_wat;
 
 extern crate hyper;
+extern crate hyper_openssl;
 extern crate igd;
 extern crate ipnetwork;
 
@@ -454,11 +1003,22 @@ const AUTHORS: &'static str = env!("CARGO_PKG_AUTHORS");
 /// * -d, --daemon LISTEN_ADDR: starts the Locksidian daemon service and HTTP REST API
 /// * -p, --protected: starts the Locksidian daemon in protected mode. Only available when running with --daemon
 /// * --local: starts the Locksidian daemon in local networking mode, thus deactivating the routable address gathering
+/// * --mining-threads THREADS: number of threads used to mine new blocks (defaults to 1). Only available when running with --daemon
+/// * --peer-timeout SECONDS: delay after which a silent peer is demoted (defaults to 180). Only available when running with --daemon
+/// * --peer-eviction SECONDS: delay after which a silent peer is evicted (defaults to 86400). Only available when running with --daemon
 /// * -i, --identity IDENTITY_HASH: switch the active node identity
 /// * --identity-new BIT_SIZE: generate a new identity (defaults to 4096 bit RSA keypair)
 /// * --identity-import PATH_TO_PEM_FILE: import the specified PEM-encoded RSA keypair as the new active identity
 /// * --identity-export IDENTITY_HASH: export the specified identity keypair to stdout
+/// * --verify-chain: verify the whole blockchain registry and output a detailed report
+/// * --relink: repair the inconsistent next links of the main chain. Only available when running with --verify-chain
+/// * --sign-authorities IDENTITY_HASHES: sign the comma-separated proof-of-authority allow-list using the active identity and output the consensus configuration. Requires --network-id
+/// * --network-id NETWORK_ID: identifier of the private network whose allow-list is signed. Only available when running with --sign-authorities
+/// * --verify-receipt PATH_TO_JSON_FILE: verify offline the specified proof-of-existence receipt
+/// * --receipt-issuers IDENTITY_HASHES: comma-separated identities trusted to issue receipts. Only available when running with --verify-receipt
 /// * -e, --entrypoint ADDRESS: specify the IP address or hotsname of the network entrypoint
+/// * --entrypoint-identity IDENTITY_HASH: identity of the network entrypoint, whose certificate is otherwise trusted on first use. Only available when running with --entrypoint
+/// * --network-config PATH_TO_JSON_FILE: JSON configuration file of the network joined by the node
 fn main() {
     match setup_registry() {
         Ok(()) => (),
@@ -490,6 +1050,8 @@ fn main() {
 fn setup_registry() -> LocksidianResult<()> {
     let connection = get_connection(database_path())?;
     setup_database(&connection)?;
+    blockchain::chain::chain_cli::index_work(&connection)?;
+    blockchain::chain::chain_cli::index_fields(&connection)?;
 
     Ok(())
 }
\ No newline at end of file
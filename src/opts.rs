@@ -18,13 +18,28 @@ fn build_opts() -> Options {
         .optopt("d", "daemon", "starts the Locksidian daemon service and HTTP REST API", "LISTEN_ADDR")
         .optflag("p", "protected", "starts the Locksidian daemon in protected mode. Only available when running with --daemon")
         .optflag("", "local", "starts the Locksidian daemon in local networking mode, thus deactivating the routable address gathering")
+        .optopt("", "mining-threads", "number of threads used to mine new blocks (defaults to 1). Only available when running with --daemon", "THREADS")
+        .optopt("", "peer-timeout", "delay after which a silent peer is demoted (defaults to 180). Only available when running with --daemon", "SECONDS")
+        .optopt("", "peer-eviction", "delay after which a silent peer is evicted (defaults to 86400). Only available when running with --daemon", "SECONDS")
+        .optflag("", "tls", "serves the REST API over HTTPS using a self-signed certificate issued for the active identity. Only available when running with --daemon")
+        .optopt("", "tls-cert", "serves the REST API over HTTPS using the specified PEM-encoded certificate, issued for the active identity key. Requires --tls-key", "PATH_TO_PEM_FILE")
+        .optopt("", "tls-key", "PEM-encoded private key of the certificate specified using --tls-cert", "PATH_TO_PEM_FILE")
         
         .optopt("i", "identity", "switch the active node identity", "IDENTITY_HASH")
         .optopt("", "identity-new", "generate a new identity (defaults to 4096 bit RSA keypair)", "BIT_SIZE")
         .optopt("", "identity-import", "import the specified PEM-encoded RSA keypair as the new active identity", "PATH_TO_PEM_FILE")
         .optopt("", "identity-export", "export the specified identity keypair to stdout", "IDENTITY_HASH")
         
-        .optopt("e", "entrypoint", "IP address or hotsname of the network entrypoint", "ADDRESS");
+        .optflag("", "verify-chain", "verify the whole blockchain registry and output a detailed report")
+        .optflag("", "relink", "repair the inconsistent next links of the main chain. Only available when running with --verify-chain")
+        .optopt("", "sign-authorities", "sign the comma-separated proof-of-authority allow-list using the active identity and output the consensus configuration. Requires --network-id", "IDENTITY_HASHES")
+        .optopt("", "network-id", "identifier of the private network whose allow-list is signed. Only available when running with --sign-authorities", "NETWORK_ID")
+        .optopt("", "verify-receipt", "verify offline the specified proof-of-existence receipt", "PATH_TO_JSON_FILE")
+        .optopt("", "receipt-issuers", "comma-separated identities trusted to issue receipts. Only available when running with --verify-receipt", "IDENTITY_HASHES")
+        
+        .optopt("e", "entrypoint", "IP address or hotsname of the network entrypoint, prefixed by https:// when it serves HTTPS", "ADDRESS")
+        .optopt("", "entrypoint-identity", "identity of the network entrypoint, whose certificate is otherwise trusted on first use. Only available when running with --entrypoint", "IDENTITY_HASH")
+        .optopt("", "network-config", "JSON configuration file of the network joined by the node", "PATH_TO_JSON_FILE");
 
     opts
 }
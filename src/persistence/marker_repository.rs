@@ -0,0 +1,58 @@
+//! Marker Repository module.
+//!
+//! Keep track of the one-off migrations of the registry (e.g. the indexing of the data persisted
+//! by a previous version of the node), so that they are not performed again at each startup.
+
+use persistence::prelude::*;
+
+table! {
+    markers(name) {
+        name -> VarChar,
+        created_at -> Integer,
+    }
+}
+
+/// Migration performed on the registry.
+#[derive(
+    Debug, Clone,
+    Queryable, Insertable, AsChangeset
+)]
+#[table_name = "markers"]
+pub struct MarkerEntity {
+    pub name: String,
+    pub created_at: i32
+}
+
+pub struct MarkerRepository<'pool> {
+    connection: &'pool SqliteConnection
+}
+
+impl<'pool> MarkerRepository<'pool> {
+
+    /// Instantiate a new `MarkerRepository` whose lifetime is bound to its pooled connection.
+    pub fn new(connection: &SqliteConnection) -> MarkerRepository {
+        MarkerRepository {
+            connection: connection
+        }
+    }
+
+    /// Has the migration identified by its `name` been performed?
+    pub fn is_set(&self, name: &str) -> bool {
+        self.get(&String::from(name)).is_some()
+    }
+
+    /// Record that the migration identified by its `name` has been performed.
+    pub fn set(&self, name: &str) -> LocksidianResult<()> {
+        let entity = MarkerEntity {
+            name: String::from(name),
+            created_at: ::blockchain::get_current_timestamp() as i32
+        };
+
+        match self.is_set(name) {
+            true => Ok(()),
+            false => self.save(&entity).map(|_| ())
+        }
+    }
+}
+
+crud_repository!(markers, MarkerEntity, String, name, MarkerRepository<'pool>);
@@ -11,9 +11,12 @@
 
 #[macro_use]
 mod macros;
+mod marker_repository;
 pub mod repository;
 pub mod prelude;
 
+pub use self::marker_repository::MarkerRepository;
+
 use error::*;
 
 use std::path::Path;
@@ -21,6 +24,7 @@ use std::fs;
 
 use diesel::prelude::*;
 use diesel::sqlite::SqliteConnection;
+use diesel::result::TransactionError;
 
 #[cfg(target_os = "windows")]
 pub fn database_path() -> String {
@@ -87,7 +91,16 @@ pub fn setup_database(connection: &SqliteConnection) -> LocksidianResult<()> {
             `next` TEXT DEFAULT "" NOT NULL,
             `author` TEXT NOT NULL,
             `received_at` INTEGER NOT NULL,
-            `received_from` TEXT NOT NULL
+            `received_from` TEXT NOT NULL,
+            `work` TEXT DEFAULT "" NOT NULL,
+            `chain_work` TEXT DEFAULT "" NOT NULL,
+            `orphan` BOOLEAN DEFAULT FALSE NOT NULL,
+            `base_difficulty` INTEGER DEFAULT 512 NOT NULL,
+            `merkle_root` TEXT DEFAULT "" NOT NULL,
+            `canonicalization` TEXT DEFAULT "" NOT NULL,
+            `metadata_hash` TEXT DEFAULT "" NOT NULL,
+            `supersedes` TEXT DEFAULT "" NOT NULL,
+            `encryption` TEXT DEFAULT "" NOT NULL
         );
 
         CREATE TABLE IF NOT EXISTS `peers` (
@@ -95,14 +108,118 @@ pub fn setup_database(connection: &SqliteConnection) -> LocksidianResult<()> {
             `key` BLOB NOT NULL,
             `address` TEXT NOT NULL,
             `last_sent` INTEGER DEFAULT 0,
-            `last_recv` INTEGER DEFAULT 0
+            `last_recv` INTEGER DEFAULT 0,
+            `active` BOOLEAN DEFAULT TRUE NOT NULL,
+            `scheme` TEXT DEFAULT "http" NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS `author_keys` (
+            `identity` TEXT PRIMARY KEY NOT NULL,
+            `key` BLOB NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS `markers` (
+            `name` TEXT PRIMARY KEY NOT NULL,
+            `created_at` INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS `inventory` (
+            `id` TEXT PRIMARY KEY NOT NULL,
+            `hash` TEXT NOT NULL,
+            `peer` TEXT NOT NULL,
+            `seen_at` INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS `documents` (
+            `data_hash` TEXT PRIMARY KEY NOT NULL,
+            `block` TEXT NOT NULL,
+            `position` INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS `fields` (
+            `id` TEXT PRIMARY KEY NOT NULL,
+            `data_hash` TEXT NOT NULL,
+            `block` TEXT NOT NULL,
+            `path` TEXT NOT NULL,
+            `kind` TEXT NOT NULL,
+            `value` TEXT NOT NULL,
+            `number` REAL,
+            `author` TEXT NOT NULL,
+            `timestamp` INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS `jobs` (
+            `id` TEXT PRIMARY KEY NOT NULL,
+            `data` TEXT NOT NULL,
+            `status` TEXT NOT NULL,
+            `block` TEXT DEFAULT "" NOT NULL,
+            `error` TEXT DEFAULT "" NOT NULL,
+            `anchor` TEXT DEFAULT "" NOT NULL,
+            `supersedes` TEXT DEFAULT "" NOT NULL,
+            `encryption` TEXT DEFAULT "" NOT NULL,
+            `created_at` INTEGER NOT NULL,
+            `updated_at` INTEGER NOT NULL
         )
+    "#) {
+        Ok(_) => (),
+        Err(err) => return Err(LocksidianError::from_err(err))
+    };
+
+    add_column(&connection, "blocks", r#"`work` TEXT DEFAULT "" NOT NULL"#)?;
+    add_column(&connection, "blocks", r#"`chain_work` TEXT DEFAULT "" NOT NULL"#)?;
+    add_column(&connection, "blocks", r#"`orphan` BOOLEAN DEFAULT FALSE NOT NULL"#)?;
+    add_column(&connection, "blocks", r#"`base_difficulty` INTEGER DEFAULT 512 NOT NULL"#)?;
+    add_column(&connection, "blocks", r#"`merkle_root` TEXT DEFAULT "" NOT NULL"#)?;
+    add_column(&connection, "blocks", r#"`canonicalization` TEXT DEFAULT "" NOT NULL"#)?;
+    add_column(&connection, "blocks", r#"`metadata_hash` TEXT DEFAULT "" NOT NULL"#)?;
+    add_column(&connection, "blocks", r#"`supersedes` TEXT DEFAULT "" NOT NULL"#)?;
+    add_column(&connection, "blocks", r#"`encryption` TEXT DEFAULT "" NOT NULL"#)?;
+    add_column(&connection, "peers", r#"`active` BOOLEAN DEFAULT TRUE NOT NULL"#)?;
+    add_column(&connection, "peers", r#"`scheme` TEXT DEFAULT "http" NOT NULL"#)?;
+    add_column(&connection, "jobs", r#"`anchor` TEXT DEFAULT "" NOT NULL"#)?;
+    add_column(&connection, "jobs", r#"`supersedes` TEXT DEFAULT "" NOT NULL"#)?;
+    add_column(&connection, "jobs", r#"`encryption` TEXT DEFAULT "" NOT NULL"#)?;
+
+    match connection.execute(r#"
+        CREATE INDEX IF NOT EXISTS `blocks_previous_index` ON `blocks` (`previous`);
+        CREATE INDEX IF NOT EXISTS `blocks_chain_work_index` ON `blocks` (`chain_work`);
+        CREATE INDEX IF NOT EXISTS `blocks_supersedes_index` ON `blocks` (`supersedes`);
+        CREATE INDEX IF NOT EXISTS `documents_block_index` ON `documents` (`block`);
+        CREATE INDEX IF NOT EXISTS `fields_value_index` ON `fields` (`path`, `value`);
+        CREATE INDEX IF NOT EXISTS `fields_number_index` ON `fields` (`path`, `number`);
+        CREATE INDEX IF NOT EXISTS `fields_timestamp_index` ON `fields` (`path`, `timestamp`);
+        CREATE INDEX IF NOT EXISTS `fields_block_index` ON `fields` (`block`);
+        CREATE INDEX IF NOT EXISTS `inventory_seen_at_index` ON `inventory` (`seen_at`);
+        CREATE INDEX IF NOT EXISTS `jobs_status_index` ON `jobs` (`status`, `created_at`);
     "#) {
         Ok(_) => Ok(()),
         Err(err) => Err(LocksidianError::from_err(err))
     }
 }
 
+/// Add a new column to a table created by a previous version of the node. Nothing is done if the
+/// column already exists.
+fn add_column(connection: &SqliteConnection, table: &str, definition: &str) -> LocksidianResult<()> {
+    match connection.execute(format!("ALTER TABLE `{}` ADD COLUMN {};", table, definition).as_ref()) {
+        Ok(_) => Ok(()),
+        Err(err) => match err.to_string().contains("duplicate column name") {
+            true => Ok(()),
+            false => Err(LocksidianError::from_err(err))
+        }
+    }
+}
+
+/// Execute the given closure inside of a transaction: if an error is returned, every change made
+/// to the persistence context is rolled back.
+pub fn transaction<T, F>(connection: &SqliteConnection, f: F) -> LocksidianResult<T>
+    where F: FnOnce() -> LocksidianResult<T> {
+    match connection.transaction(f) {
+        Ok(result) => Ok(result),
+        Err(TransactionError::UserReturnedError(err)) => Err(err),
+        Err(TransactionError::CouldntCreateTransaction(err)) => Err(LocksidianError::from_err(err))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use persistence::prelude::*;
@@ -0,0 +1,80 @@
+//! AES-256-GCM symmetric encryption, used to encrypt documents for a set of recipients.
+//!
+//! A random key is generated for every encrypted document. The key is then wrapped using the RSA
+//! public key of each recipient, so that only them are able to decrypt the document.
+
+use error::*;
+
+use openssl::rand::rand_bytes;
+use openssl::symm::{Cipher, encrypt_aead, decrypt_aead};
+
+/// Name of the cipher, as recorded along with the encrypted documents.
+pub const CIPHER: &'static str = "aes-256-gcm";
+
+/// Key size: 256 bits = 32 bytes.
+pub const KEY_SIZE: usize = 32;
+
+/// Initialization vector size: 96 bits = 12 bytes, as recommended for GCM.
+pub const IV_SIZE: usize = 12;
+
+/// Authentication tag size: 128 bits = 16 bytes.
+pub const TAG_SIZE: usize = 16;
+
+/// Generate `size` cryptographically secure random bytes, used as a key or an initialization vector.
+pub fn random_bytes(size: usize) -> LocksidianResult<Vec<u8>> {
+    let mut buffer = vec![0; size];
+
+    match rand_bytes(&mut buffer) {
+        Ok(_) => Ok(buffer),
+        Err(err) => Err(LocksidianError::from_err(err))
+    }
+}
+
+/// Encrypt the `plaintext` using the provided `key` and `iv`. Returns the ciphertext along with its
+/// authentication tag.
+pub fn encrypt(key: &[u8], iv: &[u8], plaintext: &[u8]) -> LocksidianResult<(Vec<u8>, Vec<u8>)> {
+    let mut tag = vec![0; TAG_SIZE];
+
+    match encrypt_aead(Cipher::aes_256_gcm(), key, Some(iv), &[], plaintext, &mut tag) {
+        Ok(ciphertext) => Ok((ciphertext, tag)),
+        Err(err) => Err(LocksidianError::from_err(err))
+    }
+}
+
+/// Decrypt the `ciphertext` using the provided `key` and `iv`. Returns an error if the ciphertext
+/// does not match its authentication `tag`.
+pub fn decrypt(key: &[u8], iv: &[u8], ciphertext: &[u8], tag: &[u8]) -> LocksidianResult<Vec<u8>> {
+    match decrypt_aead(Cipher::aes_256_gcm(), key, Some(iv), &[], ciphertext, tag) {
+        Ok(plaintext) => Ok(plaintext),
+        Err(err) => Err(LocksidianError::from_err(err))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_encrypt_and_decrypt_message() {
+        let key = random_bytes(KEY_SIZE).unwrap();
+        let iv = random_bytes(IV_SIZE).unwrap();
+        let message = b"Hello World!";
+
+        let (ciphertext, tag) = encrypt(&key, &iv, message).unwrap();
+        assert!(ciphertext.as_slice() != &message[..]);
+
+        let decrypted = decrypt(&key, &iv, &ciphertext, &tag).unwrap();
+        assert_eq!(decrypted.as_slice(), &message[..]);
+    }
+
+    #[test]
+    fn should_not_decrypt_a_tampered_message() {
+        let key = random_bytes(KEY_SIZE).unwrap();
+        let iv = random_bytes(IV_SIZE).unwrap();
+
+        let (mut ciphertext, tag) = encrypt(&key, &iv, b"Hello World!").unwrap();
+        ciphertext[0] ^= 1;
+
+        assert!(decrypt(&key, &iv, &ciphertext, &tag).is_err());
+    }
+}
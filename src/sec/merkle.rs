@@ -0,0 +1,184 @@
+//! Merkle trees, used to commit to a list of documents through a single checksum.
+//!
+//! The leaves of the tree are the SHA512 checksums of the documents. Each leaf node is the SHA512
+//! checksum of the `0x00` byte followed by the hexadecimal checksum of its document, and each parent
+//! node is the SHA512 checksum of the `0x01` byte followed by the concatenation of the hexadecimal
+//! checksums of its two children, the last node of an odd level being paired with itself.
+//!
+//! The prefixes separate the leaves from the inner nodes: an inner node can never be passed off as
+//! the checksum of a document.
+
+use sec::sha::sha512;
+
+/// Prefix of the hashed leaf nodes.
+const LEAF_PREFIX: u8 = 0x00;
+
+/// Prefix of the hashed inner nodes.
+const NODE_PREFIX: u8 = 0x01;
+
+/// Side of the sibling node combined with the current node at a given level of the tree.
+#[derive(
+    Debug, Clone, Copy, PartialEq,
+    Serialize, Deserialize
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Left,
+    Right
+}
+
+/// Single step of a Merkle inclusion path.
+#[derive(
+    Debug, Clone, PartialEq,
+    Serialize, Deserialize
+)]
+pub struct MerkleStep {
+    pub hash: String,
+    pub side: Side
+}
+
+/// Compute the Merkle root of the given leaves, or `None` if there is no leaf.
+pub fn merkle_root(leaves: &[String]) -> Option<String> {
+    let mut level: Vec<String> = leaf_level(leaves);
+
+    while level.len() > 1 {
+        level = parent_level(&level);
+    }
+
+    level.pop()
+}
+
+/// Compute the inclusion path of the leaf at the given `index`, from the leaf up to the root.
+/// Returns `None` if there is no such leaf.
+pub fn merkle_proof(leaves: &[String], index: usize) -> Option<Vec<MerkleStep>> {
+    if index >= leaves.len() {
+        return None;
+    }
+
+    let mut path: Vec<MerkleStep> = Vec::new();
+    let mut level: Vec<String> = leaf_level(leaves);
+    let mut index = index;
+
+    while level.len() > 1 {
+        let step = match index % 2 {
+            0 => MerkleStep {
+                hash: level.get(index + 1).unwrap_or(&level[index]).clone(),
+                side: Side::Right
+            },
+            _ => MerkleStep {
+                hash: level[index - 1].clone(),
+                side: Side::Left
+            }
+        };
+
+        path.push(step);
+        level = parent_level(&level);
+        index /= 2;
+    }
+
+    Some(path)
+}
+
+/// Returns `true` if the inclusion `path` of the `leaf` leads to the Merkle `root`.
+pub fn verify_merkle_proof(leaf: &str, path: &[MerkleStep], root: &str) -> bool {
+    let computed = path.iter().fold(hash_leaf(leaf), |node, step| match step.side {
+        Side::Left => combine(&step.hash, &node),
+        Side::Right => combine(&node, &step.hash)
+    });
+
+    computed == root
+}
+
+/// Compute the leaf nodes of the given leaves.
+fn leaf_level(leaves: &[String]) -> Vec<String> {
+    leaves.iter().map(|leaf| hash_leaf(leaf)).collect()
+}
+
+/// Compute the leaf node of a document checksum.
+fn hash_leaf(leaf: &str) -> String {
+    prefixed_hash(LEAF_PREFIX, leaf)
+}
+
+/// Compute the parent level of the given tree level.
+fn parent_level(level: &[String]) -> Vec<String> {
+    level.chunks(2).map(|pair| match pair.len() {
+        2 => combine(&pair[0], &pair[1]),
+        _ => combine(&pair[0], &pair[0])
+    }).collect()
+}
+
+/// Compute the parent node of two sibling nodes.
+fn combine(left: &str, right: &str) -> String {
+    prefixed_hash(NODE_PREFIX, format!("{}{}", left, right).as_ref())
+}
+
+/// Compute the SHA512 checksum of the `prefix` byte followed by the `content`.
+fn prefixed_hash(prefix: u8, content: &str) -> String {
+    let mut bytes = vec![prefix];
+    bytes.extend_from_slice(content.as_bytes());
+
+    sha512(&bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sec::sha::sha512;
+
+    fn leaves(count: usize) -> Vec<String> {
+        (0..count).map(|index| sha512(format!("document {}", index).as_bytes())).collect()
+    }
+
+    #[test]
+    fn root_of_a_single_leaf_should_be_the_leaf_node() {
+        let leaves = leaves(1);
+        assert_eq!(merkle_root(&leaves), Some(hash_leaf(&leaves[0])));
+        assert_eq!(merkle_root(&[]), None);
+    }
+
+    #[test]
+    fn root_should_combine_the_leaves_pairwise() {
+        let leaves = leaves(3);
+        let node = |left: &str, right: &str| sha512(format!("\x01{}{}", left, right).as_bytes());
+        let leaf = |leaf: &str| sha512(format!("\x00{}", leaf).as_bytes());
+
+        let left = node(&leaf(&leaves[0]), &leaf(&leaves[1]));
+        let right = node(&leaf(&leaves[2]), &leaf(&leaves[2]));
+
+        assert_eq!(merkle_root(&leaves), Some(node(&left, &right)));
+    }
+
+    #[test]
+    fn proof_of_every_leaf_should_lead_to_the_root() {
+        for count in 1..12 {
+            let leaves = leaves(count);
+            let root = merkle_root(&leaves).unwrap();
+
+            for (index, leaf) in leaves.iter().enumerate() {
+                let path = merkle_proof(&leaves, index).unwrap();
+                assert!(verify_merkle_proof(leaf, &path, &root));
+            }
+
+            assert!(merkle_proof(&leaves, count).is_none());
+        }
+    }
+
+    #[test]
+    fn proof_should_not_be_valid_for_another_leaf() {
+        let leaves = leaves(5);
+        let root = merkle_root(&leaves).unwrap();
+        let path = merkle_proof(&leaves, 2).unwrap();
+
+        assert!(!verify_merkle_proof(&leaves[3], &path, &root));
+    }
+
+    #[test]
+    fn inner_node_should_not_be_proven_as_a_leaf() {
+        let leaves = leaves(4);
+        let root = merkle_root(&leaves).unwrap();
+        let inner = combine(&hash_leaf(&leaves[0]), &hash_leaf(&leaves[1]));
+        let path = merkle_proof(&leaves, 0).unwrap();
+
+        assert!(!verify_merkle_proof(&inner, &path[1..], &root));
+    }
+}
@@ -2,5 +2,7 @@
 
 pub mod sha;
 pub mod rsa;
+pub mod aes;
 pub mod hex;
-pub mod ripemd;
\ No newline at end of file
+pub mod ripemd;
+pub mod merkle;
\ No newline at end of file
@@ -71,7 +71,7 @@ use openssl::rsa;
 use openssl::pkey::PKey;
 use openssl::sign::Signer;
 use openssl::sign::Verifier;
-use openssl::rsa::PKCS1_PADDING;
+use openssl::rsa::PKCS1_OAEP_PADDING;
 use openssl::hash::MessageDigest;
 
 /// `RSA` operational structure.
@@ -136,14 +136,14 @@ impl Rsa {
         size % 1024 == 0 && size >= 2048
     }
 
-    /// Encrypt the provided `message` slice using the RSA public key.
+    /// Encrypt the provided `message` slice using the RSA public key, with the OAEP padding scheme.
     pub fn encrypt(&self, message: &[u8]) -> LocksidianResult<Vec<u8>> {
         match self.pkey.rsa() {
             Ok(rsa) => {
                 let buffer_size = rsa.size();
                 let mut buffer: Vec<u8> = vec![0; buffer_size];
 
-                match rsa.public_encrypt(message, &mut buffer, PKCS1_PADDING) {
+                match rsa.public_encrypt(message, &mut buffer, PKCS1_OAEP_PADDING) {
                     Ok(length) => {
                         buffer.resize(length, 0);
                         Ok(buffer)
@@ -155,14 +155,14 @@ impl Rsa {
         }
     }
 
-    /// Decrypt the provided `message` slice using the RSA private key.
+    /// Decrypt the provided `message` slice using the RSA private key, with the OAEP padding scheme.
     pub fn decrypt(&self, message: &[u8]) -> LocksidianResult<Vec<u8>> {
         match self.pkey.rsa() {
             Ok(rsa) => {
                 let buffer_size = rsa.size();
                 let mut buffer: Vec<u8> = vec![0; buffer_size];
 
-                match rsa.private_decrypt(message, &mut buffer, PKCS1_PADDING) {
+                match rsa.private_decrypt(message, &mut buffer, PKCS1_OAEP_PADDING) {
                     Ok(length) => {
                         buffer.resize(length, 0);
                         Ok(buffer)